@@ -2,20 +2,35 @@ import axios from "axios";
 
 const BASE_URL = "/api/v1/accounts";
 
-export const getAccounts = async () => {
-  const response = await axios.get(BASE_URL);
-  return response.data;
+// Key baked in at build time; the backend rejects requests without one
+const API_KEY = process.env.REACT_APP_API_KEY;
+if (API_KEY) {
+  axios.defaults.headers.common.Authorization = `Bearer ${API_KEY}`;
+}
+
+// Follow `next_cursor` until every page of a listing has been read
+const fetchAllPages = async (url, params = {}) => {
+  const items = [];
+  let cursor;
+  do {
+    const response = await axios.get(url, {
+      params: { ...params, limit: 200, cursor },
+    });
+    items.push(...response.data.items);
+    cursor = response.data.next_cursor;
+  } while (cursor);
+  return items;
 };
 
+export const getAccounts = async () => fetchAllPages(BASE_URL);
+
 export const getAccount = async (id) => {
   const response = await axios.get(`${BASE_URL}/${id}`);
   return response.data;
 };
 
-export const getAccountBatches = async (accountId) => {
-  const response = await axios.get(`${BASE_URL}/${accountId}/batches`);
-  return response.data;
-};
+export const getAccountBatches = async (accountId, filters = {}) =>
+  fetchAllPages(`${BASE_URL}/${accountId}/batches`, filters);
 
 export const createAccount = async (account) => {
   const response = await axios.post(BASE_URL, account);
@@ -50,9 +65,11 @@ export const subscribeToAccountEvents = (
   onAccountDeleted,
   onBatchCreated,
   onPing,
-  onBetStatusUpdated
+  onBetStatusUpdated,
+  onResync
 ) => {
-  const SSE_URL = "/sse";
+  // EventSource cannot send headers, so the key goes in the query string
+  const SSE_URL = API_KEY ? `/sse?api_key=${encodeURIComponent(API_KEY)}` : "/sse";
   const eventSource = new EventSource(SSE_URL);
 
   eventSource.onopen = () => {
@@ -109,6 +126,27 @@ export const subscribeToAccountEvents = (
     }
   });
 
+  // A settled bet carries its result and payout; update it in place
+  eventSource.addEventListener("bet_settled", (event) => {
+    try {
+      const payload = JSON.parse(event.data);
+      onBetStatusUpdated?.(payload.bet);
+    } catch (err) {
+      console.error("Failed to parse bet_settled event:", err);
+    }
+  });
+
+  // The browser resends the last event id on reconnect; if the server can no
+  // longer replay from it, local state must be reloaded
+  eventSource.addEventListener("resync_required", () => {
+    onResync?.();
+  });
+
+  // Live events were dropped because this client fell behind
+  eventSource.addEventListener("lagged", () => {
+    onResync?.();
+  });
+
   eventSource.addEventListener("ping", (event) => {
     onPing?.(event.data);
   });
@@ -29,12 +29,15 @@ function formatSelection(selection) {
 }
 
 function BetStatusSelector({ bet, onChange }) {
+  // Only pending bets can be settled; the API refuses any other change
+  const settled = bet.status !== "pending";
   return (
     <div className="flex justify-center items-center space-x-4">
       <button
         onClick={() => onChange("successful")}
+        disabled={settled}
         title="Mark as Successful"
-        className={`hover:text-green-400 ${
+        className={`hover:text-green-400 disabled:cursor-not-allowed disabled:hover:text-current ${
           bet.status === "successful" ? "text-green-500" : "text-gray-400"
         }`}
       >
@@ -42,8 +45,9 @@ function BetStatusSelector({ bet, onChange }) {
       </button>
       <button
         onClick={() => onChange("failed")}
+        disabled={settled}
         title="Mark as Failed"
-        className={`hover:text-red-400 ${
+        className={`hover:text-red-400 disabled:cursor-not-allowed disabled:hover:text-current ${
           bet.status === "failed" ? "text-red-500" : "text-gray-400"
         }`}
       >
@@ -62,6 +66,8 @@ export default function AccountBatchesUI() {
   const [selectedBatchId, setSelectedBatchId] = useState(null);
   const [error, setError] = useState(null);
   const [sidebarOpen, setSidebarOpen] = useState(false);
+  // Bumped to refetch the current account after a missed-events resync
+  const [reloadKey, setReloadKey] = useState(0);
 
   const accountIdRef = useRef(accountId);
   const selectedBatchIdRef = useRef(selectedBatchId);
@@ -162,6 +168,14 @@ export default function AccountBatchesUI() {
               : batch
           )
         );
+      },
+      async () => {
+        try {
+          setAccounts(await getAccounts());
+          setReloadKey((key) => key + 1);
+        } catch {
+          setError("Failed to reload accounts");
+        }
       }
     );
 
@@ -182,7 +196,7 @@ export default function AccountBatchesUI() {
       try {
         const [accountData, batchesData] = await Promise.all([
           getAccount(accountId),
-          getAccountBatches(accountId),
+          getAccountBatches(accountId, { completed: false }),
         ]);
         const active = batchesData.filter((batch) => !batch.completed);
         setAccount(accountData);
@@ -195,11 +209,9 @@ export default function AccountBatchesUI() {
       }
     };
     fetchData();
-  }, [accountId]);
+  }, [accountId, reloadKey]);
 
   const formatDate = (d) => new Date(d).toLocaleString("en-US");
-  const calculateTotalStake = (bets) =>
-    bets.reduce((sum, bet) => sum + (bet.stake || 0), 0).toFixed(2);
 
   const getStatusCounts = (bets = []) =>
     bets.reduce(
@@ -382,8 +394,8 @@ const handleSubmitBatch = async () => {
                       {selectedBatch.bets?.length || 0}
                     </div>
                     <div>
-                      <DollarSign className="inline w-4 h-4 mr-1" /> Total Stake: $
-                      {calculateTotalStake(selectedBatch.bets)}
+                      <DollarSign className="inline w-4 h-4 mr-1" /> Total Stake:{" "}
+                      {selectedBatch.total_stake} {selectedBatch.currency}
                     </div>
                   </div>
 
@@ -458,8 +470,8 @@ const handleSubmitBatch = async () => {
                               </div>
                             </details>
                           </td>
-                          <td className="px-4 py-2">${bet.stake}</td>
-                          <td className="px-4 py-2">${bet.cost}</td>
+                          <td className="px-4 py-2">{bet.stake} {bet.currency}</td>
+                          <td className="px-4 py-2">{bet.cost} {bet.currency}</td>
                           <td className="px-4 py-2">{bet.status}</td>
                           <td className="px-4 py-2">
                             <BetStatusSelector
@@ -3,6 +3,9 @@
 BASE_URL="http://backend:3001/api/v1"
 #BASE_URL="http://localhost:3001/api/v1"
 
+# Seeding needs a key that may create accounts; the admin key always can
+AUTH_HEADER="Authorization: Bearer ${ADMIN_API_KEY:?ADMIN_API_KEY must be set}"
+
 echo "🎲 Starting data population for Betting API..."
 echo ""
 
@@ -33,11 +36,12 @@ info() {
 info "Creating accounts..."
 
 # Account 1: nimesh
-ACCOUNT1=$(curl -s -X POST "$BASE_URL/accounts" \
+ACCOUNT1=$(curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "nimesh",
-    "hostname": "host1.betting.local"
+    "hostname": "host1.betting.local",
+    "currency": "AUD"
   }')
 
 if [ $? -eq 0 ]; then
@@ -49,11 +53,12 @@ else
 fi
 
 # Account 2: ganga
-ACCOUNT2=$(curl -s -X POST "$BASE_URL/accounts" \
+ACCOUNT2=$(curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "ganga",
-    "hostname": "host2.betting.local"
+    "hostname": "host2.betting.local",
+    "currency": "AUD"
   }')
 
 if [ $? -eq 0 ]; then
@@ -65,11 +70,12 @@ else
 fi
 
 # Account 3: rajesh
-ACCOUNT3=$(curl -s -X POST "$BASE_URL/accounts" \
+ACCOUNT3=$(curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "rajesh",
-    "hostname": "host3.betting.local"
+    "hostname": "host3.betting.local",
+    "currency": "AUD"
   }')
 
 if [ $? -eq 0 ]; then
@@ -81,11 +87,12 @@ else
 fi
 
 # Account 4: priya
-ACCOUNT4=$(curl -s -X POST "$BASE_URL/accounts" \
+ACCOUNT4=$(curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "priya",
-    "hostname": "host4.betting.local"
+    "hostname": "host4.betting.local",
+    "currency": "NZD"
   }')
 
 if [ $? -eq 0 ]; then
@@ -97,11 +104,12 @@ else
 fi
 
 # Account 5: suresh
-ACCOUNT5=$(curl -s -X POST "$BASE_URL/accounts" \
+ACCOUNT5=$(curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "suresh",
-    "hostname": "host5.betting.local"
+    "hostname": "host5.betting.local",
+    "currency": "NZD"
   }')
 
 if [ $? -eq 0 ]; then
@@ -121,7 +129,7 @@ info "Creating batches..."
 
 # ===== ACCOUNT 1 BATCHES =====
 # Batch 1 - Race 1 - WIN bets (single horse only)
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -129,14 +137,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
       "race_id": 1
     },
     "bets": [
-      {"id": 1, "selection": "1", "stake": 1.0, "cost": 1.0},
-      {"id": 2, "selection": "3", "stake": 1.0, "cost": 1.0},
-      {"id": 3, "selection": "5", "stake": 0.5, "cost": 0.5}
+      {"id": 1, "selection": "1", "stake": "1.00", "cost": "1.00"},
+      {"id": 2, "selection": "3", "stake": "1.00", "cost": "1.00"},
+      {"id": 3, "selection": "5", "stake": "0.50", "cost": "0.50"}
     ]
   }' > /dev/null && success "Created batch for nimesh - Race 1 (WIN)"
 
 # Batch 2 - Race 2 - PLACE bets (single horse only)
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -144,14 +152,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
       "race_id": 2
     },
     "bets": [
-      {"id": 1, "selection": "2", "stake": 2.0, "cost": 1.0},
-      {"id": 2, "selection": "4", "stake": 1.5, "cost": 0.5},
-      {"id": 3, "selection": "6", "stake": 1.0, "cost": 0.5}
+      {"id": 1, "selection": "2", "stake": "2.00", "cost": "1.00"},
+      {"id": 2, "selection": "4", "stake": "1.50", "cost": "0.50"},
+      {"id": 3, "selection": "6", "stake": "1.00", "cost": "0.50"}
     ]
   }' > /dev/null && success "Created batch for nimesh - Race 2 (PLACE)"
 
 # Batch 3 - Race 3 - QUINELLA bets (2 horses, any order)
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -159,14 +167,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
       "race_id": 3
     },
     "bets": [
-      {"id": 1, "selection": "1/2", "stake": 3.0, "cost": 1.0},
-      {"id": 2, "selection": "3/4", "stake": 2.5, "cost": 1.0},
-      {"id": 3, "selection": "5/6", "stake": 2.0, "cost": 1.0}
+      {"id": 1, "selection": "1/2", "stake": "3.00", "cost": "1.00"},
+      {"id": 2, "selection": "3/4", "stake": "2.50", "cost": "1.00"},
+      {"id": 3, "selection": "5/6", "stake": "2.00", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for nimesh - Race 3 (QUINELLA)"
 
 # Batch 4 - Race 4 - EXACTA bets (2 horses, exact order)
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -174,13 +182,13 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
       "race_id": 4
     },
     "bets": [
-      {"id": 1, "selection": "2/3", "stake": 1.5, "cost": 0.5},
-      {"id": 2, "selection": "7/8", "stake": 1.0, "cost": 0.5}
+      {"id": 1, "selection": "2/3", "stake": "1.50", "cost": "0.50"},
+      {"id": 2, "selection": "7/8", "stake": "1.00", "cost": "0.50"}
     ]
   }' > /dev/null && success "Created batch for nimesh - Race 4 (EXACTA)"
 
 # Batch 5 - Race 5 - TRIFECTA bets (3 horses, exact order)
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -188,14 +196,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT1_ID/batches" \
       "race_id": 5
     },
     "bets": [
-      {"id": 1, "selection": "1/2/3", "stake": 5.0, "cost": 1.0},
-      {"id": 2, "selection": "4/5/6", "stake": 3.0, "cost": 1.0}
+      {"id": 1, "selection": "1/2/3", "stake": "5.00", "cost": "1.00"},
+      {"id": 2, "selection": "4/5/6", "stake": "3.00", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for nimesh - Race 5 (TRIFECTA)"
 
 # ===== ACCOUNT 2 BATCHES =====
 # Batch 6 - Race 1 - WIN bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -203,15 +211,15 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
       "race_id": 1
     },
     "bets": [
-      {"id": 1, "selection": "2", "stake": 0.5, "cost": 0.5},
-      {"id": 2, "selection": "4", "stake": 1.0, "cost": 0.5},
-      {"id": 3, "selection": "6", "stake": 0.5, "cost": 0.5},
-      {"id": 4, "selection": "8", "stake": 2.0, "cost": 1.0}
+      {"id": 1, "selection": "2", "stake": "0.50", "cost": "0.50"},
+      {"id": 2, "selection": "4", "stake": "1.00", "cost": "0.50"},
+      {"id": 3, "selection": "6", "stake": "0.50", "cost": "0.50"},
+      {"id": 4, "selection": "8", "stake": "2.00", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for ganga - Race 1 (WIN)"
 
 # Batch 7 - Race 2 - PLACE bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -219,14 +227,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
       "race_id": 2
     },
     "bets": [
-      {"id": 1, "selection": "1", "stake": 2.0, "cost": 1.0},
-      {"id": 2, "selection": "3", "stake": 1.5, "cost": 0.5},
-      {"id": 3, "selection": "7", "stake": 1.0, "cost": 0.5}
+      {"id": 1, "selection": "1", "stake": "2.00", "cost": "1.00"},
+      {"id": 2, "selection": "3", "stake": "1.50", "cost": "0.50"},
+      {"id": 3, "selection": "7", "stake": "1.00", "cost": "0.50"}
     ]
   }' > /dev/null && success "Created batch for ganga - Race 2 (PLACE)"
 
 # Batch 8 - Race 3 - QUINELLA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -234,13 +242,13 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
       "race_id": 3
     },
     "bets": [
-      {"id": 1, "selection": "1/3", "stake": 2.5, "cost": 1.0},
-      {"id": 2, "selection": "2/5", "stake": 2.0, "cost": 1.0}
+      {"id": 1, "selection": "1/3", "stake": "2.50", "cost": "1.00"},
+      {"id": 2, "selection": "2/5", "stake": "2.00", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for ganga - Race 3 (QUINELLA)"
 
 # Batch 9 - Race 4 - TRIFECTA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -248,14 +256,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT2_ID/batches" \
       "race_id": 4
     },
     "bets": [
-      {"id": 1, "selection": "1/2/3", "stake": 5.0, "cost": 1.0},
-      {"id": 2, "selection": "4/5/6", "stake": 3.0, "cost": 1.0}
+      {"id": 1, "selection": "1/2/3", "stake": "5.00", "cost": "1.00"},
+      {"id": 2, "selection": "4/5/6", "stake": "3.00", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for ganga - Race 4 (TRIFECTA)"
 
 # ===== ACCOUNT 3 BATCHES =====
 # Batch 10 - Race 1 - QUINELLA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -263,14 +271,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
       "race_id": 1
     },
     "bets": [
-      {"id": 1, "selection": "3/4", "stake": 2.5, "cost": 1.0},
-      {"id": 2, "selection": "5/7", "stake": 2.0, "cost": 1.0},
-      {"id": 3, "selection": "8/9", "stake": 1.5, "cost": 0.5}
+      {"id": 1, "selection": "3/4", "stake": "2.50", "cost": "1.00"},
+      {"id": 2, "selection": "5/7", "stake": "2.00", "cost": "1.00"},
+      {"id": 3, "selection": "8/9", "stake": "1.50", "cost": "0.50"}
     ]
   }' > /dev/null && success "Created batch for rajesh - Race 1 (QUINELLA)"
 
 # Batch 11 - Race 2 - WIN bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -278,14 +286,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
       "race_id": 2
     },
     "bets": [
-      {"id": 1, "selection": "1", "stake": 4.0, "cost": 1.0},
-      {"id": 2, "selection": "5", "stake": 3.0, "cost": 1.0},
-      {"id": 3, "selection": "7", "stake": 2.0, "cost": 1.0}
+      {"id": 1, "selection": "1", "stake": "4.00", "cost": "1.00"},
+      {"id": 2, "selection": "5", "stake": "3.00", "cost": "1.00"},
+      {"id": 3, "selection": "7", "stake": "2.00", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for rajesh - Race 2 (WIN)"
 
 # Batch 12 - Race 3 - EXACTA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -293,14 +301,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
       "race_id": 3
     },
     "bets": [
-      {"id": 1, "selection": "2/3", "stake": 2.0, "cost": 1.0},
-      {"id": 2, "selection": "4/5", "stake": 1.5, "cost": 0.5},
-      {"id": 3, "selection": "6/7", "stake": 1.0, "cost": 0.5}
+      {"id": 1, "selection": "2/3", "stake": "2.00", "cost": "1.00"},
+      {"id": 2, "selection": "4/5", "stake": "1.50", "cost": "0.50"},
+      {"id": 3, "selection": "6/7", "stake": "1.00", "cost": "0.50"}
     ]
   }' > /dev/null && success "Created batch for rajesh - Race 3 (EXACTA)"
 
 # Batch 13 - Race 5 - TRIFECTA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -308,14 +316,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT3_ID/batches" \
       "race_id": 5
     },
     "bets": [
-      {"id": 1, "selection": "2/4/6", "stake": 8.0, "cost": 2.0},
-      {"id": 2, "selection": "1/3/5", "stake": 6.0, "cost": 2.0}
+      {"id": 1, "selection": "2/4/6", "stake": "8.00", "cost": "2.00"},
+      {"id": 2, "selection": "1/3/5", "stake": "6.00", "cost": "2.00"}
     ]
   }' > /dev/null && success "Created batch for rajesh - Race 5 (TRIFECTA)"
 
 # ===== ACCOUNT 4 BATCHES =====
 # Batch 14 - Race 2 - WIN bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -323,15 +331,15 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
       "race_id": 2
     },
     "bets": [
-      {"id": 1, "selection": "1", "stake": 5.0, "cost": 2.0},
-      {"id": 2, "selection": "3", "stake": 4.0, "cost": 2.0},
-      {"id": 3, "selection": "5", "stake": 3.0, "cost": 1.0},
-      {"id": 4, "selection": "9", "stake": 2.0, "cost": 1.0}
+      {"id": 1, "selection": "1", "stake": "5.00", "cost": "2.00"},
+      {"id": 2, "selection": "3", "stake": "4.00", "cost": "2.00"},
+      {"id": 3, "selection": "5", "stake": "3.00", "cost": "1.00"},
+      {"id": 4, "selection": "9", "stake": "2.00", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for priya - Race 2 (WIN)"
 
 # Batch 15 - Race 3 - PLACE bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -339,14 +347,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
       "race_id": 3
     },
     "bets": [
-      {"id": 1, "selection": "2", "stake": 3.5, "cost": 1.0},
-      {"id": 2, "selection": "7", "stake": 2.5, "cost": 1.0},
-      {"id": 3, "selection": "4", "stake": 2.0, "cost": 0.5}
+      {"id": 1, "selection": "2", "stake": "3.50", "cost": "1.00"},
+      {"id": 2, "selection": "7", "stake": "2.50", "cost": "1.00"},
+      {"id": 3, "selection": "4", "stake": "2.00", "cost": "0.50"}
     ]
   }' > /dev/null && success "Created batch for priya - Race 3 (PLACE)"
 
 # Batch 16 - Race 4 - QUINELLA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -354,13 +362,13 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
       "race_id": 4
     },
     "bets": [
-      {"id": 1, "selection": "1/3", "stake": 4.0, "cost": 2.0},
-      {"id": 2, "selection": "5/8", "stake": 3.0, "cost": 1.0}
+      {"id": 1, "selection": "1/3", "stake": "4.00", "cost": "2.00"},
+      {"id": 2, "selection": "5/8", "stake": "3.00", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for priya - Race 4 (QUINELLA)"
 
 # Batch 17 - Race 5 - EXACTA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -368,14 +376,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT4_ID/batches" \
       "race_id": 5
     },
     "bets": [
-      {"id": 1, "selection": "3/5", "stake": 3.0, "cost": 1.5},
-      {"id": 2, "selection": "7/9", "stake": 2.5, "cost": 1.0}
+      {"id": 1, "selection": "3/5", "stake": "3.00", "cost": "1.50"},
+      {"id": 2, "selection": "7/9", "stake": "2.50", "cost": "1.00"}
     ]
   }' > /dev/null && success "Created batch for priya - Race 5 (EXACTA)"
 
 # ===== ACCOUNT 5 BATCHES =====
 # Batch 18 - Race 1 - EXACTA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -383,13 +391,13 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
       "race_id": 1
     },
     "bets": [
-      {"id": 1, "selection": "1/2", "stake": 6.0, "cost": 2.0},
-      {"id": 2, "selection": "4/5", "stake": 4.5, "cost": 1.5}
+      {"id": 1, "selection": "1/2", "stake": "6.00", "cost": "2.00"},
+      {"id": 2, "selection": "4/5", "stake": "4.50", "cost": "1.50"}
     ]
   }' > /dev/null && success "Created batch for suresh - Race 1 (EXACTA)"
 
 # Batch 19 - Race 2 - TRIFECTA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -397,13 +405,13 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
       "race_id": 2
     },
     "bets": [
-      {"id": 1, "selection": "2/3/4", "stake": 10.0, "cost": 2.0},
-      {"id": 2, "selection": "5/6/7", "stake": 8.0, "cost": 2.0}
+      {"id": 1, "selection": "2/3/4", "stake": "10.00", "cost": "2.00"},
+      {"id": 2, "selection": "5/6/7", "stake": "8.00", "cost": "2.00"}
     ]
   }' > /dev/null && success "Created batch for suresh - Race 2 (TRIFECTA)"
 
 # Batch 20 - Race 3 - WIN bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -411,15 +419,15 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
       "race_id": 3
     },
     "bets": [
-      {"id": 1, "selection": "1", "stake": 10.0, "cost": 5.0},
-      {"id": 2, "selection": "3", "stake": 8.0, "cost": 4.0},
-      {"id": 3, "selection": "5", "stake": 6.0, "cost": 3.0},
-      {"id": 4, "selection": "7", "stake": 4.0, "cost": 2.0}
+      {"id": 1, "selection": "1", "stake": "10.00", "cost": "5.00"},
+      {"id": 2, "selection": "3", "stake": "8.00", "cost": "4.00"},
+      {"id": 3, "selection": "5", "stake": "6.00", "cost": "3.00"},
+      {"id": 4, "selection": "7", "stake": "4.00", "cost": "2.00"}
     ]
   }' > /dev/null && success "Created batch for suresh - Race 3 (WIN)"
 
 # Batch 21 - Race 4 - PLACE bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -427,14 +435,14 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
       "race_id": 4
     },
     "bets": [
-      {"id": 1, "selection": "2", "stake": 5.0, "cost": 2.5},
-      {"id": 2, "selection": "6", "stake": 4.0, "cost": 2.0},
-      {"id": 3, "selection": "9", "stake": 3.0, "cost": 1.5}
+      {"id": 1, "selection": "2", "stake": "5.00", "cost": "2.50"},
+      {"id": 2, "selection": "6", "stake": "4.00", "cost": "2.00"},
+      {"id": 3, "selection": "9", "stake": "3.00", "cost": "1.50"}
     ]
   }' > /dev/null && success "Created batch for suresh - Race 4 (PLACE)"
 
 # Batch 22 - Race 5 - QUINELLA bets
-curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
+curl -s -H "$AUTH_HEADER" -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
   -H "Content-Type: application/json" \
   -d '{
     "meta": {
@@ -442,11 +450,19 @@ curl -s -X POST "$BASE_URL/accounts/$ACCOUNT5_ID/batches" \
       "race_id": 5
     },
     "bets": [
-      {"id": 1, "selection": "2/4", "stake": 7.0, "cost": 3.0},
-      {"id": 2, "selection": "6/8", "stake": 5.0, "cost": 2.0}
+      {"id": 1, "selection": "2/4", "stake": "7.00", "cost": "3.00"},
+      {"id": 2, "selection": "6/8", "stake": "5.00", "cost": "2.00"}
     ]
   }' > /dev/null && success "Created batch for suresh - Race 5 (QUINELLA)"
 
+info "Setting FX rates..."
+curl -s -H "$AUTH_HEADER" -X PUT "$BASE_URL/admin/fx-rates/NZD/AUD" \
+  -H "Content-Type: application/json" \
+  -d '{"rate": "0.9215"}' > /dev/null && success "Set NZD/AUD rate"
+curl -s -H "$AUTH_HEADER" -X PUT "$BASE_URL/admin/fx-rates/AUD/NZD" \
+  -H "Content-Type: application/json" \
+  -d '{"rate": "1.0852"}' > /dev/null && success "Set AUD/NZD rate"
+
 echo ""
 success "✅ Data population complete!"
 echo ""
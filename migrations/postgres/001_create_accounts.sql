@@ -0,0 +1,46 @@
+-- Create accounts table
+CREATE TABLE IF NOT EXISTS accounts (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    hostname TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Create indexes for better performance
+CREATE INDEX IF NOT EXISTS idx_accounts_name ON accounts(name);
+CREATE INDEX IF NOT EXISTS idx_accounts_hostname ON accounts(hostname);
+CREATE INDEX IF NOT EXISTS idx_accounts_created_at ON accounts(created_at);
+
+
+CREATE TABLE IF NOT EXISTS batches (
+    id BIGSERIAL PRIMARY KEY,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    meta JSONB NOT NULL,
+    account_id BIGINT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+
+CREATE OR REPLACE FUNCTION batches_touch_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.updated_at = now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER batches_update
+BEFORE UPDATE ON batches
+FOR EACH ROW EXECUTE FUNCTION batches_touch_updated_at();
+
+
+CREATE TABLE IF NOT EXISTS bets (
+    pid BIGSERIAL PRIMARY KEY,
+    id BIGINT NOT NULL,
+    selection TEXT NOT NULL,
+    stake DOUBLE PRECISION NOT NULL,
+    cost DOUBLE PRECISION NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'successful', 'failed')),
+    batch_id BIGINT NOT NULL REFERENCES batches(id) ON DELETE CASCADE
+);
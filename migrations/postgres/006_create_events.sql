@@ -0,0 +1,11 @@
+-- Log of every broadcast event, replayed to SSE clients that reconnect with
+-- Last-Event-ID
+CREATE TABLE IF NOT EXISTS events (
+    id BIGSERIAL PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    account_id BIGINT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_created_at ON events(created_at);
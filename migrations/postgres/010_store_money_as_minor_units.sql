@@ -0,0 +1,4 @@
+-- Stakes and costs become integer cents so sums are exact. Existing amounts
+-- are rounded to the nearest cent.
+ALTER TABLE bets ALTER COLUMN stake TYPE BIGINT USING ROUND((stake * 100)::NUMERIC)::BIGINT;
+ALTER TABLE bets ALTER COLUMN cost TYPE BIGINT USING ROUND((cost * 100)::NUMERIC)::BIGINT;
@@ -0,0 +1,15 @@
+-- Every account trades in one currency, which its batches and bets copy when
+-- they are created. Existing rows are AUD.
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS currency TEXT NOT NULL DEFAULT 'AUD';
+ALTER TABLE batches ADD COLUMN IF NOT EXISTS currency TEXT NOT NULL DEFAULT 'AUD';
+ALTER TABLE bets ADD COLUMN IF NOT EXISTS currency TEXT NOT NULL DEFAULT 'AUD';
+
+-- `rate` units of `to_currency` buy one unit of `from_currency`, stored in
+-- millionths
+CREATE TABLE IF NOT EXISTS fx_rates (
+    from_currency TEXT NOT NULL,
+    to_currency TEXT NOT NULL,
+    rate BIGINT NOT NULL CHECK (rate > 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (from_currency, to_currency)
+);
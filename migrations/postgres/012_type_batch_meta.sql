@@ -0,0 +1,73 @@
+-- The known batch meta fields become columns so batches can be filtered and
+-- indexed on them; `meta` keeps every other key. A value that cannot be read
+-- as its field's type (a race_id like "R1", an unknown bet type) leaves the
+-- column empty and stays in `meta` as legacy_<field>.
+
+-- Backfilling must not touch updated_at
+ALTER TABLE batches DISABLE TRIGGER batches_update;
+
+ALTER TABLE batches ADD COLUMN IF NOT EXISTS race_id BIGINT;
+ALTER TABLE batches ADD COLUMN IF NOT EXISTS bet_type TEXT;
+ALTER TABLE batches ADD COLUMN IF NOT EXISTS meeting TEXT;
+ALTER TABLE batches ADD COLUMN IF NOT EXISTS venue TEXT;
+ALTER TABLE batches ADD COLUMN IF NOT EXISTS strategy TEXT;
+
+UPDATE batches SET meta = jsonb_build_object('legacy_meta', meta) WHERE jsonb_typeof(meta) <> 'object';
+
+UPDATE batches SET race_id = (meta->>'race_id')::BIGINT
+WHERE (jsonb_typeof(meta->'race_id') = 'number' AND meta->>'race_id' ~ '^-?[0-9]{1,18}$')
+   OR (jsonb_typeof(meta->'race_id') = 'string' AND meta->>'race_id' ~ '^[0-9]{1,18}$');
+UPDATE batches SET bet_type = upper(meta->>'bet_type')
+WHERE jsonb_typeof(meta->'bet_type') = 'string'
+  AND upper(meta->>'bet_type') IN ('WIN', 'PLACE', 'QUINELLA', 'EXACTA', 'TRIFECTA');
+UPDATE batches SET meeting = meta->>'meeting' WHERE jsonb_typeof(meta->'meeting') = 'string';
+UPDATE batches SET venue = meta->>'venue' WHERE jsonb_typeof(meta->'venue') = 'string';
+UPDATE batches SET strategy = meta->>'strategy' WHERE jsonb_typeof(meta->'strategy') = 'string';
+
+UPDATE batches SET meta = meta - 'race_id' WHERE race_id IS NOT NULL OR jsonb_typeof(meta->'race_id') = 'null';
+UPDATE batches SET meta = meta - 'bet_type' WHERE bet_type IS NOT NULL OR jsonb_typeof(meta->'bet_type') = 'null';
+UPDATE batches SET meta = meta - 'meeting' WHERE meeting IS NOT NULL OR jsonb_typeof(meta->'meeting') = 'null';
+UPDATE batches SET meta = meta - 'venue' WHERE venue IS NOT NULL OR jsonb_typeof(meta->'venue') = 'null';
+UPDATE batches SET meta = meta - 'strategy' WHERE strategy IS NOT NULL OR jsonb_typeof(meta->'strategy') = 'null';
+
+UPDATE batches SET meta = (meta - 'race_id') || jsonb_build_object('legacy_race_id', meta->'race_id') WHERE meta ? 'race_id';
+UPDATE batches SET meta = (meta - 'bet_type') || jsonb_build_object('legacy_bet_type', meta->'bet_type') WHERE meta ? 'bet_type';
+UPDATE batches SET meta = (meta - 'meeting') || jsonb_build_object('legacy_meeting', meta->'meeting') WHERE meta ? 'meeting';
+UPDATE batches SET meta = (meta - 'venue') || jsonb_build_object('legacy_venue', meta->'venue') WHERE meta ? 'venue';
+UPDATE batches SET meta = (meta - 'strategy') || jsonb_build_object('legacy_strategy', meta->'strategy') WHERE meta ? 'strategy';
+
+ALTER TABLE batches ENABLE TRIGGER batches_update;
+
+CREATE INDEX IF NOT EXISTS idx_batches_account_race ON batches(account_id, race_id);
+CREATE INDEX IF NOT EXISTS idx_batches_race_bet_type ON batches(race_id, bet_type);
+
+-- Logged batch_created events are replayed, so their meta must read back the
+-- same way: numeric race_id strings become numbers, unreadable values move
+-- to legacy_<field>
+UPDATE events SET payload = jsonb_set(payload, '{batch,meta}', jsonb_build_object('legacy_meta', payload#>'{batch,meta}'))
+WHERE event_type = 'batch_created' AND jsonb_typeof(payload#>'{batch,meta}') <> 'object';
+
+UPDATE events SET payload = jsonb_set(payload, '{batch,meta,race_id}', to_jsonb((payload#>>'{batch,meta,race_id}')::BIGINT))
+WHERE event_type = 'batch_created'
+  AND jsonb_typeof(payload#>'{batch,meta,race_id}') = 'string'
+  AND payload#>>'{batch,meta,race_id}' ~ '^[0-9]{1,18}$';
+UPDATE events SET payload = jsonb_set(payload, '{batch,meta}',
+    ((payload#>'{batch,meta}') - 'race_id') || jsonb_build_object('legacy_race_id', payload#>'{batch,meta,race_id}'))
+WHERE event_type = 'batch_created'
+  AND jsonb_typeof(payload#>'{batch,meta,race_id}') <> 'null'
+  AND NOT (jsonb_typeof(payload#>'{batch,meta,race_id}') = 'number' AND payload#>>'{batch,meta,race_id}' ~ '^-?[0-9]{1,18}$');
+UPDATE events SET payload = jsonb_set(payload, '{batch,meta}',
+    ((payload#>'{batch,meta}') - 'bet_type') || jsonb_build_object('legacy_bet_type', payload#>'{batch,meta,bet_type}'))
+WHERE event_type = 'batch_created'
+  AND jsonb_typeof(payload#>'{batch,meta,bet_type}') <> 'null'
+  AND (jsonb_typeof(payload#>'{batch,meta,bet_type}') <> 'string'
+       OR upper(payload#>>'{batch,meta,bet_type}') NOT IN ('WIN', 'PLACE', 'QUINELLA', 'EXACTA', 'TRIFECTA'));
+UPDATE events SET payload = jsonb_set(payload, '{batch,meta}',
+    ((payload#>'{batch,meta}') - 'meeting') || jsonb_build_object('legacy_meeting', payload#>'{batch,meta,meeting}'))
+WHERE event_type = 'batch_created' AND jsonb_typeof(payload#>'{batch,meta,meeting}') NOT IN ('string', 'null');
+UPDATE events SET payload = jsonb_set(payload, '{batch,meta}',
+    ((payload#>'{batch,meta}') - 'venue') || jsonb_build_object('legacy_venue', payload#>'{batch,meta,venue}'))
+WHERE event_type = 'batch_created' AND jsonb_typeof(payload#>'{batch,meta,venue}') NOT IN ('string', 'null');
+UPDATE events SET payload = jsonb_set(payload, '{batch,meta}',
+    ((payload#>'{batch,meta}') - 'strategy') || jsonb_build_object('legacy_strategy', payload#>'{batch,meta,strategy}'))
+WHERE event_type = 'batch_created' AND jsonb_typeof(payload#>'{batch,meta,strategy}') NOT IN ('string', 'null');
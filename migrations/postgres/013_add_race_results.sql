@@ -0,0 +1,11 @@
+-- Official race results, and what each successful bet came to under them
+CREATE TABLE IF NOT EXISTS race_results (
+    race_id BIGINT PRIMARY KEY,
+    placings JSONB NOT NULL,
+    dividends JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+ALTER TABLE bets ADD COLUMN IF NOT EXISTS result TEXT CHECK (result IN ('won', 'lost'));
+ALTER TABLE bets ADD COLUMN IF NOT EXISTS payout BIGINT;
+ALTER TABLE bets ADD COLUMN IF NOT EXISTS settled_at TIMESTAMPTZ;
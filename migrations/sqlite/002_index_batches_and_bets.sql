@@ -0,0 +1,5 @@
+-- Support keyset pagination of an account's batches
+CREATE INDEX IF NOT EXISTS idx_batches_account_created ON batches(account_id, created_at DESC, id DESC);
+
+-- Support loading the bets of a page of batches in one query
+CREATE INDEX IF NOT EXISTS idx_bets_batch_id ON bets(batch_id);
@@ -0,0 +1,13 @@
+-- Responses stored under a client `Idempotency-Key`, replayed on retries.
+-- A row with no status_code is a request still in flight.
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    account_id INTEGER NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    status_code INTEGER,
+    response_body TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (account_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_created_at ON idempotency_keys(created_at);
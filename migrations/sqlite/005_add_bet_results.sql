@@ -0,0 +1,3 @@
+-- What the executor reported when settling a bet
+ALTER TABLE bets ADD COLUMN reference TEXT;
+ALTER TABLE bets ADD COLUMN message TEXT;
@@ -0,0 +1,11 @@
+-- Log of every broadcast event, replayed to SSE clients that reconnect with
+-- Last-Event-ID. AUTOINCREMENT keeps ids monotonic even after pruning.
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_type TEXT NOT NULL,
+    account_id INTEGER NOT NULL,
+    payload TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_created_at ON events(created_at);
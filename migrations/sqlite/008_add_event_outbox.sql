@@ -0,0 +1,8 @@
+-- Events are written in the same transaction as the change they describe and
+-- dispatched to subscribers afterwards, in id order. Rows logged before the
+-- outbox existed were already broadcast.
+ALTER TABLE events ADD COLUMN dispatched_at TEXT;
+
+UPDATE events SET dispatched_at = created_at;
+
+CREATE INDEX IF NOT EXISTS idx_events_undispatched ON events(id) WHERE dispatched_at IS NULL;
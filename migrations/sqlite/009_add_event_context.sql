@@ -0,0 +1,4 @@
+-- Request metadata carried in the event envelope: the X-Request-Id of the
+-- request that made the change and the caller, as JSON
+ALTER TABLE events ADD COLUMN correlation_id TEXT;
+ALTER TABLE events ADD COLUMN actor TEXT;
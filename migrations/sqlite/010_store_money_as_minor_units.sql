@@ -0,0 +1,11 @@
+-- Stakes and costs become integer cents so sums are exact. Existing amounts
+-- are rounded to the nearest cent.
+ALTER TABLE bets ADD COLUMN stake_minor INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE bets ADD COLUMN cost_minor INTEGER NOT NULL DEFAULT 0;
+
+UPDATE bets SET stake_minor = CAST(ROUND(stake * 100) AS INTEGER), cost_minor = CAST(ROUND(cost * 100) AS INTEGER);
+
+ALTER TABLE bets DROP COLUMN stake;
+ALTER TABLE bets DROP COLUMN cost;
+ALTER TABLE bets RENAME COLUMN stake_minor TO stake;
+ALTER TABLE bets RENAME COLUMN cost_minor TO cost;
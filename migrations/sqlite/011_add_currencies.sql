@@ -0,0 +1,15 @@
+-- Every account trades in one currency, which its batches and bets copy when
+-- they are created. Existing rows are AUD.
+ALTER TABLE accounts ADD COLUMN currency TEXT NOT NULL DEFAULT 'AUD';
+ALTER TABLE batches ADD COLUMN currency TEXT NOT NULL DEFAULT 'AUD';
+ALTER TABLE bets ADD COLUMN currency TEXT NOT NULL DEFAULT 'AUD';
+
+-- `rate` units of `to_currency` buy one unit of `from_currency`, stored in
+-- millionths
+CREATE TABLE IF NOT EXISTS fx_rates (
+    from_currency TEXT NOT NULL,
+    to_currency TEXT NOT NULL,
+    rate INTEGER NOT NULL CHECK (rate > 0),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (from_currency, to_currency)
+);
@@ -0,0 +1,88 @@
+-- The known batch meta fields become columns so batches can be filtered and
+-- indexed on them; `meta` keeps every other key. A value that cannot be read
+-- as its field's type (a race_id like "R1", an unknown bet type) leaves the
+-- column empty and stays in `meta` as legacy_<field>.
+
+-- Backfilling must not touch updated_at
+DROP TRIGGER IF EXISTS batches_update;
+
+ALTER TABLE batches ADD COLUMN race_id INTEGER;
+ALTER TABLE batches ADD COLUMN bet_type TEXT;
+ALTER TABLE batches ADD COLUMN meeting TEXT;
+ALTER TABLE batches ADD COLUMN venue TEXT;
+ALTER TABLE batches ADD COLUMN strategy TEXT;
+
+UPDATE batches SET meta = json_object('legacy_meta', json(meta)) WHERE json_type(meta) <> 'object';
+
+UPDATE batches SET race_id = json_extract(meta, '$.race_id')
+WHERE json_type(meta, '$.race_id') = 'integer'
+   OR (json_type(meta, '$.race_id') = 'text'
+       AND json_extract(meta, '$.race_id') NOT GLOB '*[^0-9]*'
+       AND length(json_extract(meta, '$.race_id')) BETWEEN 1 AND 18);
+UPDATE batches SET bet_type = upper(json_extract(meta, '$.bet_type'))
+WHERE json_type(meta, '$.bet_type') = 'text'
+  AND upper(json_extract(meta, '$.bet_type')) IN ('WIN', 'PLACE', 'QUINELLA', 'EXACTA', 'TRIFECTA');
+UPDATE batches SET meeting = json_extract(meta, '$.meeting') WHERE json_type(meta, '$.meeting') = 'text';
+UPDATE batches SET venue = json_extract(meta, '$.venue') WHERE json_type(meta, '$.venue') = 'text';
+UPDATE batches SET strategy = json_extract(meta, '$.strategy') WHERE json_type(meta, '$.strategy') = 'text';
+
+UPDATE batches SET meta = json_remove(meta, '$.race_id') WHERE race_id IS NOT NULL OR json_type(meta, '$.race_id') = 'null';
+UPDATE batches SET meta = json_remove(meta, '$.bet_type') WHERE bet_type IS NOT NULL OR json_type(meta, '$.bet_type') = 'null';
+UPDATE batches SET meta = json_remove(meta, '$.meeting') WHERE meeting IS NOT NULL OR json_type(meta, '$.meeting') = 'null';
+UPDATE batches SET meta = json_remove(meta, '$.venue') WHERE venue IS NOT NULL OR json_type(meta, '$.venue') = 'null';
+UPDATE batches SET meta = json_remove(meta, '$.strategy') WHERE strategy IS NOT NULL OR json_type(meta, '$.strategy') = 'null';
+
+UPDATE batches SET meta = json_set(json_remove(meta, '$.race_id'), '$.legacy_race_id', json(meta -> '$.race_id'))
+WHERE json_type(meta, '$.race_id') IS NOT NULL;
+UPDATE batches SET meta = json_set(json_remove(meta, '$.bet_type'), '$.legacy_bet_type', json(meta -> '$.bet_type'))
+WHERE json_type(meta, '$.bet_type') IS NOT NULL;
+UPDATE batches SET meta = json_set(json_remove(meta, '$.meeting'), '$.legacy_meeting', json(meta -> '$.meeting'))
+WHERE json_type(meta, '$.meeting') IS NOT NULL;
+UPDATE batches SET meta = json_set(json_remove(meta, '$.venue'), '$.legacy_venue', json(meta -> '$.venue'))
+WHERE json_type(meta, '$.venue') IS NOT NULL;
+UPDATE batches SET meta = json_set(json_remove(meta, '$.strategy'), '$.legacy_strategy', json(meta -> '$.strategy'))
+WHERE json_type(meta, '$.strategy') IS NOT NULL;
+
+CREATE TRIGGER IF NOT EXISTS batches_update
+AFTER UPDATE ON batches
+BEGIN
+    UPDATE batches SET updated_at = datetime('now') WHERE id = NEW.id;
+END;
+
+CREATE INDEX IF NOT EXISTS idx_batches_account_race ON batches(account_id, race_id);
+CREATE INDEX IF NOT EXISTS idx_batches_race_bet_type ON batches(race_id, bet_type);
+
+-- Logged batch_created events are replayed, so their meta must read back the
+-- same way: numeric race_id strings become numbers, unreadable values move
+-- to legacy_<field>
+UPDATE events SET payload = json_set(payload, '$.batch.meta', json_object('legacy_meta', json(payload -> '$.batch.meta')))
+WHERE event_type = 'batch_created' AND json_type(payload, '$.batch.meta') <> 'object';
+
+UPDATE events SET payload = json_set(payload, '$.batch.meta.race_id', CAST(json_extract(payload, '$.batch.meta.race_id') AS INTEGER))
+WHERE event_type = 'batch_created'
+  AND json_type(payload, '$.batch.meta.race_id') = 'text'
+  AND json_extract(payload, '$.batch.meta.race_id') NOT GLOB '*[^0-9]*'
+  AND length(json_extract(payload, '$.batch.meta.race_id')) BETWEEN 1 AND 18;
+UPDATE events SET payload = json_set(
+    json_remove(payload, '$.batch.meta.race_id'),
+    '$.batch.meta.legacy_race_id', json(payload -> '$.batch.meta.race_id'))
+WHERE event_type = 'batch_created' AND json_type(payload, '$.batch.meta.race_id') NOT IN ('integer', 'null');
+UPDATE events SET payload = json_set(
+    json_remove(payload, '$.batch.meta.bet_type'),
+    '$.batch.meta.legacy_bet_type', json(payload -> '$.batch.meta.bet_type'))
+WHERE event_type = 'batch_created'
+  AND json_type(payload, '$.batch.meta.bet_type') <> 'null'
+  AND (json_type(payload, '$.batch.meta.bet_type') <> 'text'
+       OR upper(json_extract(payload, '$.batch.meta.bet_type')) NOT IN ('WIN', 'PLACE', 'QUINELLA', 'EXACTA', 'TRIFECTA'));
+UPDATE events SET payload = json_set(
+    json_remove(payload, '$.batch.meta.meeting'),
+    '$.batch.meta.legacy_meeting', json(payload -> '$.batch.meta.meeting'))
+WHERE event_type = 'batch_created' AND json_type(payload, '$.batch.meta.meeting') NOT IN ('text', 'null');
+UPDATE events SET payload = json_set(
+    json_remove(payload, '$.batch.meta.venue'),
+    '$.batch.meta.legacy_venue', json(payload -> '$.batch.meta.venue'))
+WHERE event_type = 'batch_created' AND json_type(payload, '$.batch.meta.venue') NOT IN ('text', 'null');
+UPDATE events SET payload = json_set(
+    json_remove(payload, '$.batch.meta.strategy'),
+    '$.batch.meta.legacy_strategy', json(payload -> '$.batch.meta.strategy'))
+WHERE event_type = 'batch_created' AND json_type(payload, '$.batch.meta.strategy') NOT IN ('text', 'null');
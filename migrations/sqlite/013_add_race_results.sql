@@ -0,0 +1,11 @@
+-- Official race results, and what each successful bet came to under them
+CREATE TABLE IF NOT EXISTS race_results (
+    race_id INTEGER PRIMARY KEY,
+    placings TEXT NOT NULL,
+    dividends TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+ALTER TABLE bets ADD COLUMN result TEXT CHECK (result IN ('won', 'lost'));
+ALTER TABLE bets ADD COLUMN payout INTEGER;
+ALTER TABLE bets ADD COLUMN settled_at TEXT;
@@ -0,0 +1,6 @@
+-- When the current request claimed its key. A claim with no response whose
+-- lease has run out belonged to a request that never finished, e.g. the
+-- process died, and can be taken over by a retry.
+ALTER TABLE idempotency_keys ADD COLUMN claimed_at TEXT;
+
+UPDATE idempotency_keys SET claimed_at = created_at;
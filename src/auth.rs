@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query, Request, State},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::error::ApiError;
+use crate::handlers::accounts::AppState;
+use crate::models::account::BrokerEvent;
+use crate::models::api_key::{ApiKey, KeyAccess};
+use crate::models::event::Actor;
+
+/// Prefix of every generated key, so leaked keys are easy to grep for
+pub const KEY_PREFIX: &str = "bsk_";
+
+/// Header accepted as an alternative to `Authorization: Bearer`
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Who is making a request, resolved once by [`require_api_key`]
+#[derive(Debug, Clone)]
+pub enum Principal {
+    /// The configured admin key, or any caller while auth is disabled
+    Admin,
+    Key(ApiKey),
+}
+
+impl Principal {
+    pub fn allows(&self, account_id: i64, access: KeyAccess) -> bool {
+        match self {
+            Principal::Admin => true,
+            Principal::Key(key) => {
+                key.access >= access
+                    && key
+                        .account_ids
+                        .as_ref()
+                        .is_none_or(|ids| ids.contains(&account_id))
+            }
+        }
+    }
+
+    pub fn authorize(&self, account_id: i64, access: KeyAccess) -> Result<(), ApiError> {
+        if self.allows(account_id, access) {
+            Ok(())
+        } else {
+            Err(ApiError::forbidden(format!(
+                "API key does not grant {} access to account {}",
+                access, account_id
+            )))
+        }
+    }
+
+    /// Require `access` across every account, e.g. to create new accounts
+    pub fn authorize_all(&self, access: KeyAccess) -> Result<(), ApiError> {
+        match self {
+            Principal::Admin => Ok(()),
+            Principal::Key(key) if key.access >= access && key.account_ids.is_none() => Ok(()),
+            Principal::Key(_) => Err(ApiError::forbidden(format!(
+                "API key does not grant {} access to all accounts",
+                access
+            ))),
+        }
+    }
+
+    pub fn require_admin(&self) -> Result<(), ApiError> {
+        match self {
+            Principal::Admin => Ok(()),
+            Principal::Key(_) => Err(ApiError::forbidden("Admin key required")),
+        }
+    }
+
+    /// Accounts the caller may see, or `None` for all of them
+    pub fn account_scope(&self) -> Option<&[i64]> {
+        match self {
+            Principal::Admin => None,
+            Principal::Key(key) => key.account_ids.as_deref(),
+        }
+    }
+
+    /// `admin`, or the key's name and display prefix, for logs and monitoring
+    pub fn label(&self) -> String {
+        match self {
+            Principal::Admin => "admin".to_string(),
+            Principal::Key(key) => format!("{} ({})", key.name, key.key_prefix),
+        }
+    }
+
+    /// How this caller is recorded on the events its changes write
+    pub fn actor(&self) -> Actor {
+        match self {
+            Principal::Admin => Actor::Admin,
+            Principal::Key(key) => Actor::ApiKey {
+                id: key.id,
+                name: key.name.clone(),
+                key_prefix: key.key_prefix.clone(),
+            },
+        }
+    }
+
+    pub fn can_see(&self, event: &BrokerEvent) -> bool {
+        self.allows(event.account_id(), KeyAccess::Read)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Principal>()
+            .cloned()
+            .ok_or_else(|| ApiError::unauthorized("Missing API key"))
+    }
+}
+
+/// Middleware guarding `/api/v1` and `/sse`; stores the [`Principal`] in the
+/// request extensions for handlers to check scopes against
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let principal = if state.config.auth.enabled {
+        let key = presented_key(&request)
+            .ok_or_else(|| ApiError::unauthorized("Missing API key"))?;
+        authenticate(&state, &key).await?
+    } else {
+        Principal::Admin
+    };
+
+    request.extensions_mut().insert(principal);
+    Ok(next.run(request).await)
+}
+
+async fn authenticate(state: &AppState, key: &str) -> Result<Principal, ApiError> {
+    let key_hash = hash_key(key);
+
+    if let Some(admin_key) = &state.config.auth.admin_key {
+        if hash_key(admin_key) == key_hash {
+            return Ok(Principal::Admin);
+        }
+    }
+
+    state
+        .store
+        .find_api_key(&key_hash)
+        .await?
+        .map(Principal::Key)
+        .ok_or_else(|| ApiError::unauthorized("Invalid or revoked API key"))
+}
+
+// `Authorization: Bearer`, then `X-API-Key`. Browsers cannot set headers on
+// an EventSource or a WebSocket, so event streams also take `?api_key=`.
+fn presented_key(request: &Request) -> Option<String> {
+    let headers = request.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let api_key = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok());
+
+    if let Some(key) = bearer.or(api_key) {
+        return Some(key.trim().to_string());
+    }
+
+    if is_event_stream(request.uri().path()) {
+        let Query(params) = Query::<HashMap<String, String>>::try_from_uri(request.uri()).ok()?;
+        return params.get("api_key").cloned();
+    }
+
+    None
+}
+
+// `/sse`, `/ws` and the per-account `/api/v1/accounts/:id/events`
+fn is_event_stream(path: &str) -> bool {
+    path == "/sse" || path == "/ws" || (path.starts_with("/api/v1/accounts/") && path.ends_with("/events"))
+}
+
+/// Generate a new random key; only its hash is ever stored
+pub fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", KEY_PREFIX, hex::encode(bytes))
+}
+
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Leading characters kept in clear text to identify a key in listings
+pub fn display_prefix(key: &str) -> String {
+    key.chars().take(KEY_PREFIX.len() + 8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn key(access: KeyAccess, account_ids: Option<Vec<i64>>) -> Principal {
+        Principal::Key(ApiKey {
+            id: 1,
+            name: "test".to_string(),
+            key_prefix: "bsk_test".to_string(),
+            access,
+            account_ids,
+            created_at: Utc::now(),
+            revoked_at: None,
+        })
+    }
+
+    #[test]
+    fn write_implies_read_within_scope() {
+        let scoped = key(KeyAccess::Write, Some(vec![1, 2]));
+
+        assert!(scoped.allows(1, KeyAccess::Read));
+        assert!(scoped.allows(2, KeyAccess::Write));
+        assert!(!scoped.allows(3, KeyAccess::Read));
+        assert!(scoped.authorize_all(KeyAccess::Write).is_err());
+        assert_eq!(scoped.account_scope(), Some(&[1, 2][..]));
+    }
+
+    #[test]
+    fn read_keys_cannot_write() {
+        let reader = key(KeyAccess::Read, None);
+
+        assert!(reader.allows(42, KeyAccess::Read));
+        assert!(!reader.allows(42, KeyAccess::Write));
+        assert!(reader.authorize_all(KeyAccess::Read).is_ok());
+        assert!(matches!(
+            reader.authorize(42, KeyAccess::Write),
+            Err(ApiError::Forbidden(_))
+        ));
+        assert!(reader.require_admin().is_err());
+    }
+
+    #[test]
+    fn generated_keys_hash_stably() {
+        let a = generate_key();
+        let b = generate_key();
+
+        assert!(a.starts_with(KEY_PREFIX));
+        assert_ne!(a, b);
+        assert_eq!(hash_key(&a), hash_key(&a));
+        assert_ne!(hash_key(&a), hash_key(&b));
+        assert_eq!(display_prefix(&a).len(), KEY_PREFIX.len() + 8);
+    }
+
+    #[test]
+    fn query_keys_only_on_event_streams() {
+        let presented = |uri: &str| {
+            presented_key(&Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap())
+        };
+
+        assert_eq!(presented("/sse?api_key=k").as_deref(), Some("k"));
+        assert_eq!(presented("/api/v1/accounts/7/events?api_key=k").as_deref(), Some("k"));
+        assert_eq!(presented("/api/v1/accounts/7?api_key=k"), None);
+        assert_eq!(presented("/api/v1/admin/api-keys?api_key=k"), None);
+    }
+}
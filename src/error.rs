@@ -0,0 +1,253 @@
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRequest, FromRequestParts,
+    },
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// RFC 7807 problem details body returned by every failing endpoint
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    /// URI identifying the problem type
+    #[serde(rename = "type")]
+    #[schema(example = "urn:betstream:error:not_found")]
+    pub problem_type: String,
+    /// Short, human-readable summary of the problem type
+    #[schema(example = "Not Found")]
+    pub title: String,
+    /// HTTP status code
+    #[schema(example = 404)]
+    pub status: u16,
+    /// Human-readable explanation specific to this occurrence
+    #[schema(example = "Account 42 not found")]
+    pub detail: String,
+    /// Stable machine-readable error code
+    #[schema(example = "not_found")]
+    pub code: String,
+    /// Each invalid field, for validation failures
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// One invalid field of a request body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    /// JSON pointer to the field within the request body
+    #[schema(example = "/bets/1/selection")]
+    pub pointer: String,
+    /// What is wrong with the field
+    #[schema(example = "WIN takes 1 runner, got 2")]
+    pub detail: String,
+}
+
+impl FieldError {
+    pub fn new(pointer: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { pointer: pointer.into(), detail: detail.into() }
+    }
+}
+
+/// Error type shared by all API handlers
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    UniqueViolation(String),
+    ForeignKeyViolation(String),
+    Unprocessable(String),
+    /// A request body with one or more invalid fields, listed in `errors`
+    Validation(String, Vec<FieldError>),
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        Self::NotFound(detail.into())
+    }
+
+    pub fn bad_request(detail: impl Into<String>) -> Self {
+        Self::BadRequest(detail.into())
+    }
+
+    pub fn unauthorized(detail: impl Into<String>) -> Self {
+        Self::Unauthorized(detail.into())
+    }
+
+    pub fn forbidden(detail: impl Into<String>) -> Self {
+        Self::Forbidden(detail.into())
+    }
+
+    pub fn conflict(detail: impl Into<String>) -> Self {
+        Self::Conflict(detail.into())
+    }
+
+    /// A validation failure listing every invalid field. The detail is the
+    /// field's own when there is just one.
+    pub fn validation(errors: Vec<FieldError>) -> Self {
+        let detail = match errors.len() {
+            1 => errors[0].detail.clone(),
+            n => format!("{} fields are invalid", n),
+        };
+        Self::Validation(detail, errors)
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) | Self::UniqueViolation(_) => StatusCode::CONFLICT,
+            Self::ForeignKeyViolation(_) | Self::Unprocessable(_) | Self::Validation(..) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable machine-readable code, safe for clients to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "bad_request",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::NotFound(_) => "not_found",
+            Self::Conflict(_) => "conflict",
+            Self::UniqueViolation(_) => "unique_violation",
+            Self::ForeignKeyViolation(_) => "foreign_key_violation",
+            Self::Unprocessable(_) => "unprocessable_entity",
+            Self::Validation(..) => "validation_failed",
+            Self::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn detail(&self) -> &str {
+        match self {
+            Self::BadRequest(d)
+            | Self::Unauthorized(d)
+            | Self::Forbidden(d)
+            | Self::NotFound(d)
+            | Self::Conflict(d)
+            | Self::UniqueViolation(d)
+            | Self::ForeignKeyViolation(d)
+            | Self::Unprocessable(d)
+            | Self::Validation(d, _)
+            | Self::Internal(d) => d,
+        }
+    }
+
+    pub fn to_problem(&self) -> ProblemDetails {
+        let status = self.status();
+        ProblemDetails {
+            problem_type: format!("urn:betstream:error:{}", self.code()),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: self.detail().to_string(),
+            code: self.code().to_string(),
+            errors: match self {
+                Self::Validation(_, errors) => errors.clone(),
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code(), self.detail())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::to_string(&self.to_problem())
+            .unwrap_or_else(|_| String::from("{}"));
+
+        let mut response = (
+            self.status(),
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            body,
+        )
+            .into_response();
+
+        if let Self::Unauthorized(_) = self {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+
+        response
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::RowNotFound => Self::NotFound("Resource not found".to_string()),
+            sqlx::Error::Database(db_err) => match db_err.kind() {
+                sqlx::error::ErrorKind::UniqueViolation => Self::UniqueViolation(
+                    "A resource with the same unique value already exists".to_string(),
+                ),
+                sqlx::error::ErrorKind::ForeignKeyViolation => Self::ForeignKeyViolation(
+                    "Referenced resource does not exist".to_string(),
+                ),
+                sqlx::error::ErrorKind::CheckViolation => Self::Unprocessable(
+                    "Value violates a database constraint".to_string(),
+                ),
+                _ => {
+                    eprintln!("Database error: {}", err);
+                    Self::Internal("Database error".to_string())
+                }
+            },
+            _ => {
+                eprintln!("Database error: {}", err);
+                Self::Internal("Database error".to_string())
+            }
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::JsonDataError(e) => Self::Unprocessable(e.body_text()),
+            other => Self::BadRequest(other.body_text()),
+        }
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+/// JSON body extractor that reports malformed input as problem+json
+#[derive(Debug, FromRequest)]
+#[from_request(via(axum::Json), rejection(ApiError))]
+pub struct ApiJson<T>(pub T);
+
+/// Path extractor that reports malformed parameters as problem+json
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(ApiError))]
+pub struct ApiPath<T>(pub T);
+
+/// Query string extractor that reports malformed parameters as problem+json
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApiError))]
+pub struct ApiQuery<T>(pub T);
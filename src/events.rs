@@ -0,0 +1,237 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use chrono::Utc;
+use futures::StreamExt;
+
+use crate::error::ApiError;
+use crate::handlers::accounts::AppState;
+use crate::models::account::BrokerEvent;
+use crate::models::event::{ConnectionStats, EventEnvelope, SseConnectionsReport};
+use crate::store::{Store, StoreResult};
+
+/// SSE event sent instead of a replay when the client's `Last-Event-ID` is
+/// no longer in the event log
+pub const RESYNC_REQUIRED: &str = "resync_required";
+
+/// SSE event sent when a subscriber fell behind and missed live events
+pub const LAGGED: &str = "lagged";
+
+/// SSE event a `snapshot=true` stream starts with, holding the current state
+pub const SNAPSHOT: &str = "snapshot";
+
+// How often the background task prunes the event log
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Server-side subscription filter; `None` fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub account_id: Option<i64>,
+    pub batch_id: Option<i64>,
+    pub types: Option<HashSet<&'static str>>,
+}
+
+impl EventFilter {
+    /// Build a filter from query parameters; `types` is a comma-separated
+    /// list of event names and unknown names are rejected
+    pub fn new(account_id: Option<i64>, batch_id: Option<i64>, types: Option<&str>) -> Result<Self, ApiError> {
+        let types = match types {
+            None => None,
+            Some(list) => {
+                let mut parsed = HashSet::new();
+                for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                    let known = BrokerEvent::TYPES
+                        .iter()
+                        .find(|known| **known == name)
+                        .ok_or_else(|| {
+                            ApiError::bad_request(format!(
+                                "Unknown event type '{}'; expected one of {}",
+                                name,
+                                BrokerEvent::TYPES.join(", ")
+                            ))
+                        })?;
+                    parsed.insert(*known);
+                }
+                Some(parsed).filter(|parsed| !parsed.is_empty())
+            }
+        };
+
+        Ok(EventFilter { account_id, batch_id, types })
+    }
+
+    pub fn matches(&self, event: &BrokerEvent) -> bool {
+        self.account_id.is_none_or(|id| event.account_id() == id)
+            && self.batch_id.is_none_or(|id| event.batch_id() == Some(id))
+            && self.types.as_ref().is_none_or(|types| types.contains(event.event_name()))
+    }
+}
+
+/// What a reconnecting SSE client is sent before the live stream
+#[derive(Debug)]
+pub enum Replay {
+    /// Events the client missed, oldest first
+    Events(Vec<EventEnvelope>),
+    /// The gap cannot be filled from the log; the client must reload its state
+    ResyncRequired { latest_id: i64 },
+}
+
+/// Work out the replay for a client that last saw `last_event_id`. Ids that
+/// are unparseable, ahead of the log, already pruned, or more than `limit`
+/// events behind all require a resync.
+pub async fn replay_since(store: &dyn Store, last_event_id: &str, limit: usize) -> StoreResult<Replay> {
+    let range = store.event_id_range().await?;
+    let latest_id = range.map_or(0, |(_, newest)| newest);
+    let resync = Replay::ResyncRequired { latest_id };
+
+    let Some(last_id) = last_event_id.trim().parse::<i64>().ok().filter(|id| *id >= 0) else {
+        return Ok(resync);
+    };
+    match range {
+        None if last_id == 0 => return Ok(Replay::Events(Vec::new())),
+        None => return Ok(resync),
+        Some((oldest, newest)) if last_id > newest || last_id < oldest - 1 => return Ok(resync),
+        Some(_) => {}
+    }
+
+    // Fetch one extra row to tell "exactly limit" from "too far behind"
+    let limit = i64::try_from(limit).unwrap_or(i64::MAX - 1);
+    let events = store.events_after(last_id, limit + 1).await?;
+    if events.len() as i64 > limit {
+        return Ok(resync);
+    }
+
+    Ok(Replay::Events(events))
+}
+
+/// Open SSE connections and their lag counters, for monitoring
+#[derive(Debug, Default)]
+pub struct SseConnections {
+    next_id: AtomicU64,
+    open: Mutex<HashMap<u64, ConnectionStats>>,
+    total_skipped: AtomicU64,
+    disconnected_for_lag: AtomicU64,
+}
+
+impl SseConnections {
+    /// Track a new connection until the returned handle is dropped
+    pub fn register(self: &Arc<Self>, subscriber: String) -> ConnectionHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let stats = ConnectionStats {
+            id,
+            subscriber,
+            connected_at: Utc::now(),
+            lag_count: 0,
+            skipped_events: 0,
+        };
+        self.lock().insert(id, stats);
+
+        ConnectionHandle { registry: self.clone(), id }
+    }
+
+    pub fn report(&self) -> SseConnectionsReport {
+        let mut connections: Vec<ConnectionStats> = self.lock().values().cloned().collect();
+        connections.sort_by_key(|stats| stats.id);
+
+        SseConnectionsReport {
+            connections,
+            total_skipped_events: self.total_skipped.load(Ordering::Relaxed),
+            disconnected_for_lag: self.disconnected_for_lag.load(Ordering::Relaxed),
+        }
+    }
+
+    // Counters stay usable even if a holder panicked
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, ConnectionStats>> {
+        self.open.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A registered SSE connection; dropping it (with the stream) unregisters it
+#[derive(Debug)]
+pub struct ConnectionHandle {
+    registry: Arc<SseConnections>,
+    id: u64,
+}
+
+impl ConnectionHandle {
+    /// Record that `skipped` events were lost to lag; returns how many times
+    /// this connection has lagged so far
+    pub fn record_lag(&self, skipped: u64) -> u64 {
+        self.registry.total_skipped.fetch_add(skipped, Ordering::Relaxed);
+        let mut open = self.registry.lock();
+        let Some(stats) = open.get_mut(&self.id) else {
+            return 0;
+        };
+        stats.lag_count += 1;
+        stats.skipped_events += skipped;
+        stats.lag_count
+    }
+
+    pub fn record_disconnect(&self) {
+        self.registry.disconnected_for_lag.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ConnectionHandle {
+    fn drop(&mut self) {
+        self.registry.lock().remove(&self.id);
+    }
+}
+
+/// Dispatch events left in the log every `interval`, starting with any a
+/// previous process committed but never dispatched
+pub fn spawn_dispatcher(state: AppState, interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            state.dispatch().await;
+        }
+    });
+}
+
+/// Relay events other instances dispatch every `interval`, or as soon as the
+/// store signals a dispatch where it can. Only events dispatched from now on
+/// are relayed.
+pub async fn spawn_relay(state: AppState, interval: Duration) -> StoreResult<()> {
+    *state.dispatch_lock.lock().await = state.store.dispatched_through().await?;
+    let mut dispatches = state.store.watch_dispatches().await?;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            match dispatches.as_mut() {
+                Some(dispatches) => {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = dispatches.next() => {}
+                    }
+                }
+                None => {
+                    interval.tick().await;
+                }
+            }
+            state.relay_dispatched().await;
+        }
+    });
+    Ok(())
+}
+
+/// Delete events past `retention` from the log every minute
+pub fn spawn_pruner(store: Arc<dyn Store>, retention: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match store.prune_events(retention).await {
+                Ok(0) => {}
+                Ok(pruned) => println!("Pruned {} events from the event log", pruned),
+                Err(e) => eprintln!("Failed to prune the event log: {:?}", e),
+            }
+        }
+    });
+}
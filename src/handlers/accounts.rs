@@ -1,83 +1,426 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::State,
+    http::{HeaderMap, StatusCode},
     response::{
         sse::{Event, Sse, KeepAlive},
         Json,
     },
-    Json as JsonExtract,
 };
 use futures::stream::Stream;
 use futures::StreamExt;
-use sqlx::SqlitePool;
-use std::{convert::Infallible, time::Duration};
-use tokio::sync::broadcast;
-use tokio_stream::wrappers::BroadcastStream;
+use std::{collections::HashSet, convert::Infallible, sync::Arc};
+use tokio::sync::{broadcast, Mutex, Notify};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use crate::auth::Principal;
+use crate::config::Config;
+use crate::error::{ApiError, ApiJson, ApiPath, ApiQuery};
+use crate::events::{self, EventFilter, Replay, SseConnections, LAGGED, RESYNC_REQUIRED, SNAPSHOT};
 use crate::models::account::*;
+use crate::models::api_key::KeyAccess;
+use crate::models::event::{
+    EventContext, EventEnvelope, EventQuery, SseConnectionsReport, SseQuery, StateSnapshot,
+};
+use crate::models::pagination::{Page, PageRequest};
+use crate::sinks::EventSink;
+use crate::store::{Store, StoreError};
+
+// Global event broadcaster; envelopes carry their id from the event log
+pub type EventSender = broadcast::Sender<EventEnvelope>;
+
+// Undispatched events loaded per query while dispatching
+const DISPATCH_BATCH_SIZE: i64 = 100;
 
-// Global event broadcaster
-pub type EventSender = broadcast::Sender<BrokerEvent>;
+/// Header an `EventSource` sends on reconnect with the last id it received
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
 
 // Application state that includes the event broadcaster
 #[derive(Clone)]
 pub struct AppState {
-    pub pool: SqlitePool,
+    pub store: Arc<dyn Store>,
     pub event_sender: EventSender,
+    /// Held while dispatching so subscribers get events in id order. Guards
+    /// the id of the last event broadcast here, the cross-instance relay's
+    /// position in the log.
+    pub dispatch_lock: Arc<Mutex<i64>>,
+    /// Open SSE connections, for lag monitoring
+    pub connections: Arc<SseConnections>,
+    /// Signalled when webhook deliveries are queued
+    pub webhook_wakeup: Arc<Notify>,
+    /// Extra destinations from `sinks` config, written after each broadcast
+    pub sinks: Arc<Vec<Box<dyn EventSink>>>,
+    pub config: Arc<Config>,
+}
+
+impl AppState {
+    /// Hand every undispatched event in the log to its subscribers, oldest
+    /// first: queue it for matching webhooks and mark it dispatched in one
+    /// transaction, then broadcast it to SSE subscribers and write it to the
+    /// configured sinks. Handlers call this
+    /// after each committed change; the background dispatcher calls it to
+    /// pick up events left behind by a failure or a crash. A failed event
+    /// stops the pass so later events never overtake it.
+    ///
+    /// In cross-instance mode, events another instance dispatched are
+    /// broadcast here too, ahead of any later event of this instance.
+    pub async fn dispatch(&self) {
+        let mut cursor = self.dispatch_lock.lock().await;
+        let cross_instance = self.config.events.cross_instance;
+        loop {
+            let pending = match self.store.undispatched_events(DISPATCH_BATCH_SIZE).await {
+                Ok(pending) => pending,
+                Err(e) => {
+                    eprintln!("Failed to load undispatched events: {:?}", e);
+                    return;
+                }
+            };
+            if pending.is_empty() {
+                if cross_instance {
+                    self.relay(&mut cursor, None).await;
+                }
+                return;
+            }
+
+            for envelope in pending {
+                let id = envelope.event_id;
+                match self.store.dispatch_event(&envelope).await {
+                    Ok(None) => continue,
+                    Ok(Some(0)) => {}
+                    Ok(Some(_)) => self.webhook_wakeup.notify_one(),
+                    Err(e) => {
+                        eprintln!("Failed to dispatch event {}: {:?}", id, e);
+                        return;
+                    }
+                }
+                if cross_instance {
+                    self.relay(&mut cursor, Some(id)).await;
+                }
+                let _ = self.event_sender.send(envelope.clone());
+                *cursor = (*cursor).max(id);
+                for sink in self.sinks.iter() {
+                    if let Err(e) = sink.write(&envelope).await {
+                        eprintln!("Failed to write event {} to the {} sink: {:?}", id, sink.name(), e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Broadcast events other instances sharing the database dispatched
+    /// since the last one seen here. Sinks and webhooks are left to the
+    /// instance that dispatched them.
+    pub async fn relay_dispatched(&self) {
+        let mut cursor = self.dispatch_lock.lock().await;
+        self.relay(&mut cursor, None).await;
+    }
+
+    // Broadcast dispatched events after `cursor`, oldest first, stopping at
+    // `before`. Events dispatched here are already behind the cursor, so
+    // only other instances' events go out.
+    async fn relay(&self, cursor: &mut i64, before: Option<i64>) {
+        loop {
+            let events = match self.store.dispatched_events_after(*cursor, DISPATCH_BATCH_SIZE).await {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("Failed to load dispatched events to relay: {:?}", e);
+                    return;
+                }
+            };
+            let complete = (events.len() as i64) < DISPATCH_BATCH_SIZE;
+            for envelope in events {
+                if before.is_some_and(|before| envelope.event_id >= before) {
+                    return;
+                }
+                *cursor = envelope.event_id;
+                let _ = self.event_sender.send(envelope);
+            }
+            if complete {
+                return;
+            }
+        }
+    }
 }
 
-// SSE endpoint handler
+/// Subscribe to real-time events for the accounts the key can read. A client
+/// reconnecting with `Last-Event-ID` first gets the events it missed from the
+/// event log, or a `resync_required` event if they are gone. With
+/// `snapshot=true` the stream starts with a `snapshot` event of the current
+/// state instead, unless a replay can fill the gap.
+#[utoipa::path(
+    get,
+    path = "/sse",
+    params(
+        SseQuery,
+        ("Last-Event-ID" = Option<String>, Header, description = "Id of the last event received; missed events are replayed first")
+    ),
+    responses(
+        (status = 200, description = "Stream of matching events, after a `snapshot` event if requested", content_type = "text/event-stream"),
+        (status = 400, description = "Unknown event type", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key cannot read the requested account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "events"
+)]
 pub async fn sse_handler(
-    axum::extract::State(state): axum::extract::State<AppState>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    State(state): State<AppState>,
+    principal: Principal,
+    headers: HeaderMap,
+    ApiQuery(query): ApiQuery<SseQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    if let Some(account_id) = query.account_id {
+        principal.authorize(account_id, KeyAccess::Read)?;
+    }
+    let filter = EventFilter::new(query.account_id, query.batch_id, query.types.as_deref())?;
+
+    event_stream(state, principal, &headers, filter, query.snapshot).await
+}
+
+/// Subscribe to real-time events for one account; same stream as `/sse`
+/// with `account_id` fixed by the path
+#[utoipa::path(
+    get,
+    path = "/api/v1/accounts/{id}/events",
+    params(
+        ("id" = i64, Path, description = "Account ID"),
+        EventQuery,
+        ("Last-Event-ID" = Option<String>, Header, description = "Id of the last event received; missed events are replayed first")
+    ),
+    responses(
+        (status = 200, description = "Stream of the account's matching events", content_type = "text/event-stream"),
+        (status = 400, description = "Unknown event type", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Account not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "events"
+)]
+pub async fn account_events(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiPath(account_id): ApiPath<i64>,
+    headers: HeaderMap,
+    ApiQuery(query): ApiQuery<EventQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    principal.authorize(account_id, KeyAccess::Read)?;
+    let filter = EventFilter::new(Some(account_id), query.batch_id, query.types.as_deref())?;
+
+    if state.store.get_account(account_id).await?.is_none() {
+        return Err(ApiError::not_found(format!("Account {} not found", account_id)));
+    }
+
+    event_stream(state, principal, &headers, filter, query.snapshot).await
+}
+
+/// Open SSE connections with their lag counters
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/sse-connections",
+    responses(
+        (status = 200, description = "Open connections and lag totals", body = SseConnectionsReport),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn sse_connections(
+    principal: Principal,
+    State(state): State<AppState>,
+) -> Result<Json<SseConnectionsReport>, ApiError> {
+    principal.require_admin()?;
+
+    Ok(Json(state.connections.report()))
+}
+
+// Replay from `Last-Event-ID` if given, or start from a snapshot if asked
+// to, then follow the live broadcast. All of them only pass what the key can
+// read and `filter` matches.
+async fn event_stream(
+    state: AppState,
+    principal: Principal,
+    headers: &HeaderMap,
+    filter: EventFilter,
+    snapshot: bool,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let connection = state.connections.register(principal.label());
+    let scope = match filter.account_id {
+        Some(account_id) => Some(vec![account_id]),
+        None => principal.account_scope().map(<[i64]>::to_vec),
+    };
+    let batch_id = filter.batch_id;
+    let visible = move |event: &BrokerEvent| principal.can_see(event) && filter.matches(event);
+
+    // Subscribe before reading the log so nothing falls between the two
     let rx = state.event_sender.subscribe();
-    
-    let event_stream = BroadcastStream::new(rx)
-        .then(|result| async move {
-            let event = result.ok()?;
-            let event_name = event.event_name();
-            
-            // Serialize the event data
-            let data = match serde_json::to_string(&event) {
-                Ok(json) => json,
-                Err(e) => {
-                    eprintln!("Failed to serialize event: {}", e);
+
+    // Events up to `seen_id` were already sent from the log or are covered
+    // by the snapshot
+    let mut backlog = Vec::new();
+    let mut seen_id = 0;
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .map(|value| value.to_str().unwrap_or_default());
+    let replay = match last_event_id {
+        Some(last_event_id) => Some(
+            events::replay_since(
+                state.store.as_ref(),
+                last_event_id,
+                state.config.events.replay_limit,
+            )
+            .await?,
+        ),
+        None => None,
+    };
+    match replay {
+        // A replay is all a reconnecting client needs, even in snapshot mode
+        Some(Replay::Events(missed)) => {
+            seen_id = missed.last().map_or(0, |envelope| envelope.event_id);
+            backlog.extend(
+                missed
+                    .iter()
+                    .filter(|envelope| visible(&envelope.event))
+                    .filter_map(sse_event),
+            );
+        }
+        None | Some(Replay::ResyncRequired { .. }) if snapshot => {
+            let mut current = state.store.snapshot(scope.as_deref()).await?;
+            if let Some(batch_id) = batch_id {
+                current.batches.retain(|batch| batch.id == batch_id);
+            }
+            seen_id = current.event_id;
+            backlog.push(snapshot_event(&current));
+        }
+        Some(Replay::ResyncRequired { latest_id }) => {
+            seen_id = latest_id;
+            let data = serde_json::json!({
+                "type": RESYNC_REQUIRED,
+                "last_event_id": last_event_id,
+                "latest_event_id": latest_id,
+            });
+            backlog.push(
+                Event::default()
+                    .event(RESYNC_REQUIRED)
+                    .id(latest_id.to_string())
+                    .data(data.to_string()),
+            );
+        }
+        None => {}
+    }
+
+    // Skip events already replayed and filtered events. Lag becomes a
+    // `lagged` event; past `max_lags` of them the stream ends after it.
+    let max_lags = state.config.events.max_lags;
+    let live = futures::stream::unfold(
+        (BroadcastStream::new(rx), connection, false),
+        move |(mut rx, connection, closed)| {
+            let visible = visible.clone();
+            async move {
+                if closed {
                     return None;
                 }
-            };
+                loop {
+                    match rx.next().await? {
+                        Ok(envelope) => {
+                            if envelope.event_id <= seen_id || !visible(&envelope.event) {
+                                continue;
+                            }
+                            if let Some(event) = sse_event(&envelope) {
+                                return Some((event, (rx, connection, false)));
+                            }
+                        }
+                        Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                            let lag_count = connection.record_lag(skipped);
+                            let disconnect = max_lags > 0 && lag_count >= max_lags;
+                            if disconnect {
+                                connection.record_disconnect();
+                            }
+                            let event = lagged_event(skipped, lag_count, disconnect);
+                            return Some((event, (rx, connection, disconnect)));
+                        }
+                    }
+                }
+            }
+        },
+    );
 
-            Some(Ok(Event::default()
-                .event(event_name)
-                .data(data)))
-        })
-        .filter_map(|x| async { x });
+    let event_stream = futures::stream::iter(backlog).chain(live).map(Ok);
 
-    Sse::new(event_stream).keep_alive(
+    Ok(Sse::new(event_stream).keep_alive(
         KeepAlive::new()
-            .interval(Duration::from_secs(15))
+            .interval(state.config.events.keep_alive())
             .text("keep-alive"),
+    ))
+}
+
+// Tell a subscriber how many live events it missed; it can reconnect with its
+// `Last-Event-ID` to have them replayed, or refetch
+fn lagged_event(skipped: u64, lag_count: u64, disconnecting: bool) -> Event {
+    let data = serde_json::json!({
+        "type": LAGGED,
+        "skipped": skipped,
+        "lag_count": lag_count,
+        "disconnecting": disconnecting,
+    });
+    Event::default().event(LAGGED).data(data.to_string())
+}
+
+// The state a snapshot-mode stream starts from; its id is the last event it
+// covers, so a reconnect resumes right after it
+fn snapshot_event(snapshot: &StateSnapshot) -> Event {
+    let data = serde_json::json!({
+        "type": SNAPSHOT,
+        "event_id": snapshot.event_id,
+        "accounts": snapshot.accounts,
+        "batches": snapshot.batches,
+    });
+
+    Event::default()
+        .event(SNAPSHOT)
+        .id(snapshot.event_id.to_string())
+        .data(data.to_string())
+}
+
+// Render an envelope as an SSE event named after its type
+fn sse_event(envelope: &EventEnvelope) -> Option<Event> {
+    let data = match serde_json::to_string(envelope) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("Failed to serialize event: {}", e);
+            return None;
+        }
+    };
+
+    Some(
+        Event::default()
+            .event(envelope.event.event_name())
+            .id(envelope.event_id.to_string())
+            .data(data),
     )
 }
 
-/// Get all accounts
+/// List accounts, newest first, one page at a time
 #[utoipa::path(
     get,
     path = "/api/v1/accounts",
+    params(AccountQuery),
     responses(
-        (status = 200, description = "List of accounts retrieved successfully", body = Vec<Account>),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Page of accounts retrieved successfully", body = AccountPage),
+        (status = 400, description = "Invalid query parameters or cursor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "accounts"
 )]
 pub async fn get_accounts(
+    principal: Principal,
     State(state): State<AppState>,
-) -> Result<Json<Vec<Account>>, StatusCode> {
-    let accounts = sqlx::query_as::<_, Account>("SELECT * FROM accounts ORDER BY created_at DESC")
-        .fetch_all(&state.pool)
-        .await
-        .map_err(|e| {
-            eprintln!("Database error fetching accounts: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    ApiQuery(query): ApiQuery<AccountQuery>,
+) -> Result<Json<Page<Account>>, ApiError> {
+    let page = PageRequest::new(query.limit, query.cursor.as_deref())?;
+    let accounts = state
+        .store
+        .list_accounts(&query, principal.account_scope(), &page)
+        .await?;
 
     Ok(Json(accounts))
 }
@@ -91,23 +434,25 @@ pub async fn get_accounts(
     ),
     responses(
         (status = 200, description = "Account found", body = Account),
-        (status = 404, description = "Account not found"),
-        (status = 500, description = "Internal server error")
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Account not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "accounts"
 )]
 pub async fn get_account(
+    principal: Principal,
     State(state): State<AppState>,
-    Path(id): Path<i64>,
-) -> Result<Json<Account>, StatusCode> {
-    let account = sqlx::query_as::<_, Account>("SELECT * FROM accounts WHERE id = ?")
-        .bind(id)
-        .fetch_one(&state.pool)
-        .await
-        .map_err(|e| {
-            eprintln!("Database error fetching account: {}", e);
-            StatusCode::NOT_FOUND
-        })?;
+    ApiPath(id): ApiPath<i64>,
+) -> Result<Json<Account>, ApiError> {
+    principal.authorize(id, KeyAccess::Read)?;
+
+    let account = state
+        .store
+        .get_account(id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Account {} not found", id)))?;
 
     Ok(Json(account))
 }
@@ -119,34 +464,30 @@ pub async fn get_account(
     request_body = CreateAccountRequest,
     responses(
         (status = 200, description = "Account created successfully", body = Account),
-        (status = 400, description = "Bad request"),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Bad request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Account name already exists", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid request body", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "accounts"
 )]
 pub async fn create_account(
+    principal: Principal,
+    ctx: EventContext,
     State(state): State<AppState>,
-    JsonExtract(payload): JsonExtract<CreateAccountRequest>,
-) -> Result<Json<Account>, StatusCode> {
-    let account = sqlx::query_as::<_, Account>(
-        r#"
-        INSERT INTO accounts (name, hostname, created_at, updated_at)
-        VALUES (?, ?, datetime('now'), datetime('now'))
-        RETURNING *
-        "#,
-    )
-    .bind(&payload.name)
-    .bind(&payload.hostname)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Database error inserting account: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let _ = state.event_sender.send(BrokerEvent::AccountCreated {
-            account: account.clone(),
-        });
+    ApiJson(payload): ApiJson<CreateAccountRequest>,
+) -> Result<Json<Account>, ApiError> {
+    principal.authorize_all(KeyAccess::Write)?;
+
+    let account = state
+        .store
+        .create_account(&payload, &ctx)
+        .await
+        .map_err(|e| account_name_conflict(e, &payload.name))?;
+
+    state.dispatch().await;
 
     println!(
         "Account created - ID: {}, Name: {}, Hostname: {}",
@@ -166,37 +507,31 @@ pub async fn create_account(
     request_body = CreateAccountRequest,
     responses(
         (status = 200, description = "Account updated successfully", body = Account),
-        (status = 404, description = "Account not found"),
-        (status = 500, description = "Internal server error")
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Account not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Account name already exists", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "accounts"
 )]
 pub async fn update_account(
+    principal: Principal,
+    ctx: EventContext,
     State(state): State<AppState>,
-    Path(account_id): Path<i64>,
-    JsonExtract(payload): JsonExtract<CreateAccountRequest>,
-) -> Result<Json<Account>, StatusCode> {
-    let account = sqlx::query_as::<_, Account>(
-        r#"
-        UPDATE accounts 
-        SET name = ?, hostname = ?, updated_at = datetime('now')
-        WHERE id = ?
-        RETURNING *
-        "#,
-    )
-    .bind(&payload.name)
-    .bind(&payload.hostname)
-    .bind(account_id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Database error updating account: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let _ = state.event_sender.send(BrokerEvent::AccountUpdated {
-            account: account.clone(),
-        });
+    ApiPath(account_id): ApiPath<i64>,
+    ApiJson(payload): ApiJson<CreateAccountRequest>,
+) -> Result<Json<Account>, ApiError> {
+    principal.authorize(account_id, KeyAccess::Write)?;
+
+    let account = state
+        .store
+        .update_account(account_id, &payload, &ctx)
+        .await
+        .map_err(|e| account_name_conflict(e, &payload.name))?
+        .ok_or_else(|| ApiError::not_found(format!("Account {} not found", account_id)))?;
+
+    state.dispatch().await;
 
     println!(
         "Account updated - ID: {}, Name: {}, Hostname: {}",
@@ -211,90 +546,43 @@ pub async fn update_account(
     post,
     path = "/api/v1/accounts/{id}/batches",
     params(
-        ("id" = i64, Path, description = "Account ID")
+        ("id" = i64, Path, description = "Account ID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replay the stored response when a request is retried with this key")
     ),
     request_body = CreateBatchRequest,
     responses(
         (status = 200, description = "Batch created successfully", body = BatchResponse),
-        (status = 400, description = "Bad request"),
-        (status = 404, description = "Account not found"),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Bad request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Idempotency-Key reused with a different request or still in progress", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Account does not exist, invalid request body, or bets whose selections do not fit the bet type, listed in `errors`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "batches"
 )]
 pub async fn create_batch(
-    Path(account_id): Path<i64>,
+    principal: Principal,
+    ctx: EventContext,
+    ApiPath(account_id): ApiPath<i64>,
     State(state): State<AppState>,
-    JsonExtract(payload): JsonExtract<CreateBatchRequest>,
-) -> Result<Json<BatchResponse>, StatusCode> {
-    let mut tx = state.pool.begin().await.map_err(|e| {
-        eprintln!("Transaction begin error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let meta_json = serde_json::to_string(&payload.meta).map_err(|e| {
-        eprintln!("JSON serialization error: {}", e);
-        StatusCode::BAD_REQUEST
-    })?;
-
-    let batch = sqlx::query_as::<_, Batch>(
-        r#"
-        INSERT INTO batches (meta, account_id, created_at, updated_at)
-        VALUES (?, ?, datetime('now'), datetime('now'))
-        RETURNING *
-        "#,
-    )
-    .bind(&meta_json)
-    .bind(account_id)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| {
-        eprintln!("Database error creating batch: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let mut bets = Vec::new();
-    for bet_request in payload.bets {
-        let bet = sqlx::query_as::<_, Bet>(
-            r#"
-            INSERT INTO bets (id, selection, stake, cost, batch_id)
-            VALUES (?, ?, ?, ?, ?)
-            RETURNING *
-            "#,
-        )
-        .bind(bet_request.id)
-        .bind(&bet_request.selection)
-        .bind(bet_request.stake)
-        .bind(bet_request.cost)
-        .bind(batch.id)
-        .fetch_one(&mut *tx)
+    ApiJson(payload): ApiJson<CreateBatchRequest>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    principal.authorize(account_id, KeyAccess::Write)?;
+    payload.validate()?;
+
+    let response = state
+        .store
+        .create_batch(account_id, &payload, &ctx)
         .await
-        .map_err(|e| {
-            eprintln!("Database error creating bet: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+        .map_err(|e| match ApiError::from(e) {
+            ApiError::ForeignKeyViolation(_) => {
+                ApiError::ForeignKeyViolation(format!("Account {} does not exist", account_id))
+            }
+            other => other,
         })?;
-        bets.push(bet);
-    }
-
-    tx.commit().await.map_err(|e| {
-        eprintln!("Transaction commit error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
 
-    let response = BatchResponse {
-        id: batch.id,
-        completed: batch.completed,
-        created_at: batch.created_at.to_rfc3339(),
-        updated_at: batch.updated_at.to_rfc3339(),
-        meta: batch.meta,
-        account_id: batch.account_id,
-        bets: bets.clone(),
-    };
-    
-    let _ = state.event_sender.send(BrokerEvent::BatchCreated {
-        batch: response.clone(),
-    });
+    state.dispatch().await;
 
     println!(
         "Batch created - ID: {}, Account: {}, Bets: {}",
@@ -304,197 +592,244 @@ pub async fn create_batch(
     Ok(Json(response))
 }
 
-/// Get all batches for an account
+/// List an account's batches, newest first, one page at a time
 #[utoipa::path(
     get,
     path = "/api/v1/accounts/{id}/batches",
     params(
-        ("id" = i64, Path, description = "Account ID")
+        ("id" = i64, Path, description = "Account ID"),
+        BatchQuery
     ),
     responses(
-        (status = 200, description = "List of batches retrieved successfully", body = Vec<BatchResponse>),
-        (status = 404, description = "Account not found"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Page of batches retrieved successfully", body = BatchPage),
+        (status = 400, description = "Invalid query parameters or cursor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Account not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "batches"
 )]
 pub async fn account_batches(
-    Path(account_id): Path<i64>,
+    principal: Principal,
+    ApiPath(account_id): ApiPath<i64>,
     State(state): State<AppState>,
-) -> Result<Json<Vec<BatchResponse>>, StatusCode> {
-    let batches = sqlx::query_as::<_, Batch>(
-        r#"
-        SELECT * FROM batches 
-        WHERE account_id = ? 
-        ORDER BY created_at DESC
-        "#,
-    )
-    .bind(account_id)
-    .fetch_all(&state.pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Database error fetching batches: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let mut batch_responses = Vec::new();
-
-    for batch in batches {
-        let bets = sqlx::query_as::<_, Bet>(
-            r#"
-            SELECT * FROM bets 
-            WHERE batch_id = ? 
-            ORDER BY id
-            "#,
-        )
-        .bind(batch.id)
-        .fetch_all(&state.pool)
-        .await
-        .map_err(|e| {
-            eprintln!("Database error fetching bets for batch {}: {}", batch.id, e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    ApiQuery(query): ApiQuery<BatchQuery>,
+) -> Result<Json<Page<BatchResponse>>, ApiError> {
+    principal.authorize(account_id, KeyAccess::Read)?;
 
-        let response = BatchResponse {
-            id: batch.id,
-            completed: batch.completed,
-            created_at: batch.created_at.to_rfc3339(),
-            updated_at: batch.updated_at.to_rfc3339(),
-            meta: batch.meta.clone(),
-            account_id: batch.account_id,
-            bets,
-        };
-
-        batch_responses.push(response);
-    }
+    let page = PageRequest::new(query.limit, query.cursor.as_deref())?;
+    let batches = state.store.list_batches(account_id, &query, &page).await?;
 
     println!(
         "Retrieved {} batches for account {}",
-        batch_responses.len(),
+        batches.items.len(),
         account_id
     );
 
-    Ok(Json(batch_responses))
+    Ok(Json(batches))
 }
 
-/// Update multiple bets in a batch
+/// Get a single batch with its bets
+#[utoipa::path(
+    get,
+    path = "/api/v1/accounts/{id}/batches/{batch_id}",
+    params(
+        ("id" = i64, Path, description = "Account ID"),
+        ("batch_id" = i64, Path, description = "Batch ID")
+    ),
+    responses(
+        (status = 200, description = "Batch found", body = BatchResponse),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Batch not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "batches"
+)]
+pub async fn get_account_batch(
+    principal: Principal,
+    ApiPath((account_id, batch_id)): ApiPath<(i64, i64)>,
+    State(state): State<AppState>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    principal.authorize(account_id, KeyAccess::Read)?;
+
+    let batch = state
+        .store
+        .get_batch(account_id, batch_id)
+        .await?
+        .ok_or_else(|| {
+            ApiError::not_found(format!(
+                "Batch {} not found for account {}",
+                batch_id, account_id
+            ))
+        })?;
+
+    Ok(Json(batch))
+}
+
+/// Get a single bet
+#[utoipa::path(
+    get,
+    path = "/api/v1/accounts/{id}/batches/{batch_id}/bets/{bet_id}",
+    params(
+        ("id" = i64, Path, description = "Account ID"),
+        ("batch_id" = i64, Path, description = "Batch ID"),
+        ("bet_id" = i64, Path, description = "Bet ID (pid)")
+    ),
+    responses(
+        (status = 200, description = "Bet found", body = Bet),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Bet not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "bets"
+)]
+pub async fn get_account_batch_bet(
+    principal: Principal,
+    ApiPath((account_id, batch_id, bet_id)): ApiPath<(i64, i64, i64)>,
+    State(state): State<AppState>,
+) -> Result<Json<Bet>, ApiError> {
+    principal.authorize(account_id, KeyAccess::Read)?;
+
+    let bet = state
+        .store
+        .get_bet(account_id, batch_id, bet_id)
+        .await?
+        .ok_or_else(|| {
+            ApiError::not_found(format!("Bet {} not found in batch {}", bet_id, batch_id))
+        })?;
+
+    Ok(Json(bet))
+}
+
+/// Update the status of several bets in a batch in one transaction. Bets
+/// missing from the batch are reported per item; an illegal transition for
+/// any bet rejects the whole request.
 #[utoipa::path(
     patch,
     path = "/api/v1/accounts/{id}/batches/{batch_id}/bets",
     params(
         ("id" = i64, Path, description = "Account ID"),
-        ("batch_id" = i64, Path, description = "Batch ID")
+        ("batch_id" = i64, Path, description = "Batch ID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replay the stored response when a request is retried with this key")
     ),
     request_body = Vec<BetUpdateRequest>,
     responses(
-        (status = 200, description = "Bets updated successfully", body = Vec<Bet>),
-        (status = 404, description = "Batch not found"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Outcome for each requested bet, in request order", body = BulkBetUpdateResponse),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Batch not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Illegal status transition, completed batch, or Idempotency-Key conflict", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid request body or a bet listed twice", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "bets"
 )]
 pub async fn update_account_batch_bets(
-    Path((account_id, batch_id)): Path<(i64, i64)>,
+    principal: Principal,
+    ctx: EventContext,
+    ApiPath((account_id, batch_id)): ApiPath<(i64, i64)>,
     State(state): State<AppState>,
-    Json(bets): Json<Vec<BetUpdateRequest>>,
-) -> Result<Json<Vec<Bet>>, StatusCode> {
-    let mut tx = state.pool.begin().await.map_err(|e| {
-        eprintln!("Transaction begin error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let mut updated_bets = Vec::new();
-    for bet in bets {
-        let result = sqlx::query_as::<_, Bet>(
-            r#"
-            UPDATE bets SET status = 'successful'
-            WHERE pid = ? AND batch_id = ?
-            RETURNING *
-            "#,
-        )
-        .bind(bet.pid)
-        .bind(batch_id)
-        .fetch_one(&mut *tx)
-        .await
-        .map_err(|e| {
-            eprintln!("Failed to update bet status to successful: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-        updated_bets.push(result);
+    ApiJson(updates): ApiJson<Vec<BetUpdateRequest>>,
+) -> Result<Json<BulkBetUpdateResponse>, ApiError> {
+    principal.authorize(account_id, KeyAccess::Write)?;
+
+    let mut seen = HashSet::new();
+    if let Some(update) = updates.iter().find(|update| !seen.insert(update.pid)) {
+        return Err(ApiError::Unprocessable(format!(
+            "Bet {} is listed more than once",
+            update.pid
+        )));
     }
 
-    tx.commit().await.map_err(|e| {
-        eprintln!("Transaction commit error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    
-    let _ = state.event_sender.send(BrokerEvent::BatchBetsUpdated {
-        batch_id,
-        account_id,
-        bets: updated_bets.clone(),
-    });
+    let changes: Vec<BetStatusChange> = updates.iter().map(BetUpdateRequest::to_change).collect();
+    let updated = state
+        .store
+        .update_bets_status(account_id, batch_id, &changes, &ctx)
+        .await?;
+
+    let results: Vec<BetUpdateResult> = updates
+        .iter()
+        .zip(updated)
+        .map(|(update, bet)| BetUpdateResult {
+            pid: update.pid,
+            outcome: if bet.is_some() {
+                BetUpdateOutcome::Updated
+            } else {
+                BetUpdateOutcome::NotFound
+            },
+            bet,
+        })
+        .collect();
+
+    state.dispatch().await;
 
-    Ok(Json(updated_bets))
+    Ok(Json(BulkBetUpdateResponse { results }))
 }
 
-/// Update a single bet status
+/// Update a single bet status. Pending bets can be settled as successful or
+/// failed; settled bets cannot change unless the admin key sends `force`,
+/// which skips the transition rules. Bets in completed batches never change,
+/// even with `force`.
 #[utoipa::path(
     patch,
     path = "/api/v1/accounts/{id}/batches/{batch_id}/bets/{bet_id}",
     params(
         ("id" = i64, Path, description = "Account ID"),
         ("batch_id" = i64, Path, description = "Batch ID"),
-        ("bet_id" = i64, Path, description = "Bet ID (pid)")
+        ("bet_id" = i64, Path, description = "Bet ID (pid)"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replay the stored response when a request is retried with this key")
     ),
     request_body = UpdateBetStatusRequest,
     responses(
         (status = 200, description = "Bet status updated successfully", body = Bet),
-        (status = 404, description = "Bet not found"),
-        (status = 500, description = "Internal server error")
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account, or `force` without the admin key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Bet not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Illegal status transition without `force`, completed batch (even with `force`), or Idempotency-Key conflict", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "bets"
 )]
 pub async fn update_account_batch_bet(
-    Path((account_id, batch_id, bet_id)): Path<(i64, i64, i64)>,
+    principal: Principal,
+    ctx: EventContext,
+    ApiPath((account_id, batch_id, bet_id)): ApiPath<(i64, i64, i64)>,
     State(state): State<AppState>,
-    Json(payload): Json<UpdateBetStatusRequest>,
-) -> Result<Json<Bet>, StatusCode> {
-    let validated_status = match payload.status {
-        BetStatus::Pending => "pending",
-        BetStatus::Successful => "successful",
-        BetStatus::Failed => "failed",
+    ApiJson(payload): ApiJson<UpdateBetStatusRequest>,
+) -> Result<Json<Bet>, ApiError> {
+    principal.authorize(account_id, KeyAccess::Write)?;
+
+    let from = if payload.force {
+        principal.require_admin()?;
+        BetStatus::ALL.to_vec()
+    } else {
+        BetStatus::previous_statuses(payload.status)
+    };
+    let change = BetStatusChange {
+        pid: bet_id,
+        from,
+        status: payload.status,
+        reference: payload.reference,
+        message: payload.message,
     };
 
-    let updated_bet = sqlx::query_as::<_, Bet>(
-        r#"
-        UPDATE bets 
-        SET status = ? 
-        WHERE pid = ? AND batch_id = ?
-        RETURNING *
-        "#
-    )
-    .bind(validated_status)
-    .bind(bet_id)
-    .bind(batch_id)
-    .fetch_optional(&state.pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Database error updating bet: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let updated_bet = state
+        .store
+        .update_bet_status(account_id, batch_id, &change, &ctx)
+        .await?;
 
     match updated_bet {
         Some(bet) => {
-            let _ = state.event_sender.send(BrokerEvent::BetStatusUpdated {
-                bet: bet.clone(),
-            });
+            state.dispatch().await;
             Ok(Json(bet))
         }
-        None => {
-            eprintln!("❌ Bet not found: pid={}, batch_id={}", bet_id, batch_id);
-            Err(StatusCode::NOT_FOUND)
-        }
+        None => Err(ApiError::not_found(format!(
+            "Bet {} not found in batch {}",
+            bet_id, batch_id
+        ))),
     }
 }
 
@@ -508,39 +843,29 @@ pub async fn update_account_batch_bet(
     ),
     responses(
         (status = 200, description = "Batch completed successfully"),
-        (status = 404, description = "Batch not found"),
-        (status = 500, description = "Internal server error")
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Batch not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "batches"
 )]
 pub async fn complete_account_batch(
+    principal: Principal,
+    ctx: EventContext,
     State(state): State<AppState>,
-    Path((account_id, batch_id)): Path<(i64, i64)>,
-) -> Result<(), StatusCode> {
-    let result = sqlx::query(
-        r#"
-        UPDATE batches 
-        SET completed = 1, updated_at = datetime('now')
-        WHERE id = ? AND account_id = ? AND completed = 0
-        "#,
-    )
-    .bind(batch_id)
-    .bind(account_id)
-    .execute(&state.pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Failed to mark batch as completed: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
+    ApiPath((account_id, batch_id)): ApiPath<(i64, i64)>,
+) -> Result<(), ApiError> {
+    principal.authorize(account_id, KeyAccess::Write)?;
+
+    if !state.store.complete_batch(account_id, batch_id, &ctx).await? {
+        return Err(ApiError::not_found(format!(
+            "Open batch {} not found for account {}",
+            batch_id, account_id
+        )));
     }
 
-    let _ = state.event_sender.send(BrokerEvent::BatchCompleted {
-        id: batch_id,
-        account_id,
-    });
+    state.dispatch().await;
 
     Ok(())
 }
@@ -554,34 +879,38 @@ pub async fn complete_account_batch(
     ),
     responses(
         (status = 204, description = "Account deleted successfully"),
-        (status = 404, description = "Account not found"),
-        (status = 500, description = "Internal server error")
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key lacks the required access for this account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Account not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     ),
     tag = "accounts"
 )]
 pub async fn delete_account(
+    principal: Principal,
+    ctx: EventContext,
     State(state): State<AppState>,
-    Path(account_id): Path<i64>,
-) -> Result<StatusCode, StatusCode> {
-    // Delete account (CASCADE will handle batches and bets automatically)
-    let result = sqlx::query("DELETE FROM accounts WHERE id = ?")
-        .bind(account_id)
-        .execute(&state.pool)
-        .await
-        .map_err(|e| {
-            eprintln!("Database error deleting account: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    ApiPath(account_id): ApiPath<i64>,
+) -> Result<StatusCode, ApiError> {
+    principal.authorize(account_id, KeyAccess::Write)?;
 
-    // Check if account existed
-    if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
+    // Delete account (CASCADE will handle batches and bets automatically)
+    if !state.store.delete_account(account_id, &ctx).await? {
+        return Err(ApiError::not_found(format!("Account {} not found", account_id)));
     }
 
-    let _ = state.event_sender.send(BrokerEvent::AccountDeleted {
-            id: account_id,
-        });
+    state.dispatch().await;
 
     println!("Account deleted - ID: {} (cascaded batches and bets)", account_id);
     Ok(StatusCode::NO_CONTENT)
 }
+
+// Give unique violations on the account name a useful detail message
+fn account_name_conflict(err: StoreError, name: &str) -> ApiError {
+    match ApiError::from(err) {
+        ApiError::UniqueViolation(_) => {
+            ApiError::UniqueViolation(format!("Account name '{}' already exists", name))
+        }
+        other => other,
+    }
+}
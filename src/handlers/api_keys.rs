@@ -0,0 +1,127 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+
+use crate::auth::{display_prefix, generate_key, hash_key, Principal};
+use crate::error::{ApiError, ApiJson, ApiPath};
+use crate::handlers::accounts::AppState;
+use crate::models::api_key::*;
+
+/// List all API keys, including revoked ones
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/api-keys",
+    responses(
+        (status = 200, description = "API keys retrieved successfully", body = Vec<ApiKey>),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn list_api_keys(
+    principal: Principal,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ApiKey>>, ApiError> {
+    principal.require_admin()?;
+
+    Ok(Json(state.store.list_api_keys().await?))
+}
+
+/// Create an API key. The key is only returned in this response.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "API key created", body = CreatedApiKey),
+        (status = 400, description = "Bad request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid request body or unknown account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn create_api_key(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedApiKey>), ApiError> {
+    principal.require_admin()?;
+
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::Unprocessable("name must not be empty".to_string()));
+    }
+
+    let account_ids = match payload.account_ids {
+        Some(ids) if ids.is_empty() => {
+            return Err(ApiError::Unprocessable(
+                "account_ids must not be empty; omit it to cover every account".to_string(),
+            ))
+        }
+        Some(mut ids) => {
+            ids.sort_unstable();
+            ids.dedup();
+            Some(ids)
+        }
+        None => None,
+    };
+
+    let key = generate_key();
+    let new_key = NewApiKey {
+        name: name.to_string(),
+        key_hash: hash_key(&key),
+        key_prefix: display_prefix(&key),
+        access: payload.access,
+        account_ids,
+    };
+
+    let api_key = state
+        .store
+        .create_api_key(&new_key)
+        .await
+        .map_err(|e| match ApiError::from(e) {
+            ApiError::ForeignKeyViolation(_) => ApiError::ForeignKeyViolation(
+                "account_ids references an account that does not exist".to_string(),
+            ),
+            other => other,
+        })?;
+
+    println!(
+        "API key created - ID: {}, Name: {}, Access: {}",
+        api_key.id, api_key.name, api_key.access
+    );
+
+    Ok((StatusCode::CREATED, Json(CreatedApiKey { key, api_key })))
+}
+
+/// Revoke an API key
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/api-keys/{id}",
+    params(
+        ("id" = i64, Path, description = "API key ID")
+    ),
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No active API key with this ID", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn revoke_api_key(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<i64>,
+) -> Result<StatusCode, ApiError> {
+    principal.require_admin()?;
+
+    if !state.store.revoke_api_key(id).await? {
+        return Err(ApiError::not_found(format!("Active API key {} not found", id)));
+    }
+
+    println!("API key revoked - ID: {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
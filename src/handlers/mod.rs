@@ -1 +1,6 @@
-pub mod accounts;
\ No newline at end of file
+pub mod accounts;
+pub mod api_keys;
+pub mod races;
+pub mod reports;
+pub mod webhooks;
+pub mod ws;
@@ -0,0 +1,80 @@
+use axum::{extract::State, response::Json};
+
+use crate::auth::Principal;
+use crate::error::{ApiError, ApiJson, ApiPath};
+use crate::handlers::accounts::AppState;
+use crate::models::event::EventContext;
+use crate::models::race::*;
+
+/// Get a race's result. Results hold placings and dividends but nothing
+/// about any account, so every valid key can read them, whatever its scope.
+#[utoipa::path(
+    get,
+    path = "/api/v1/races/{race_id}/result",
+    params(
+        ("race_id" = i64, Path, description = "Race ID, as in batch `meta.race_id`")
+    ),
+    responses(
+        (status = 200, description = "Race result retrieved successfully", body = RaceResult),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No result set for the race", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "races"
+)]
+pub async fn get_race_result(
+    State(state): State<AppState>,
+    ApiPath(race_id): ApiPath<i64>,
+) -> Result<Json<RaceResult>, ApiError> {
+    let result = state
+        .store
+        .get_race_result(race_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("No result for race {}", race_id)))?;
+
+    Ok(Json(result))
+}
+
+/// Set a race's result, replacing any previous one, and settle the
+/// successful bets of every batch on the race. Every winning selection with
+/// successful bets needs a dividend. Bets whose settlement changes emit
+/// `bet_settled`; batches left with no unsettled bet emit `batch_settled`.
+#[utoipa::path(
+    put,
+    path = "/api/v1/races/{race_id}/result",
+    params(
+        ("race_id" = i64, Path, description = "Race ID, as in batch `meta.race_id`")
+    ),
+    request_body = SetRaceResultRequest,
+    responses(
+        (status = 200, description = "Result set and bets settled", body = RaceSettlement),
+        (status = 400, description = "Invalid race ID", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The result would pay a batch more than 1000000000.00", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid placings, a dividend for a selection that did not win, or a winner with bets but no dividend", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "races"
+)]
+pub async fn set_race_result(
+    principal: Principal,
+    ctx: EventContext,
+    State(state): State<AppState>,
+    ApiPath(race_id): ApiPath<i64>,
+    ApiJson(payload): ApiJson<SetRaceResultRequest>,
+) -> Result<Json<RaceSettlement>, ApiError> {
+    principal.require_admin()?;
+    let bets = state.store.race_selections(race_id).await?;
+    payload.validate(&bets)?;
+
+    let settlement = state.store.set_race_result(race_id, &payload, &ctx).await?;
+
+    state.dispatch().await;
+
+    println!(
+        "Race result set - Race: {}, Settled: {}, Won: {}, Unsettled: {}",
+        race_id, settlement.settled, settlement.won, settlement.unsettled
+    );
+    Ok(Json(settlement))
+}
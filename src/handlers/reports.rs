@@ -0,0 +1,130 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+
+use crate::auth::Principal;
+use crate::error::{ApiError, ApiJson, ApiPath, ApiQuery};
+use crate::handlers::accounts::AppState;
+use crate::models::api_key::KeyAccess;
+use crate::models::money::Currency;
+use crate::models::report::*;
+
+/// List FX rates
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/fx-rates",
+    responses(
+        (status = 200, description = "FX rates retrieved successfully", body = Vec<FxRate>),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn list_fx_rates(
+    principal: Principal,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<FxRate>>, ApiError> {
+    principal.require_admin()?;
+
+    Ok(Json(state.store.list_fx_rates().await?))
+}
+
+/// Set the rate from one currency to another, replacing any previous rate.
+/// The reverse direction is a separate rate.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/fx-rates/{from}/{to}",
+    params(
+        ("from" = Currency, Path, description = "Currency converted from"),
+        ("to" = Currency, Path, description = "Currency converted to")
+    ),
+    request_body = SetFxRateRequest,
+    responses(
+        (status = 200, description = "FX rate set", body = FxRate),
+        (status = 400, description = "Unsupported currency", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid rate, or both currencies are the same", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn set_fx_rate(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiPath((from, to)): ApiPath<(Currency, Currency)>,
+    ApiJson(payload): ApiJson<SetFxRateRequest>,
+) -> Result<Json<FxRate>, ApiError> {
+    principal.require_admin()?;
+
+    if from == to {
+        return Err(ApiError::Unprocessable(format!("Cannot set a rate from {} to itself", from)));
+    }
+    let rate = state.store.set_fx_rate(from, to, payload.rate).await?;
+
+    println!("FX rate set - {}/{}: {}", from, to, rate.rate);
+    Ok(Json(rate))
+}
+
+/// Delete the rate from one currency to another
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/fx-rates/{from}/{to}",
+    params(
+        ("from" = Currency, Path, description = "Currency converted from"),
+        ("to" = Currency, Path, description = "Currency converted to")
+    ),
+    responses(
+        (status = 204, description = "FX rate deleted"),
+        (status = 400, description = "Unsupported currency", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "FX rate not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn delete_fx_rate(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiPath((from, to)): ApiPath<(Currency, Currency)>,
+) -> Result<StatusCode, ApiError> {
+    principal.require_admin()?;
+
+    if !state.store.delete_fx_rate(from, to).await? {
+        return Err(ApiError::not_found(format!("FX rate from {} to {} not found", from, to)));
+    }
+
+    println!("FX rate deleted - {}/{}", from, to);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Stake and cost of the bets the key can read, summed per currency and
+/// converted into one base currency with the stored FX rates
+#[utoipa::path(
+    get,
+    path = "/api/v1/reports/totals",
+    params(TotalsQuery),
+    responses(
+        (status = 200, description = "Totals in the base currency", body = TotalsReport),
+        (status = 400, description = "Invalid query parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "API key cannot read the requested account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "No FX rate from a currency with bets to the base currency", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "reports"
+)]
+pub async fn totals_report(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<TotalsQuery>,
+) -> Result<Json<TotalsReport>, ApiError> {
+    if let Some(account_id) = query.account_id {
+        principal.authorize(account_id, KeyAccess::Read)?;
+    }
+
+    let totals = state.store.stake_totals(&query, principal.account_scope()).await?;
+    let rates = state.store.list_fx_rates().await?;
+
+    Ok(Json(TotalsReport::convert(query.currency, totals, &rates)?))
+}
@@ -0,0 +1,180 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+
+use crate::auth::Principal;
+use crate::error::{ApiError, ApiJson, ApiPath, ApiQuery};
+use crate::handlers::accounts::AppState;
+use crate::models::account::BrokerEvent;
+use crate::models::pagination::{Page, PageRequest};
+use crate::models::webhook::*;
+use crate::webhooks::generate_secret;
+
+/// List webhook subscriptions
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/webhooks",
+    responses(
+        (status = 200, description = "Webhooks retrieved successfully", body = Vec<Webhook>),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn list_webhooks(
+    principal: Principal,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Webhook>>, ApiError> {
+    principal.require_admin()?;
+
+    Ok(Json(state.store.list_webhooks().await?))
+}
+
+/// Subscribe a URL to events. The signing secret is only returned in this
+/// response.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "Webhook created", body = CreatedWebhook),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid URL, unknown event type or unknown account", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn create_webhook(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedWebhook>), ApiError> {
+    principal.require_admin()?;
+
+    let url = payload.url.trim();
+    let valid_url = reqwest::Url::parse(url)
+        .map(|parsed| matches!(parsed.scheme(), "http" | "https") && parsed.has_host())
+        .unwrap_or(false);
+    if !valid_url {
+        return Err(ApiError::Unprocessable(format!(
+            "url '{}' must be an absolute http(s) URL",
+            url
+        )));
+    }
+
+    let event_types = match payload.event_types {
+        Some(types) if types.is_empty() => {
+            return Err(ApiError::Unprocessable(
+                "event_types must not be empty; omit it to receive every type".to_string(),
+            ))
+        }
+        Some(mut types) => {
+            if let Some(unknown) = types.iter().find(|t| !BrokerEvent::TYPES.contains(&t.as_str())) {
+                return Err(ApiError::Unprocessable(format!(
+                    "Unknown event type '{}'; expected one of {}",
+                    unknown,
+                    BrokerEvent::TYPES.join(", ")
+                )));
+            }
+            types.sort_unstable();
+            types.dedup();
+            Some(types)
+        }
+        None => None,
+    };
+
+    let secret = generate_secret();
+    let new_webhook = NewWebhook {
+        url: url.to_string(),
+        secret: secret.clone(),
+        event_types,
+        account_id: payload.account_id,
+    };
+
+    let webhook = state
+        .store
+        .create_webhook(&new_webhook)
+        .await
+        .map_err(|e| match ApiError::from(e) {
+            ApiError::ForeignKeyViolation(_) => ApiError::ForeignKeyViolation(format!(
+                "Account {} does not exist",
+                payload.account_id.unwrap_or_default()
+            )),
+            other => other,
+        })?;
+
+    println!("Webhook created - ID: {}, URL: {}", webhook.id, webhook.url);
+
+    Ok((StatusCode::CREATED, Json(CreatedWebhook { secret, webhook })))
+}
+
+/// Delete a webhook subscription along with its delivery history
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/webhooks/{id}",
+    params(
+        ("id" = i64, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Webhook not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn delete_webhook(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<i64>,
+) -> Result<StatusCode, ApiError> {
+    principal.require_admin()?;
+
+    if !state.store.delete_webhook(id).await? {
+        return Err(ApiError::not_found(format!("Webhook {} not found", id)));
+    }
+
+    println!("Webhook deleted - ID: {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A webhook's delivery history, newest first; `status=dead` lists the
+/// dead-letter queue
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/webhooks/{id}/deliveries",
+    params(
+        ("id" = i64, Path, description = "Webhook ID"),
+        DeliveryQuery
+    ),
+    responses(
+        (status = 200, description = "Page of deliveries retrieved successfully", body = WebhookDeliveryPage),
+        (status = 400, description = "Invalid query parameters or cursor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin key required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Webhook not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "admin"
+)]
+pub async fn list_webhook_deliveries(
+    principal: Principal,
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<i64>,
+    ApiQuery(query): ApiQuery<DeliveryQuery>,
+) -> Result<Json<Page<WebhookDelivery>>, ApiError> {
+    principal.require_admin()?;
+
+    let page = PageRequest::new(query.limit, query.cursor.as_deref())?;
+    if state.store.get_webhook(id).await?.is_none() {
+        return Err(ApiError::not_found(format!("Webhook {} not found", id)));
+    }
+
+    let deliveries = state
+        .store
+        .list_webhook_deliveries(id, query.status, &page)
+        .await?;
+
+    Ok(Json(deliveries))
+}
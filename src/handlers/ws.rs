@@ -0,0 +1,214 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{Json, Response},
+};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{interval_at, Instant};
+
+use crate::auth::Principal;
+use crate::error::{ApiError, ApiJson, ApiPath};
+use crate::events::{EventFilter, LAGGED};
+use crate::handlers::accounts::{self, AppState};
+use crate::models::account::UpdateBetStatusRequest;
+use crate::models::api_key::KeyAccess;
+use crate::models::event::{EventContext, EventEnvelope};
+use crate::models::ws::{HeartbeatResult, SubscribeResult, WsCommand, WsRequest, WsResponse};
+
+/// Open a WebSocket carrying events and executor commands
+///
+/// The server sends the same event envelopes as `/sse` for every active
+/// subscription, and a `response` with the command's `request_id` for each
+/// command. A new socket has no subscriptions; send `subscribe` first.
+#[utoipa::path(
+    get,
+    path = "/ws",
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol"),
+        (status = 401, description = "Missing or invalid API key", body = ProblemDetails, content_type = "application/problem+json")
+    ),
+    tag = "events"
+)]
+pub async fn ws_handler(
+    State(state): State<AppState>,
+    principal: Principal,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| run_socket(socket, state, principal))
+}
+
+// Per-socket state: who is connected and what they subscribed to
+struct Session {
+    principal: Principal,
+    subscriptions: Vec<(u64, EventFilter)>,
+    last_subscription_id: u64,
+}
+
+impl Session {
+    fn wants(&self, envelope: &EventEnvelope) -> bool {
+        self.principal.can_see(&envelope.event)
+            && self.subscriptions.iter().any(|(_, filter)| filter.matches(&envelope.event))
+    }
+}
+
+// Interleave commands from the client with broadcast events until either
+// side goes away. Commands run one at a time, so a command's response is
+// sent before the events it caused.
+async fn run_socket(mut socket: WebSocket, state: AppState, principal: Principal) {
+    let connection = state.connections.register(principal.label());
+    let mut rx = state.event_sender.subscribe();
+    let mut session = Session {
+        principal,
+        subscriptions: Vec::new(),
+        last_subscription_id: 0,
+    };
+    let keep_alive = state.config.events.keep_alive();
+    let mut ping = interval_at(Instant::now() + keep_alive, keep_alive);
+    let max_lags = state.config.events.max_lags;
+
+    loop {
+        let mut close_after = false;
+        let outgoing = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => to_text(&handle_message(&state, &mut session, &text).await),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by the protocol layer
+                Some(Ok(_)) => continue,
+            },
+            received = rx.recv() => match received {
+                Ok(envelope) if session.wants(&envelope) => to_text(&envelope),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    let lag_count = connection.record_lag(skipped);
+                    close_after = max_lags > 0 && lag_count >= max_lags;
+                    if close_after {
+                        connection.record_disconnect();
+                    }
+                    to_text(&serde_json::json!({
+                        "type": LAGGED,
+                        "skipped": skipped,
+                        "lag_count": lag_count,
+                        "disconnecting": close_after,
+                    }))
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = ping.tick() => Some(Message::Ping(Vec::new())),
+        };
+
+        if let Some(message) = outgoing {
+            if socket.send(message).await.is_err() {
+                break;
+            }
+        }
+        if close_after {
+            let _ = socket.send(Message::Close(None)).await;
+            break;
+        }
+    }
+}
+
+fn to_text<T: Serialize>(value: &T) -> Option<Message> {
+    match serde_json::to_string(value) {
+        Ok(json) => Some(Message::Text(json)),
+        Err(e) => {
+            eprintln!("Failed to serialize WebSocket message: {}", e);
+            None
+        }
+    }
+}
+
+// Parse and run one command, always producing a response
+async fn handle_message(state: &AppState, session: &mut Session, text: &str) -> WsResponse {
+    let request = match serde_json::from_str::<WsRequest>(text) {
+        Ok(request) => request,
+        Err(e) => {
+            // Still correlate the error if the request id is readable
+            let request_id = serde_json::from_str::<JsonValue>(text)
+                .ok()
+                .and_then(|value| value.get("request_id")?.as_str().map(str::to_string));
+            let error = ApiError::bad_request(format!("Invalid command: {}", e));
+            return response(request_id, Err(error));
+        }
+    };
+
+    let result = execute(state, session, &request.request_id, request.command).await;
+    response(Some(request.request_id), result)
+}
+
+fn response(request_id: Option<String>, result: Result<JsonValue, ApiError>) -> WsResponse {
+    let (result, error) = match result {
+        Ok(value) => (Some(value), None),
+        Err(e) => (None, Some(e.to_problem())),
+    };
+    WsResponse {
+        message_type: "response",
+        request_id,
+        ok: error.is_none(),
+        result,
+        error,
+    }
+}
+
+// Changes go through the HTTP handlers, so permissions, transition rules and
+// event dispatch are exactly those of the REST API
+async fn execute(
+    state: &AppState,
+    session: &mut Session,
+    request_id: &str,
+    command: WsCommand,
+) -> Result<JsonValue, ApiError> {
+    let principal = session.principal.clone();
+    let ctx = EventContext {
+        correlation_id: Some(request_id.to_string()),
+        actor: Some(principal.actor()),
+    };
+
+    match command {
+        WsCommand::UpdateBetStatus { account_id, batch_id, pid, status, reference, message, force } => {
+            let update = UpdateBetStatusRequest { status, reference, message, force };
+            let Json(bet) = accounts::update_account_batch_bet(
+                principal,
+                ctx,
+                ApiPath((account_id, batch_id, pid)),
+                State(state.clone()),
+                ApiJson(update),
+            )
+            .await?;
+            to_value(&bet)
+        }
+        WsCommand::CompleteBatch { account_id, batch_id } => {
+            accounts::complete_account_batch(principal, ctx, State(state.clone()), ApiPath((account_id, batch_id)))
+                .await?;
+            Ok(JsonValue::Null)
+        }
+        WsCommand::Heartbeat => to_value(&HeartbeatResult { server_time: Utc::now() }),
+        WsCommand::Subscribe { account_id, batch_id, types } => {
+            if let Some(account_id) = account_id {
+                principal.authorize(account_id, KeyAccess::Read)?;
+            }
+            let filter = EventFilter::new(account_id, batch_id, types.as_deref())?;
+            session.last_subscription_id += 1;
+            let subscription_id = session.last_subscription_id;
+            session.subscriptions.push((subscription_id, filter));
+            to_value(&SubscribeResult { subscription_id })
+        }
+        WsCommand::Unsubscribe { subscription_id } => {
+            let before = session.subscriptions.len();
+            session.subscriptions.retain(|(id, _)| *id != subscription_id);
+            if session.subscriptions.len() == before {
+                return Err(ApiError::not_found(format!("Subscription {} not found", subscription_id)));
+            }
+            Ok(JsonValue::Null)
+        }
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<JsonValue, ApiError> {
+    serde_json::to_value(value).map_err(|e| ApiError::Internal(e.to_string()))
+}
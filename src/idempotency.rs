@@ -0,0 +1,246 @@
+use std::{collections::HashMap, future::Future, time::Duration};
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+
+use crate::auth::Principal;
+use crate::error::{ApiError, ApiPath};
+use crate::handlers::accounts::AppState;
+use crate::models::api_key::KeyAccess;
+use crate::models::idempotency::IdempotencyRecord;
+use crate::store::StoreResult;
+
+/// Request header carrying the client's idempotency key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set to `true` on replayed responses
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest key accepted
+pub const MAX_KEY_LEN: usize = 255;
+
+// Same limit axum applies to JSON bodies by default
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+// Tries at storing a finished request's response, waiting `STORE_BACKOFF`
+// after the first failure and twice as long after each one since
+const STORE_ATTEMPTS: u32 = 4;
+const STORE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Middleware for write routes under `/accounts/:id`. A request with an
+/// `Idempotency-Key` header runs once per account and key; retries with the
+/// same method, path and body get the stored response back, and a different
+/// request under the same key is rejected with 409. Only successful
+/// responses are stored, so a failed request can be retried with its key.
+/// A claim whose request never finished lapses after
+/// `idempotency.lease_secs`. So does one whose response could not be stored
+/// even after retrying, and a retry after that runs the request again.
+pub async fn idempotent(
+    State(state): State<AppState>,
+    principal: Principal,
+    ApiPath(params): ApiPath<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.run(request).await);
+    };
+    let key = key
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LEN)
+        .ok_or_else(|| {
+            ApiError::bad_request(format!(
+                "Idempotency-Key must be 1 to {} visible ASCII characters",
+                MAX_KEY_LEN
+            ))
+        })?
+        .to_string();
+
+    let account_id = params
+        .get("id")
+        .and_then(|id| id.parse::<i64>().ok())
+        .ok_or_else(|| ApiError::bad_request("Invalid account id"))?;
+
+    // Check scope before touching stored responses, so keys cannot be probed
+    principal.authorize(account_id, KeyAccess::Write)?;
+
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| ApiError::bad_request("Request body too large"))?;
+    let request_hash = request_hash(&parts, &body);
+
+    let idempotency = &state.config.idempotency;
+    let existing = state
+        .store
+        .reserve_idempotency_key(account_id, &key, &request_hash, idempotency.ttl(), idempotency.lease())
+        .await?;
+    match existing {
+        None => {}
+        Some(record) if record.request_hash != request_hash => {
+            return Err(ApiError::conflict(
+                "Idempotency-Key was already used with a different request",
+            ))
+        }
+        Some(IdempotencyRecord {
+            status_code: Some(status),
+            response_body: Some(body),
+            ..
+        }) => return Ok(replay(status, body)),
+        Some(_) => {
+            return Err(ApiError::conflict(
+                "A request with this Idempotency-Key is still in progress",
+            ))
+        }
+    }
+
+    // The request runs in its own task so the key is completed or released
+    // even if the client disconnects and this future is dropped; otherwise
+    // the claim would block every retry until its lease runs out
+    let request = Request::from_parts(parts, Body::from(body));
+    let task = tokio::spawn(run_claimed(state, account_id, key, next.run(request)));
+    task.await.map_err(|e| {
+        eprintln!("Idempotent request task failed: {}", e);
+        ApiError::Internal("Request failed".to_string())
+    })
+}
+
+// Run a request holding a claimed key, then store its response for replay,
+// or release the key if it failed. Store failures here are logged rather
+// than reported, so the client always gets the request's own response.
+async fn run_claimed(
+    state: AppState,
+    account_id: i64,
+    key: String,
+    request: impl Future<Output = Response>,
+) -> Response {
+    let response = request.await;
+
+    // Left claimed, the key only blocks retries until its lease runs out
+    if !response.status().is_success() {
+        if let Err(e) = state.store.release_idempotency_key(account_id, &key).await {
+            eprintln!("Failed to release idempotency key {}: {:?}", key, e);
+        }
+        return response;
+    }
+
+    // The change is already committed, so storing the response is retried;
+    // if that still fails, the key lapses with its lease and a retry after
+    // that runs the request again
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            eprintln!("Failed to buffer response for idempotency key {}: {}", key, e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let response_body = String::from_utf8_lossy(&body);
+    let stored = retry(STORE_ATTEMPTS, STORE_BACKOFF, || {
+        state
+            .store
+            .complete_idempotency_key(account_id, &key, parts.status.as_u16(), &response_body)
+    })
+    .await;
+    if let Err(e) = stored {
+        eprintln!(
+            "Failed to store response for idempotency key {} after {} attempts: {:?}",
+            key, STORE_ATTEMPTS, e
+        );
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+// Run `op` up to `attempts` times until it succeeds, doubling the wait
+// between tries from `backoff`; returns the last error
+async fn retry<T, F, Fut>(attempts: u32, backoff: Duration, mut op: F) -> StoreResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = StoreResult<T>>,
+{
+    let mut wait = backoff;
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < attempts => {
+                eprintln!("Store call failed, retrying in {:?}: {:?}", wait, e);
+                tokio::time::sleep(wait).await;
+                wait *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// JSON bodies are hashed in canonical form so key order and whitespace do
+// not count as a different request
+fn request_hash(parts: &Parts, body: &Bytes) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str());
+    hasher.update(b"\n");
+    hasher.update(parts.uri.path());
+    hasher.update(b"\n");
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => hasher.update(json.to_string()),
+        Err(_) => hasher.update(body),
+    }
+    hex::encode(hasher.finalize())
+}
+
+fn replay(status: i32, body: String) -> Response {
+    let status = u16::try_from(status)
+        .ok()
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .unwrap_or(StatusCode::OK);
+
+    let has_body = !body.is_empty();
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    if has_body {
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+    headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::StoreError;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn store_calls_are_retried_until_they_succeed() {
+        let calls = AtomicU32::new(0);
+        let flaky = |fail_times: u32| {
+            calls.store(0, Ordering::SeqCst);
+            let calls = &calls;
+            move || async move {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    n if n < fail_times => Err(StoreError::Conflict(format!("failure {}", n + 1))),
+                    _ => Ok(()),
+                }
+            }
+        };
+
+        assert!(retry(3, Duration::from_millis(1), flaky(2)).await.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let Err(StoreError::Conflict(detail)) = retry(3, Duration::from_millis(1), flaky(5)).await else {
+            panic!("expected the last error");
+        };
+        assert_eq!(detail, "failure 3");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,319 @@
+pub mod auth;
+pub mod config;
+pub mod error;
+pub mod events;
+pub mod handlers;
+pub mod idempotency;
+pub mod models;
+pub mod request_id;
+pub mod sinks;
+pub mod store;
+pub mod webhooks;
+
+use axum::{
+    middleware,
+    routing::{get, post, put, patch, delete},
+    Router,
+};
+use axum::http::{HeaderName, HeaderValue};
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use handlers::accounts::{
+    create_account,
+    update_account,
+    get_accounts,
+    get_account,
+    delete_account,
+    create_batch,
+    account_batches,
+    get_account_batch,
+    get_account_batch_bet,
+    update_account_batch_bet,
+    update_account_batch_bets,
+    complete_account_batch,
+    sse_handler,
+    account_events,
+    sse_connections,
+    AppState
+};
+use handlers::api_keys::{create_api_key, list_api_keys, revoke_api_key};
+use handlers::webhooks::{create_webhook, delete_webhook, list_webhook_deliveries, list_webhooks};
+use handlers::reports::{delete_fx_rate, list_fx_rates, set_fx_rate, totals_report};
+use handlers::races::{get_race_result, set_race_result};
+use handlers::ws::ws_handler;
+use config::Config;
+use events::SseConnections;
+use tokio::sync::{broadcast, Mutex, Notify};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_swagger_ui::SwaggerUi;
+use error::{FieldError, ProblemDetails};
+use models::pagination::{AccountPage, BatchPage, WebhookDeliveryPage};
+use models::account::{
+    Account, CreateAccountRequest, Batch, BatchMeta, BatchResponse, 
+    Bet, CreateBatchRequest, CreateBetRequest, 
+    UpdateBetStatusRequest, BetUpdateRequest, BetStatus,
+    BetUpdateOutcome, BetUpdateResult, BulkBetUpdateResponse, BrokerEvent
+};
+use models::money::{Currency, ExchangeRate, Money};
+use models::selection::BetType;
+use models::race::{BetResult, Dividend, RaceResult, RaceSettlement, SetRaceResultRequest};
+use models::report::{ConvertedTotals, FxRate, SetFxRateRequest, TotalsReport};
+use models::event::{Actor, ConnectionStats, EventEnvelope, SseConnectionsReport, StateSnapshot};
+use models::ws::{HeartbeatResult, SubscribeResult, WsCommand, WsRequest, WsResponse};
+use models::webhook::{CreateWebhookRequest, CreatedWebhook, DeliveryStatus, Webhook, WebhookDelivery};
+use models::api_key::{
+    ApiKey as ApiKeyRecord, CreateApiKeyRequest, CreatedApiKey, KeyAccess
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        handlers::accounts::get_accounts,
+        handlers::accounts::create_account,
+        handlers::accounts::get_account,
+        handlers::accounts::update_account,
+        handlers::accounts::delete_account,
+        handlers::accounts::create_batch,
+        handlers::accounts::account_batches,
+        handlers::accounts::get_account_batch,
+        handlers::accounts::get_account_batch_bet,
+        handlers::accounts::update_account_batch_bet,
+        handlers::accounts::update_account_batch_bets,
+        handlers::accounts::complete_account_batch,
+        handlers::accounts::sse_handler,
+        handlers::accounts::account_events,
+        handlers::ws::ws_handler,
+        handlers::api_keys::list_api_keys,
+        handlers::api_keys::create_api_key,
+        handlers::api_keys::revoke_api_key,
+        handlers::accounts::sse_connections,
+        handlers::webhooks::list_webhooks,
+        handlers::webhooks::create_webhook,
+        handlers::webhooks::delete_webhook,
+        handlers::webhooks::list_webhook_deliveries,
+        handlers::reports::list_fx_rates,
+        handlers::reports::set_fx_rate,
+        handlers::reports::delete_fx_rate,
+        handlers::reports::totals_report,
+        handlers::races::get_race_result,
+        handlers::races::set_race_result,
+    ),
+    components(
+        schemas(
+            Account, 
+            CreateAccountRequest, 
+            Batch, 
+            BatchMeta,
+            BatchResponse, 
+            Bet, 
+            CreateBatchRequest, 
+            CreateBetRequest,
+            Money, 
+            Currency,
+            ExchangeRate,
+            BetType,
+            UpdateBetStatusRequest, 
+            BetUpdateRequest,
+            BetUpdateOutcome,
+            BetUpdateResult,
+            BulkBetUpdateResponse,
+            BetStatus,
+            AccountPage,
+            BatchPage,
+            ProblemDetails,
+            FieldError,
+            ApiKeyRecord,
+            CreateApiKeyRequest,
+            CreatedApiKey,
+            KeyAccess,
+            ConnectionStats,
+            SseConnectionsReport,
+            BrokerEvent,
+            EventEnvelope,
+            Actor,
+            StateSnapshot,
+            WsRequest,
+            WsCommand,
+            WsResponse,
+            HeartbeatResult,
+            SubscribeResult,
+            Webhook,
+            CreateWebhookRequest,
+            CreatedWebhook,
+            WebhookDelivery,
+            WebhookDeliveryPage,
+            DeliveryStatus,
+            FxRate,
+            SetFxRateRequest,
+            ConvertedTotals,
+            TotalsReport,
+            BetResult,
+            Dividend,
+            RaceResult,
+            SetRaceResultRequest,
+            RaceSettlement
+        )
+    ),
+    modifiers(&SecurityAddon),
+    security(
+        ("bearer_auth" = []),
+        ("api_key_header" = [])
+    ),
+    tags(
+        (name = "accounts", description = "Account management endpoints"),
+        (name = "batches", description = "Batch management endpoints"),
+        (name = "bets", description = "Bet management endpoints"),
+        (name = "events", description = "Server-sent event streams, filtered on the server"),
+        (name = "admin", description = "API key, webhook and FX rate management, admin key only"),
+        (name = "reports", description = "Aggregate reports across accounts"),
+        (name = "races", description = "Race results and bet settlement")
+    ),
+    info(
+        title = "Betstream API",
+        version = "1.0.0",
+        description = "API for managing betting accounts, batches, and bets",
+        contact(
+            name = "API Support",
+            email = "support@betstream.com"
+        )
+    )
+)]
+pub struct ApiDoc;
+
+// Declares how API keys are presented; applied to every operation above
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("API key sent as `Authorization: Bearer <key>`"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "api_key_header",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+    }
+}
+
+/// Connect to the configured database, run its migrations and build the
+/// full router. `main` serves the result; tests drive it in-process.
+pub async fn build_app(config: Config) -> anyhow::Result<Router> {
+    let store = store::connect(&config.database).await?;
+    events::spawn_pruner(store.clone(), config.events.log_retention());
+    let sinks = sinks::from_config(&config.sinks).await?;
+    let app_state = create_app_state(store, config, sinks);
+    events::spawn_dispatcher(app_state.clone(), app_state.config.events.dispatch_interval());
+    if app_state.config.events.cross_instance {
+        events::spawn_relay(app_state.clone(), app_state.config.events.relay_interval())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to start the event relay: {:?}", e))?;
+    }
+    webhooks::spawn_worker(
+        app_state.store.clone(),
+        app_state.config.webhooks.clone(),
+        app_state.webhook_wakeup.clone(),
+    );
+    Ok(router(app_state))
+}
+
+/// Route table over an already constructed [`AppState`]
+pub fn router(app_state: AppState) -> Router {
+    let cors = cors_layer(&app_state.config);
+    // Replays retried writes that carry an `Idempotency-Key`
+    let idempotent = || middleware::from_fn_with_state(app_state.clone(), idempotency::idempotent);
+
+    // Everything under /api/v1 and /sse requires an API key
+    let protected = Router::new()
+        // Account routes
+        .route("/api/v1/accounts", get(get_accounts))
+        .route("/api/v1/accounts", post(create_account))
+        .route("/api/v1/accounts/:id", get(get_account))
+        .route("/api/v1/accounts/:id", put(update_account))
+        .route("/api/v1/accounts/:id", delete(delete_account))
+        .route("/api/v1/accounts/:id/batches", post(create_batch).route_layer(idempotent()))
+        .route("/api/v1/accounts/:id/batches", get(account_batches))
+        .route("/api/v1/accounts/:id/batches/:batch_id", get(get_account_batch))
+        .route("/api/v1/accounts/:id/batches/:batch_id/bets/:bet_id", get(get_account_batch_bet))
+        .route("/api/v1/accounts/:id/batches/:batch_id/bets/:bet_id", patch(update_account_batch_bet).route_layer(idempotent()))
+        .route("/api/v1/accounts/:id/batches/:batch_id/bets", patch(update_account_batch_bets).route_layer(idempotent()))
+        .route("/api/v1/accounts/:id/batches/:batch_id", delete(complete_account_batch))
+        .route("/api/v1/accounts/:id/events", get(account_events))
+        // Admin routes
+        .route("/api/v1/admin/api-keys", get(list_api_keys))
+        .route("/api/v1/admin/api-keys", post(create_api_key))
+        .route("/api/v1/admin/api-keys/:id", delete(revoke_api_key))
+        .route("/api/v1/admin/sse-connections", get(sse_connections))
+        .route("/api/v1/admin/webhooks", get(list_webhooks))
+        .route("/api/v1/admin/webhooks", post(create_webhook))
+        .route("/api/v1/admin/webhooks/:id", delete(delete_webhook))
+        .route("/api/v1/admin/webhooks/:id/deliveries", get(list_webhook_deliveries))
+        .route("/api/v1/admin/fx-rates", get(list_fx_rates))
+        .route("/api/v1/admin/fx-rates/:from/:to", put(set_fx_rate))
+        .route("/api/v1/admin/fx-rates/:from/:to", delete(delete_fx_rate))
+        .route("/api/v1/reports/totals", get(totals_report))
+        .route("/api/v1/races/:race_id/result", get(get_race_result))
+        .route("/api/v1/races/:race_id/result", put(set_race_result))
+        .route("/sse", get(sse_handler))
+        .route("/ws", get(ws_handler))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::require_api_key,
+        ));
+
+    Router::new()
+        .merge(SwaggerUi::new("/swagger-ui")
+            .url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .route("/", get(|| async { "Betting API 🎲" }))
+        .route("/health", get(|| async { "OK" }))
+        .merge(protected)
+        .layer(middleware::from_fn(request_id::assign_request_id))
+        .layer(cors)
+        .with_state(app_state)
+}
+
+pub fn create_app_state(
+    store: Arc<dyn store::Store>,
+    config: Config,
+    sinks: Vec<Box<dyn sinks::EventSink>>,
+) -> AppState {
+    let (event_sender, _) = broadcast::channel(config.events.channel_capacity);
+    AppState {
+        store,
+        event_sender,
+        dispatch_lock: Arc::new(Mutex::new(0)),
+        connections: Arc::new(SseConnections::default()),
+        webhook_wakeup: Arc::new(Notify::new()),
+        sinks: Arc::new(sinks),
+        config: Arc::new(config),
+    }
+}
+
+// `*` allows any origin, otherwise only the listed origins (validated at load)
+fn cors_layer(config: &Config) -> CorsLayer {
+    let origins = &config.server.cors_origins;
+    let allow_origin = if origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok()),
+        )
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers([HeaderName::from_static(request_id::REQUEST_ID_HEADER)])
+}
@@ -1,9 +1,14 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value as JsonValue;
+use serde_json::{Map as JsonMap, Value as JsonValue};
 use sqlx::FromRow;
 use chrono::{DateTime, Utc};
 use std::str::FromStr;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::{ApiError, FieldError};
+use crate::models::money::{Currency, Money};
+use crate::models::race::BetResult;
+use crate::models::selection::{BetType, Selection};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "lowercase")]
@@ -35,9 +40,53 @@ impl FromStr for BetStatus {
     }
 }
 
+impl BetStatus {
+    pub const ALL: [BetStatus; 3] = [BetStatus::Pending, BetStatus::Successful, BetStatus::Failed];
+
+    /// Statuses a bet may move to from this one. Settled bets are final and
+    /// can only be changed with an admin override.
+    pub fn next_statuses(self) -> &'static [BetStatus] {
+        match self {
+            BetStatus::Pending => &[BetStatus::Successful, BetStatus::Failed],
+            BetStatus::Successful | BetStatus::Failed => &[],
+        }
+    }
+
+    pub fn can_transition_to(self, next: BetStatus) -> bool {
+        self.next_statuses().contains(&next)
+    }
+
+    /// Statuses a bet may be in to move to `next`
+    pub fn previous_statuses(next: BetStatus) -> Vec<BetStatus> {
+        Self::ALL
+            .into_iter()
+            .filter(|status| status.can_transition_to(next))
+            .collect()
+    }
+}
+
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateBetStatusRequest {
     pub status: BetStatus,
+    /// Bookmaker ticket or transaction reference
+    pub reference: Option<String>,
+    /// Executor note, e.g. why the bet failed
+    pub message: Option<String>,
+    /// Skip the transition rules, e.g. to correct a settled bet. Admin key
+    /// only. Bets in completed batches stay frozen regardless.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// A guarded status change as applied by the store: the bet must currently
+/// be in one of `from`. `None` result fields keep their stored value.
+#[derive(Debug, Clone)]
+pub struct BetStatusChange {
+    pub pid: i64,
+    pub from: Vec<BetStatus>,
+    pub status: BetStatus,
+    pub reference: Option<String>,
+    pub message: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -62,7 +111,10 @@ pub enum BrokerEvent {
     },
     
     #[serde(rename = "bet_status_updated")]
-    BetStatusUpdated { bet: Bet },
+    BetStatusUpdated {
+        account_id: i64,
+        bet: Bet,
+    },
     
     #[serde(rename = "batch_bets_updated")]
     BatchBetsUpdated { 
@@ -70,9 +122,44 @@ pub enum BrokerEvent {
         account_id: i64,
         bets: Vec<Bet>,
     },
+
+    /// A successful bet's result came in, or was corrected
+    #[serde(rename = "bet_settled")]
+    BetSettled {
+        account_id: i64,
+        race_id: i64,
+        bet: Bet,
+    },
+
+    /// Every bet in the batch is settled or failed; totals cover the settled
+    /// bets, in `currency`
+    #[serde(rename = "batch_settled")]
+    BatchSettled {
+        batch_id: i64,
+        account_id: i64,
+        race_id: i64,
+        settled: i64,
+        won: i64,
+        stake: Money,
+        payout: Money,
+        currency: Currency,
+    },
 }
 
 impl BrokerEvent {
+    /// Every value [`BrokerEvent::event_name`] can return
+    pub const TYPES: [&'static str; 9] = [
+        "account_created",
+        "account_updated",
+        "account_deleted",
+        "batch_created",
+        "batch_completed",
+        "bet_status_updated",
+        "batch_bets_updated",
+        "bet_settled",
+        "batch_settled",
+    ];
+
     // Helper to extract event name for SSE
     pub fn event_name(&self) -> &str {
         match self {
@@ -83,6 +170,35 @@ impl BrokerEvent {
             Self::BatchCompleted { .. } => "batch_completed",
             Self::BetStatusUpdated { .. } => "bet_status_updated",
             Self::BatchBetsUpdated { .. } => "batch_bets_updated",
+            Self::BetSettled { .. } => "bet_settled",
+            Self::BatchSettled { .. } => "batch_settled",
+        }
+    }
+
+    /// Account the event belongs to, used to scope subscribers
+    pub fn account_id(&self) -> i64 {
+        match self {
+            Self::AccountCreated { account } | Self::AccountUpdated { account } => account.id,
+            Self::AccountDeleted { id } => *id,
+            Self::BatchCreated { batch } => batch.account_id,
+            Self::BatchCompleted { account_id, .. }
+            | Self::BetStatusUpdated { account_id, .. }
+            | Self::BatchBetsUpdated { account_id, .. }
+            | Self::BetSettled { account_id, .. }
+            | Self::BatchSettled { account_id, .. } => *account_id,
+        }
+    }
+
+    /// Batch the event belongs to; `None` for account-level events
+    pub fn batch_id(&self) -> Option<i64> {
+        match self {
+            Self::AccountCreated { .. }
+            | Self::AccountUpdated { .. }
+            | Self::AccountDeleted { .. } => None,
+            Self::BatchCreated { batch } => Some(batch.id),
+            Self::BatchCompleted { id, .. } => Some(*id),
+            Self::BetStatusUpdated { bet, .. } | Self::BetSettled { bet, .. } => Some(bet.batch_id),
+            Self::BatchBetsUpdated { batch_id, .. } | Self::BatchSettled { batch_id, .. } => Some(*batch_id),
         }
     }
 }
@@ -92,6 +208,9 @@ pub struct Account {
     pub id: i64,
     pub name: String,
     pub hostname: String,
+    /// Currency of the account's tote; new batches and bets inherit it
+    #[serde(default)]
+    pub currency: Currency,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -100,11 +219,67 @@ pub struct Account {
 pub struct CreateAccountRequest {
     pub name: String,
     pub hostname: String,
+    pub currency: Currency,
 }
 
-#[derive(Deserialize, ToSchema)]
+/// Filters and paging for the account listing
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AccountQuery {
+    /// Maximum number of accounts to return (1-200, default 50)
+    pub limit: Option<u32>,
+    /// Cursor returned as `next_cursor` by the previous page
+    pub cursor: Option<String>,
+    /// Only return the account with this exact name
+    pub name: Option<String>,
+    /// Only return accounts on this hostname
+    pub hostname: Option<String>,
+}
+
+/// One item of a bulk bet update
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct BetUpdateRequest {
     pub pid: i64,
+    pub status: BetStatus,
+    /// Bookmaker ticket or transaction reference
+    pub reference: Option<String>,
+    /// Executor note, e.g. why the bet failed
+    pub message: Option<String>,
+}
+
+impl BetUpdateRequest {
+    /// The change to apply, allowed only along the transition table
+    pub fn to_change(&self) -> BetStatusChange {
+        BetStatusChange {
+            pid: self.pid,
+            from: BetStatus::previous_statuses(self.status),
+            status: self.status,
+            reference: self.reference.clone(),
+            message: self.message.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BetUpdateOutcome {
+    Updated,
+    NotFound,
+}
+
+/// Per-item result of a bulk bet update, in request order
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BetUpdateResult {
+    pub pid: i64,
+    pub outcome: BetUpdateOutcome,
+    /// The bet after the update; absent when it was not found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bet: Option<Bet>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkBetUpdateResponse {
+    pub results: Vec<BetUpdateResult>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
@@ -113,22 +288,136 @@ pub struct Batch {
     pub completed: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    pub meta: JsonValue,
+    #[sqlx(flatten)]
+    pub meta: BatchMeta,
     pub account_id: i64,
+    /// The account's currency when the batch was created
+    #[serde(default)]
+    pub currency: Currency,
+}
+
+/// What a batch bets on. The known fields are stored as columns; any other
+/// keys are kept as sent and travel next to them.
+///
+/// `race_id` and `bet_type` are required to create a batch. Only batches
+/// created before meta was validated can lack them, when their values could
+/// not be read; the originals are kept as `legacy_race_id` and
+/// `legacy_bet_type`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct BatchMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = 7)]
+    pub race_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bet_type: Option<BetType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Flemington")]
+    pub meeting: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub venue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    /// Free-form keys, e.g. `name`
+    #[serde(flatten)]
+    #[sqlx(rename = "meta", json)]
+    #[schema(value_type = HashMap<String, Object>)]
+    pub extra: JsonMap<String, JsonValue>,
+}
+
+/// Filters and paging for an account's batch listing
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BatchQuery {
+    /// Maximum number of batches to return (1-200, default 50)
+    pub limit: Option<u32>,
+    /// Cursor returned as `next_cursor` by the previous page
+    pub cursor: Option<String>,
+    /// Only return completed (`true`) or open (`false`) batches
+    pub completed: Option<bool>,
+    /// Only return batches created at or after this time (RFC 3339)
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only return batches created before this time (RFC 3339)
+    pub created_before: Option<DateTime<Utc>>,
+    /// Match `meta.race_id`
+    pub race_id: Option<i64>,
+    /// Match `meta.bet_type`, case-insensitively
+    pub bet_type: Option<BetType>,
+    /// Match `meta.meeting`
+    pub meeting: Option<String>,
+    /// Match `meta.venue`
+    pub venue: Option<String>,
+    /// Match `meta.strategy`
+    pub strategy: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateBatchRequest {
-    pub meta: JsonValue,
+    /// Needs `race_id` and `bet_type`; every bet's selection must fit the
+    /// bet type, e.g. `"1/2/3"` for a TRIFECTA
+    pub meta: BatchMeta,
     pub bets: Vec<CreateBetRequest>,
 }
 
+impl CreateBatchRequest {
+    /// Check `meta` has a positive `race_id` and a `bet_type`, and every
+    /// bet's selection parses as one for that bet type. Reports every invalid
+    /// field, not just the first.
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let mut errors = Vec::new();
+
+        match self.meta.race_id {
+            None => errors.push(FieldError::new("/meta/race_id", "race_id is required")),
+            Some(race_id) if race_id < 1 => {
+                errors.push(FieldError::new("/meta/race_id", format!("race_id must be positive, got {}", race_id)))
+            }
+            Some(_) => {}
+        }
+        let bet_type = self.meta.bet_type;
+        if bet_type.is_none() {
+            errors.push(FieldError::new("/meta/bet_type", "bet_type is required"));
+        }
+
+        for (i, bet) in self.bets.iter().enumerate() {
+            let parsed = match bet_type {
+                Some(bet_type) => Selection::for_bet_type(&bet.selection, bet_type),
+                None => bet.selection.parse::<Selection>(),
+            };
+            if let Err(e) = parsed {
+                errors.push(FieldError::new(format!("/bets/{}/selection", i), format!("Bet {}: {}", bet.id, e)));
+            }
+            for (field, amount) in [("stake", bet.stake), ("cost", bet.cost)] {
+                if amount > Money::MAX {
+                    errors.push(FieldError::new(
+                        format!("/bets/{}/{}", i, field),
+                        format!("Bet {}: {} {} is above the most, {}", bet.id, field, amount, Money::MAX),
+                    ));
+                }
+            }
+        }
+        let stake = self.bets.iter().map(|bet| bet.stake).sum::<Option<Money>>();
+        let cost = self.bets.iter().map(|bet| bet.cost).sum::<Option<Money>>();
+        for (field, total) in [("stake", stake), ("cost", cost)] {
+            if total.is_none_or(|total| total > Money::MAX) {
+                errors.push(FieldError::new(
+                    "/bets",
+                    format!("Total {} is above the most a batch may carry, {}", field, Money::MAX),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(ApiError::validation(errors))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateBetRequest {
     pub id: i64,
     pub selection: String,
-    pub stake: f64,
-    pub cost: f64,
+    pub stake: Money,
+    pub cost: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
@@ -136,10 +425,25 @@ pub struct Bet {
     pub pid: i64,
     pub id: i64,
     pub selection: String,
-    pub stake: f64,
-    pub cost: f64,
+    pub stake: Money,
+    pub cost: Money,
+    /// Currency of `stake` and `cost`, that of the batch
+    #[serde(default)]
+    pub currency: Currency,
     pub status: String,
     pub batch_id: i64,
+    /// Bookmaker ticket or transaction reference reported by the executor
+    pub reference: Option<String>,
+    /// Executor note, e.g. why the bet failed
+    pub message: Option<String>,
+    /// How the bet finished, once its race result is in; successful bets only
+    #[serde(default)]
+    pub result: Option<BetResult>,
+    /// Return on the stake when settled, zero for a lost bet
+    #[serde(default)]
+    pub payout: Option<Money>,
+    #[serde(default)]
+    pub settled_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -148,7 +452,144 @@ pub struct BatchResponse {
     pub completed: bool,
     pub created_at: String,
     pub updated_at: String,
-    pub meta: JsonValue,
+    pub meta: BatchMeta,
     pub account_id: i64,
+    #[serde(default)]
+    pub currency: Currency,
+    /// Sum of the bets' stakes, in `currency`
+    #[serde(default)]
+    pub total_stake: Money,
+    /// Sum of the bets' costs, in `currency`
+    #[serde(default)]
+    pub total_cost: Money,
     pub bets: Vec<Bet>,
 }
+
+impl BatchResponse {
+    pub fn new(batch: Batch, bets: Vec<Bet>) -> Self {
+        BatchResponse {
+            id: batch.id,
+            completed: batch.completed,
+            created_at: batch.created_at.to_rfc3339(),
+            updated_at: batch.updated_at.to_rfc3339(),
+            meta: batch.meta,
+            account_id: batch.account_id,
+            currency: batch.currency,
+            // Created batches are checked to total within range; saturate
+            // rather than overflow on anything stored otherwise
+            total_stake: bets.iter().map(|bet| bet.stake).sum::<Option<Money>>().unwrap_or(Money::from_minor(i64::MAX)),
+            total_cost: bets.iter().map(|bet| bet.cost).sum::<Option<Money>>().unwrap_or(Money::from_minor(i64::MAX)),
+            bets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use BetStatus::*;
+
+    #[test]
+    fn transition_table() {
+        let table = [
+            (Pending, Pending, false),
+            (Pending, Successful, true),
+            (Pending, Failed, true),
+            (Successful, Pending, false),
+            (Successful, Successful, false),
+            (Successful, Failed, false),
+            (Failed, Pending, false),
+            (Failed, Successful, false),
+            (Failed, Failed, false),
+        ];
+        assert_eq!(table.len(), BetStatus::ALL.len() * BetStatus::ALL.len());
+
+        for (from, to, allowed) in table {
+            assert_eq!(from.can_transition_to(to), allowed, "{} -> {}", from, to);
+            assert_eq!(
+                BetStatus::previous_statuses(to).contains(&from),
+                allowed,
+                "{} -> {}",
+                from,
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn batches_report_every_invalid_field() {
+        let request = |meta: JsonValue, selections: &[&str]| CreateBatchRequest {
+            meta: serde_json::from_value(meta).unwrap(),
+            bets: selections
+                .iter()
+                .enumerate()
+                .map(|(i, selection)| CreateBetRequest {
+                    id: i as i64 + 1,
+                    selection: selection.to_string(),
+                    stake: Money::ZERO,
+                    cost: Money::ZERO,
+                })
+                .collect(),
+        };
+
+        let valid = request(json!({ "race_id": 3, "bet_type": "TRIFECTA" }), &["1/2/3", "4/5/6"]);
+        assert!(valid.validate().is_ok());
+
+        let Err(ApiError::Validation(detail, errors)) =
+            request(json!({ "race_id": 3, "bet_type": "trifecta" }), &["1/2/3", "1/2", "1/1/2"]).validate()
+        else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(detail, "2 fields are invalid");
+        let pointers: Vec<_> = errors.iter().map(|e| e.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/bets/1/selection", "/bets/2/selection"]);
+        assert_eq!(errors[0].detail, "Bet 2: TRIFECTA takes 3 runners, got 2");
+
+        let Err(ApiError::Validation(_, errors)) = request(json!({ "race_id": 0 }), &["A"]).validate() else {
+            panic!("expected a validation error");
+        };
+        let pointers: Vec<_> = errors.iter().map(|e| e.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/meta/race_id", "/meta/bet_type", "/bets/0/selection"]);
+
+        let mut large = request(json!({ "race_id": 3, "bet_type": "WIN" }), &["1", "2"]);
+        large.bets[0].stake = Money::from_minor(Money::MAX.minor_units() + 1);
+        large.bets[1].cost = Money::MAX;
+        large.bets[0].cost = Money::from_minor(1);
+        let Err(ApiError::Validation(_, errors)) = large.validate() else {
+            panic!("expected a validation error");
+        };
+        let pointers: Vec<_> = errors.iter().map(|e| e.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/bets/0/stake", "/bets", "/bets"]);
+        assert_eq!(errors[0].detail, "Bet 1: stake 1000000000.01 is above the most, 1000000000.00");
+    }
+
+    #[test]
+    fn unknown_meta_keys_are_kept_next_to_the_known_fields() {
+        let meta: BatchMeta = serde_json::from_value(json!({
+            "race_id": 7,
+            "bet_type": "exacta",
+            "venue": "Randwick",
+            "name": "Morning run",
+            "params": { "edge": 0.05 }
+        }))
+        .unwrap();
+        assert_eq!(meta.race_id, Some(7));
+        assert_eq!(meta.bet_type, Some(BetType::Exacta));
+        assert_eq!(meta.extra.len(), 2);
+
+        assert_eq!(
+            serde_json::to_value(&meta).unwrap(),
+            json!({
+                "race_id": 7,
+                "bet_type": "EXACTA",
+                "venue": "Randwick",
+                "name": "Morning run",
+                "params": { "edge": 0.05 }
+            })
+        );
+
+        assert!(serde_json::from_value::<BatchMeta>(json!({ "race_id": "R1" })).is_err());
+        assert!(serde_json::from_value::<BatchMeta>(json!({ "bet_type": "DOUBLE" })).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// What an API key may do within its account scope
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyAccess {
+    /// GET endpoints and the event stream
+    Read,
+    /// Everything `read` allows, plus creating, updating and deleting
+    Write,
+}
+
+impl std::fmt::Display for KeyAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyAccess::Read => write!(f, "read"),
+            KeyAccess::Write => write!(f, "write"),
+        }
+    }
+}
+
+impl FromStr for KeyAccess {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(KeyAccess::Read),
+            "write" => Ok(KeyAccess::Write),
+            _ => Err(format!("Invalid key access: {}", s)),
+        }
+    }
+}
+
+/// An API key as shown to administrators; the secret itself is never stored
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    /// First characters of the key, to help tell keys apart
+    #[schema(example = "bsk_1a2b3c4d")]
+    pub key_prefix: String,
+    pub access: KeyAccess,
+    /// Accounts the key is limited to; `null` means every account
+    pub account_ids: Option<Vec<i64>>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub access: KeyAccess,
+    /// Limit the key to these accounts; omit for every account
+    pub account_ids: Option<Vec<i64>>,
+}
+
+/// Returned once on creation; the plaintext key cannot be retrieved again
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedApiKey {
+    /// Send as `Authorization: Bearer <key>`
+    #[schema(example = "bsk_1a2b3c4d...")]
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+/// A key ready to be persisted, already hashed
+#[derive(Debug)]
+pub struct NewApiKey {
+    pub name: String,
+    pub key_hash: String,
+    pub key_prefix: String,
+    pub access: KeyAccess,
+    pub account_ids: Option<Vec<i64>>,
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::models::account::{Account, BatchResponse, BrokerEvent};
+
+/// Version of [`EventEnvelope`] and the event payloads inside it; bumped on
+/// incompatible changes
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Envelope every event is delivered in, over SSE, webhooks and sinks. The
+/// event's own fields, including its `type` tag, stay at the top level next
+/// to the metadata, so clients that only read the event keep working.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EventEnvelope {
+    /// Position in the event log, increasing in commit order; also the SSE `id:`
+    pub event_id: i64,
+    #[schema(example = 1)]
+    pub schema_version: u32,
+    /// When the change was committed
+    pub emitted_at: DateTime<Utc>,
+    /// `X-Request-Id` of the request that made the change
+    pub correlation_id: Option<String>,
+    /// Who made the change
+    pub actor: Option<Actor>,
+    #[serde(flatten)]
+    pub event: BrokerEvent,
+}
+
+/// The caller behind a change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Actor {
+    /// The admin key, or any caller while auth is disabled
+    Admin,
+    ApiKey {
+        id: i64,
+        name: String,
+        key_prefix: String,
+    },
+}
+
+/// Request metadata recorded with the events a change writes
+#[derive(Debug, Clone, Default)]
+pub struct EventContext {
+    pub correlation_id: Option<String>,
+    pub actor: Option<Actor>,
+}
+
+/// Filters for `/sse`; events are dropped on the server unless they match
+/// every filter given
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SseQuery {
+    /// Only events for this account
+    pub account_id: Option<i64>,
+    /// Only events for this batch; account-level events are dropped
+    pub batch_id: Option<i64>,
+    /// Comma-separated event types, e.g. `bet_status_updated,batch_created`
+    pub types: Option<String>,
+    /// Start with a `snapshot` event of the current accounts and open batches
+    #[serde(default)]
+    pub snapshot: bool,
+}
+
+/// Filters for an account's event stream
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventQuery {
+    /// Only events for this batch; account-level events are dropped
+    pub batch_id: Option<i64>,
+    /// Comma-separated event types, e.g. `bet_status_updated,batch_created`
+    pub types: Option<String>,
+    /// Start with a `snapshot` event of the account and its open batches
+    #[serde(default)]
+    pub snapshot: bool,
+}
+
+/// Accounts and open batches as of one point in the event log: every event
+/// up to `event_id` is reflected, none after it
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StateSnapshot {
+    /// Id of the newest event included; live events continue after it
+    pub event_id: i64,
+    pub accounts: Vec<Account>,
+    /// Batches not yet completed, with their bets
+    pub batches: Vec<BatchResponse>,
+}
+
+/// Delivery health of one open SSE connection
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConnectionStats {
+    pub id: u64,
+    /// `admin`, or the name and prefix of the API key
+    pub subscriber: String,
+    pub connected_at: DateTime<Utc>,
+    /// Times the subscriber fell behind the broadcast channel
+    pub lag_count: u64,
+    /// Events it never received because of lag
+    pub skipped_events: u64,
+}
+
+/// Open SSE connections and lag totals since startup
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SseConnectionsReport {
+    pub connections: Vec<ConnectionStats>,
+    /// Events skipped by all subscribers, including disconnected ones
+    pub total_skipped_events: u64,
+    /// Subscribers disconnected for lagging `events.max_lags` times
+    pub disconnected_for_lag: u64,
+}
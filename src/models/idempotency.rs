@@ -0,0 +1,11 @@
+use sqlx::FromRow;
+
+/// A request already seen under an `Idempotency-Key`
+#[derive(Debug, Clone, FromRow)]
+pub struct IdempotencyRecord {
+    /// Hash of the method, path and body of the first request
+    pub request_hash: String,
+    /// `None` while the first request is still running
+    pub status_code: Option<i32>,
+    pub response_body: Option<String>,
+}
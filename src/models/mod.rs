@@ -1 +1,11 @@
-pub mod account;
\ No newline at end of file
+pub mod account;
+pub mod api_key;
+pub mod event;
+pub mod idempotency;
+pub mod money;
+pub mod pagination;
+pub mod race;
+pub mod report;
+pub mod selection;
+pub mod webhook;
+pub mod ws;
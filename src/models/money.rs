@@ -0,0 +1,300 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, iter::Sum, str::FromStr};
+use utoipa::ToSchema;
+
+/// A non-negative amount of money held exactly as integer minor units
+/// (cents). It travels as a decimal string such as `"2.50"`; requests may
+/// also send a JSON number, which is read by its decimal digits rather than
+/// as a float.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type, ToSchema)]
+#[sqlx(transparent)]
+#[schema(value_type = String, example = "2.50")]
+pub struct Money(i64);
+
+impl Money {
+    /// Digits after the decimal point
+    pub const SCALE: u32 = 2;
+
+    pub const ZERO: Money = Money(0);
+
+    /// Largest stake or cost a request may send, and largest total of
+    /// either in a batch, 1,000,000,000.00, so that sums of many amounts stay
+    /// far from overflowing. Requests are checked against it, not parsing:
+    /// any amount that was written out must read back.
+    pub const MAX: Money = Money(100_000_000_000);
+
+    pub fn from_minor(minor: i64) -> Self {
+        Money(minor)
+    }
+
+    pub fn minor_units(self) -> i64 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+}
+
+/// Sums to `None` if the total overflows
+impl Sum<Money> for Option<Money> {
+    fn sum<I: Iterator<Item = Money>>(mut iter: I) -> Option<Money> {
+        iter.try_fold(Money::ZERO, Money::checked_add)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_decimal(f, self.0, Self::SCALE)
+    }
+}
+
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_decimal(s, Self::SCALE, "Amount").map(Money)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let digits = deserializer.deserialize_any(DecimalVisitor { scale: Self::SCALE })?;
+        digits.parse().map_err(de::Error::custom)
+    }
+}
+
+// Reads a decimal string or JSON number as its decimal digits
+struct DecimalVisitor {
+    scale: u32,
+}
+
+impl de::Visitor<'_> for DecimalVisitor {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a decimal string with at most {} decimal places", self.scale)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+
+    // Display gives the shortest digits that read back as the same float,
+    // so 0.1 is "0.1" and not 0.1000000000000000055...
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<String, E> {
+        if !v.is_finite() {
+            return Err(E::custom(format!("Invalid decimal: {}", v)));
+        }
+        Ok(v.to_string())
+    }
+}
+
+/// Currencies accounts can trade in. Each has two decimal places, so
+/// [`Money`] holds cents in any of them. The default, AUD, is what accounts
+/// created before currencies existed trade in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(type_name = "TEXT", rename_all = "UPPERCASE")]
+pub enum Currency {
+    #[default]
+    Aud,
+    Nzd,
+    Gbp,
+    Usd,
+    Eur,
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Currency::Aud => write!(f, "AUD"),
+            Currency::Nzd => write!(f, "NZD"),
+            Currency::Gbp => write!(f, "GBP"),
+            Currency::Usd => write!(f, "USD"),
+            Currency::Eur => write!(f, "EUR"),
+        }
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "AUD" => Ok(Currency::Aud),
+            "NZD" => Ok(Currency::Nzd),
+            "GBP" => Ok(Currency::Gbp),
+            "USD" => Ok(Currency::Usd),
+            "EUR" => Ok(Currency::Eur),
+            _ => Err(format!("Unsupported currency: {}", s)),
+        }
+    }
+}
+
+/// Units of one currency per unit of another, exact to six decimal places.
+/// Travels as a decimal string like [`Money`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type, ToSchema)]
+#[sqlx(transparent)]
+#[schema(value_type = String, example = "0.921500")]
+pub struct ExchangeRate(i64);
+
+impl ExchangeRate {
+    /// Digits after the decimal point
+    pub const SCALE: u32 = 6;
+
+    pub const ONE: ExchangeRate = ExchangeRate(10_i64.pow(Self::SCALE));
+
+    /// `amount` in the other currency, rounded half up to the nearest cent
+    pub fn convert(self, amount: Money) -> Option<Money> {
+        let unit = i128::from(Self::ONE.0);
+        let scaled = i128::from(amount.0) * i128::from(self.0);
+        i64::try_from((scaled + unit / 2) / unit).ok().map(Money)
+    }
+}
+
+impl fmt::Display for ExchangeRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_decimal(f, self.0, Self::SCALE)
+    }
+}
+
+impl FromStr for ExchangeRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_decimal(s, Self::SCALE, "Exchange rate")? {
+            0 => Err("Exchange rate must be greater than zero".to_string()),
+            rate => Ok(ExchangeRate(rate)),
+        }
+    }
+}
+
+impl Serialize for ExchangeRate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExchangeRate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let digits = deserializer.deserialize_any(DecimalVisitor { scale: Self::SCALE })?;
+        digits.parse().map_err(de::Error::custom)
+    }
+}
+
+// `value` as a decimal with `scale` places, e.g. 250 at scale 2 is "2.50"
+fn write_decimal(f: &mut fmt::Formatter<'_>, value: i64, scale: u32) -> fmt::Result {
+    let sign = if value < 0 { "-" } else { "" };
+    let unit = 10_u64.pow(scale);
+    let digits = value.unsigned_abs();
+    write!(f, "{}{}.{:0width$}", sign, digits / unit, digits % unit, width = scale as usize)
+}
+
+// A non-negative decimal string with at most `scale` places as an integer
+// count of its smallest unit; `what` names the value in errors
+fn parse_decimal(s: &str, scale: u32, what: &str) -> Result<i64, String> {
+    if s.starts_with('-') {
+        return Err(format!("{} must not be negative: {}", what, s));
+    }
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) || (s.contains('.') && fraction.is_empty()) {
+        return Err(format!("Invalid {}: {}", what.to_lowercase(), s));
+    }
+    if fraction.len() > scale as usize {
+        return Err(format!("{} has more than {} decimal places: {}", what, scale, s));
+    }
+
+    let too_large = || format!("{} is too large: {}", what, s);
+    let whole: i64 = whole.parse().map_err(|_| too_large())?;
+    let fraction: i64 = format!("{:0<width$}", fraction, width = scale as usize)
+        .parse()
+        .unwrap_or(0);
+    whole
+        .checked_mul(10_i64.pow(scale))
+        .and_then(|units| units.checked_add(fraction))
+        .ok_or_else(too_large)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_and_prints_decimal_strings() {
+        let cases = [("0", 0), ("0.1", 10), ("1.5", 150), ("2.50", 250), ("007.05", 705), ("1000", 100_000)];
+        for (text, minor) in cases {
+            let money: Money = text.parse().unwrap();
+            assert_eq!(money.minor_units(), minor, "{}", text);
+        }
+        assert_eq!(Money::from_minor(5).to_string(), "0.05");
+        assert_eq!(Money::from_minor(123_456).to_string(), "1234.56");
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        for text in ["", "-1", "-0.00", "1.234", "1.", ".5", "1e3", "NaN", "inf", "1,5", " 1", "99999999999999999999"] {
+            assert!(text.parse::<Money>().is_err(), "{:?} parsed", text);
+        }
+
+        assert_eq!("1000000000".parse::<Money>().unwrap(), Money::MAX);
+        assert!(serde_json::from_value::<Money>(json!("99999999999999999999")).is_err());
+    }
+
+    #[test]
+    fn amounts_above_the_request_cap_read_back() {
+        // Event payloads carry totals and payouts past `Money::MAX`
+        for money in [Money::from_minor(Money::MAX.minor_units() + 1), Money::from_minor(i64::MAX)] {
+            let json = serde_json::to_value(money).unwrap();
+            assert_eq!(serde_json::from_value::<Money>(json).unwrap(), money);
+        }
+    }
+
+    #[test]
+    fn json_numbers_are_read_exactly() {
+        let total: Money = [json!(0.1), json!(0.2)]
+            .into_iter()
+            .map(|v| serde_json::from_value::<Money>(v).unwrap())
+            .sum::<Option<Money>>()
+            .unwrap();
+        assert_eq!(total, "0.3".parse().unwrap());
+        assert_eq!([Money::from_minor(i64::MAX), Money::from_minor(1)].into_iter().sum::<Option<Money>>(), None);
+        assert_eq!(serde_json::from_value::<Money>(json!(3)).unwrap().minor_units(), 300);
+        assert_eq!(serde_json::to_value(total).unwrap(), json!("0.30"));
+
+        assert!(serde_json::from_value::<Money>(json!(-1.5)).is_err());
+        assert!(serde_json::from_value::<Money>(json!(1.005)).is_err());
+        assert!(serde_json::from_value::<Money>(json!(true)).is_err());
+    }
+
+    #[test]
+    fn exchange_rates_convert_to_the_nearest_cent() {
+        let rate: ExchangeRate = "0.9215".parse().unwrap();
+        assert_eq!(rate.to_string(), "0.921500");
+        assert_eq!(rate.convert(Money::from_minor(1000)), Some(Money::from_minor(922)));
+        assert_eq!(rate.convert(Money::from_minor(200)), Some(Money::from_minor(184)));
+        assert_eq!(ExchangeRate::ONE.convert(Money::from_minor(12_345)), Some(Money::from_minor(12_345)));
+
+        assert!("0".parse::<ExchangeRate>().is_err());
+        assert!("1.0000001".parse::<ExchangeRate>().is_err());
+        assert_eq!(serde_json::from_value::<ExchangeRate>(json!(1.1)).unwrap().to_string(), "1.100000");
+        assert_eq!(serde_json::from_value::<Currency>(json!("NZD")).unwrap(), Currency::Nzd);
+        assert!(serde_json::from_value::<Currency>(json!("XYZ")).is_err());
+    }
+}
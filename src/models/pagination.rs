@@ -0,0 +1,92 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::account::{Account, BatchResponse};
+use super::webhook::WebhookDelivery;
+use crate::error::ApiError;
+
+pub const DEFAULT_PAGE_LIMIT: u32 = 50;
+pub const MAX_PAGE_LIMIT: u32 = 200;
+
+/// A page of results ordered newest first
+#[derive(Debug, Serialize, ToSchema)]
+#[aliases(
+    AccountPage = Page<Account>,
+    BatchPage = Page<BatchResponse>,
+    WebhookDeliveryPage = Page<WebhookDelivery>
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Opaque cursor for the next page, absent on the last page
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+    pub limit: u32,
+}
+
+impl<T> Page<T> {
+    /// Build a page from rows fetched with `PageRequest::probe_limit`, trimming the probe row
+    pub fn from_probe(mut items: Vec<T>, limit: u32, key: impl Fn(&T) -> Cursor) -> Self {
+        let has_more = items.len() > limit as usize;
+        items.truncate(limit as usize);
+        let next_cursor = if has_more {
+            items.last().map(|last| key(last).encode())
+        } else {
+            None
+        };
+
+        Page { items, next_cursor, has_more, limit }
+    }
+}
+
+/// Keyset position over `(created_at, id)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}|{}", self.created_at.to_rfc3339(), self.id))
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, ApiError> {
+        let invalid = || ApiError::bad_request(format!("Invalid cursor: {}", raw));
+
+        let bytes = URL_SAFE_NO_PAD.decode(raw).map_err(|_| invalid())?;
+        let text = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (created_at, id) = text.split_once('|').ok_or_else(invalid)?;
+
+        Ok(Cursor {
+            created_at: DateTime::parse_from_rfc3339(created_at)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Decoded paging parameters handed to the store
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    pub cursor: Option<Cursor>,
+    pub limit: u32,
+}
+
+impl PageRequest {
+    /// Validate the raw `limit`/`cursor` query parameters, clamping the limit
+    /// into `1..=MAX_PAGE_LIMIT`
+    pub fn new(limit: Option<u32>, cursor: Option<&str>) -> Result<Self, ApiError> {
+        Ok(PageRequest {
+            cursor: cursor.map(Cursor::decode).transpose()?,
+            limit: limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT),
+        })
+    }
+
+    /// Number of rows to fetch: one more than the limit to detect a next page
+    pub fn probe_limit(&self) -> i64 {
+        i64::from(self.limit) + 1
+    }
+}
@@ -0,0 +1,294 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+use crate::error::{ApiError, FieldError};
+use crate::models::money::Money;
+use crate::models::selection::{BetType, Selection};
+
+/// How a settled bet finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum BetResult {
+    Won,
+    Lost,
+}
+
+/// Tote dividend declared for one winning selection, paid per 1.00 staked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Dividend {
+    pub bet_type: BetType,
+    /// Winning selection, e.g. `"3"` for WIN or `"3/1/7"` for TRIFECTA
+    pub selection: String,
+    #[schema(example = "4.20")]
+    pub dividend: Money,
+}
+
+/// Official result of a race
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct RaceResult {
+    pub race_id: i64,
+    /// Runner numbers in finishing order
+    #[sqlx(json)]
+    pub placings: Vec<u32>,
+    #[sqlx(json)]
+    pub dividends: Vec<Dividend>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetRaceResultRequest {
+    /// Runner numbers in finishing order, winner first
+    #[schema(example = json!([3, 1, 7, 2]))]
+    pub placings: Vec<u32>,
+    /// Every paying selection and its dividend; a selection without one loses
+    pub dividends: Vec<Dividend>,
+}
+
+impl SetRaceResultRequest {
+    /// Check the placings name distinct runners, every dividend is for a
+    /// selection the placings make a winner, at most once each, and every
+    /// winner among `bets` (the race's successful bets, as bet type and
+    /// selection) has a dividend. Reports every invalid field, not just the
+    /// first.
+    pub fn validate(&self, bets: &[(BetType, String)]) -> Result<(), ApiError> {
+        let mut errors = Vec::new();
+
+        match parse_placings(&self.placings) {
+            Err(e) => errors.push(FieldError::new("/placings", e)),
+            Ok(placings) => {
+                let mut unpaid: Vec<(BetType, Selection)> = Vec::new();
+                for (bet_type, selection) in bets {
+                    let Ok(selection) = Selection::for_bet_type(selection, *bet_type) else {
+                        continue;
+                    };
+                    let paid = self.dividends.iter().any(|d| {
+                        d.bet_type == *bet_type
+                            && Selection::for_bet_type(&d.selection, *bet_type)
+                                .is_ok_and(|s| s.matches(&selection, *bet_type))
+                    });
+                    if selection.finishes_in(&placings, *bet_type)
+                        && !paid
+                        && !unpaid.iter().any(|(t, s)| t == bet_type && s.matches(&selection, *bet_type))
+                    {
+                        errors.push(FieldError::new(
+                            "/dividends",
+                            format!("{} {} won on placings {} and has bets, but no dividend", bet_type, selection, placings),
+                        ));
+                        unpaid.push((*bet_type, selection));
+                    }
+                }
+
+                let mut seen: Vec<(BetType, Selection)> = Vec::new();
+                for (i, dividend) in self.dividends.iter().enumerate() {
+                    let bet_type = dividend.bet_type;
+                    match Selection::for_bet_type(&dividend.selection, bet_type) {
+                        Err(e) => errors.push(FieldError::new(format!("/dividends/{}/selection", i), e)),
+                        Ok(selection) if !selection.finishes_in(&placings, bet_type) => errors.push(FieldError::new(
+                            format!("/dividends/{}/selection", i),
+                            format!("{} {} did not win on placings {}", bet_type, selection, placings),
+                        )),
+                        Ok(selection) if seen.iter().any(|(t, s)| *t == bet_type && s.matches(&selection, bet_type)) => {
+                            errors.push(FieldError::new(
+                                format!("/dividends/{}/selection", i),
+                                format!("{} {} has more than one dividend", bet_type, selection),
+                            ))
+                        }
+                        Ok(selection) => seen.push((bet_type, selection)),
+                    }
+                    if dividend.dividend == Money::ZERO {
+                        errors.push(FieldError::new(
+                            format!("/dividends/{}/dividend", i),
+                            "dividend must be greater than zero",
+                        ));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(ApiError::validation(errors))
+    }
+}
+
+// The placings as a selection, for matching against bets
+fn parse_placings(placings: &[u32]) -> Result<Selection, String> {
+    if placings.is_empty() {
+        return Err("placings must name at least one runner".to_string());
+    }
+    for (i, runner) in placings.iter().enumerate() {
+        if *runner == 0 {
+            return Err("runner numbers start at 1".to_string());
+        }
+        if placings[..i].contains(runner) {
+            return Err(format!("runner {} is placed twice", runner));
+        }
+    }
+    Ok(Selection { runners: placings.to_vec() })
+}
+
+/// A race result as stored, and what setting it changed
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RaceSettlement {
+    pub result: RaceResult,
+    /// Bets settled for the first time or with a different outcome
+    pub settled: i64,
+    /// How many of those won
+    pub won: i64,
+    /// Successful bets on the race that could not be settled, e.g. because
+    /// their batch predates required bet types and has none
+    pub unsettled: i64,
+}
+
+/// What a bet came to under a race result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settlement {
+    pub result: BetResult,
+    /// `stake` times the dividend, rounded down to the cent; zero if lost
+    pub payout: Money,
+}
+
+impl RaceResult {
+    /// Settle a bet of `bet_type` on `selection`: it wins if the selection
+    /// finishes as the bet type requires on the placings, and pays the
+    /// dividend declared for it (in any order for QUINELLA). `None`, leaving
+    /// the bet unsettled, if the selection does not parse for the bet type or
+    /// wins without a declared dividend.
+    pub fn settle(&self, bet_type: BetType, selection: &str, stake: Money) -> Option<Settlement> {
+        let selection = Selection::for_bet_type(selection, bet_type).ok()?;
+        let placings = Selection { runners: self.placings.clone() };
+        if !selection.finishes_in(&placings, bet_type) {
+            return Some(Settlement { result: BetResult::Lost, payout: Money::ZERO });
+        }
+
+        let dividend = self.dividends.iter().find(|d| {
+            d.bet_type == bet_type
+                && Selection::for_bet_type(&d.selection, bet_type).is_ok_and(|s| s.matches(&selection, bet_type))
+        })?;
+        Some(Settlement { result: BetResult::Won, payout: payout(stake, dividend.dividend) })
+    }
+}
+
+// `stake` times a dividend per 1.00, rounded down to the cent as the tote
+// does; saturates rather than overflows, as callers cap payouts far lower
+fn payout(stake: Money, dividend: Money) -> Money {
+    let unit = 10_i128.pow(Money::SCALE);
+    let cents = i128::from(stake.minor_units()) * i128::from(dividend.minor_units()) / unit;
+    Money::from_minor(i64::try_from(cents).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dividend(bet_type: BetType, selection: &str, amount: &str) -> Dividend {
+        Dividend { bet_type, selection: selection.to_string(), dividend: amount.parse().unwrap() }
+    }
+
+    fn request(placings: &[u32], dividends: Vec<Dividend>) -> SetRaceResultRequest {
+        SetRaceResultRequest { placings: placings.to_vec(), dividends }
+    }
+
+    #[test]
+    fn dividends_must_match_the_placings() {
+        let valid = request(
+            &[3, 1, 7, 2],
+            vec![
+                dividend(BetType::Win, "3", "4.20"),
+                dividend(BetType::Place, "7", "2.10"),
+                dividend(BetType::Quinella, "1/3", "8.50"),
+                dividend(BetType::Exacta, "3/1", "15.00"),
+                dividend(BetType::Trifecta, "3/1/7", "60.00"),
+            ],
+        );
+        assert!(valid.validate(&[]).is_ok());
+
+        let Err(ApiError::Validation(_, errors)) = request(
+            &[3, 1, 7, 2],
+            vec![
+                dividend(BetType::Win, "1", "4.20"),
+                dividend(BetType::Place, "2", "2.10"),
+                dividend(BetType::Exacta, "1/3", "15.00"),
+                dividend(BetType::Quinella, "3/1", "8.50"),
+                dividend(BetType::Quinella, "1/3", "8.50"),
+                dividend(BetType::Trifecta, "3/1/7", "0"),
+            ],
+        )
+        .validate(&[]) else {
+            panic!("expected a validation error");
+        };
+        let pointers: Vec<_> = errors.iter().map(|e| e.pointer.as_str()).collect();
+        assert_eq!(
+            pointers,
+            [
+                "/dividends/0/selection",
+                "/dividends/1/selection",
+                "/dividends/2/selection",
+                "/dividends/4/selection",
+                "/dividends/5/dividend"
+            ]
+        );
+        assert_eq!(errors[0].detail, "WIN 1 did not win on placings 3/1/7/2");
+
+        for placings in [&[][..], &[1, 1], &[0, 2]] {
+            let Err(ApiError::Validation(_, errors)) = request(placings, vec![]).validate(&[]) else {
+                panic!("{:?} validated", placings);
+            };
+            assert_eq!(errors[0].pointer, "/placings");
+        }
+
+        // Winners with bets need a dividend; losers and unbacked winners do not
+        let bets = |list: &[(BetType, &str)]| list.iter().map(|(t, s)| (*t, s.to_string())).collect::<Vec<_>>();
+        let win_only = request(&[3, 1, 7], vec![dividend(BetType::Win, "3", "4.20")]);
+        assert!(win_only.validate(&bets(&[(BetType::Win, "3"), (BetType::Win, "1"), (BetType::Exacta, "1/3")])).is_ok());
+        let Err(ApiError::Validation(_, errors)) =
+            win_only.validate(&bets(&[(BetType::Quinella, "1/3"), (BetType::Quinella, "3/1"), (BetType::Place, "7")]))
+        else {
+            panic!("expected a validation error");
+        };
+        let details: Vec<_> = errors.iter().map(|e| (e.pointer.as_str(), e.detail.as_str())).collect();
+        assert_eq!(
+            details,
+            [
+                ("/dividends", "QUINELLA 1/3 won on placings 3/1/7 and has bets, but no dividend"),
+                ("/dividends", "PLACE 7 won on placings 3/1/7 and has bets, but no dividend")
+            ]
+        );
+    }
+
+    #[test]
+    fn settles_on_declared_dividends() {
+        let result = RaceResult {
+            race_id: 1,
+            placings: vec![3, 1, 7],
+            dividends: vec![
+                dividend(BetType::Win, "3", "4.25"),
+                dividend(BetType::Quinella, "3/1", "8.50"),
+                dividend(BetType::Exacta, "3/1", "15.00"),
+            ],
+            updated_at: Utc::now(),
+        };
+        let settle = |bet_type, selection, stake: &str| result.settle(bet_type, selection, stake.parse().unwrap());
+        let won = |payout: &str| Some(Settlement { result: BetResult::Won, payout: payout.parse().unwrap() });
+        let lost = Some(Settlement { result: BetResult::Lost, payout: Money::ZERO });
+
+        // 1.50 x 4.25 = 6.375, rounded down
+        assert_eq!(settle(BetType::Win, "3", "1.50"), won("6.37"));
+        assert_eq!(settle(BetType::Win, "1", "1.50"), lost);
+        assert_eq!(settle(BetType::Quinella, "1/3", "2.00"), won("17.00"));
+        assert_eq!(settle(BetType::Exacta, "1/3", "2.00"), lost);
+        assert_eq!(settle(BetType::Place, "2", "1.00"), lost);
+        // A winner is never settled lost: without a dividend it stays unsettled
+        assert_eq!(settle(BetType::Place, "3", "1.00"), None);
+        assert_eq!(settle(BetType::Trifecta, "3/1/7", "1.00"), None);
+        assert_eq!(settle(BetType::Win, "1/3", "1.00"), None);
+
+        // Saturates for the store to reject, rather than wrapping
+        let huge = Money::from_minor(i64::MAX);
+        assert_eq!(payout(huge, "2.00".parse().unwrap()), huge);
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::ApiError;
+use crate::models::account::BetStatus;
+use crate::models::money::{Currency, ExchangeRate, Money};
+
+/// `rate` units of `to` buy one unit of `from`
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct FxRate {
+    #[sqlx(rename = "from_currency")]
+    pub from: Currency,
+    #[sqlx(rename = "to_currency")]
+    pub to: Currency,
+    pub rate: ExchangeRate,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetFxRateRequest {
+    /// Units of the target currency per unit of the source, e.g. `"0.9215"`
+    pub rate: ExchangeRate,
+}
+
+/// Which bets a totals report covers, and the currency it is reported in
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TotalsQuery {
+    /// Base currency to convert every total into
+    pub currency: Currency,
+    /// Only bets of this account
+    pub account_id: Option<i64>,
+    /// Only bets in this status, e.g. `successful` for money actually placed
+    pub status: Option<BetStatus>,
+    /// Only bets in batches created at or after this time (RFC 3339)
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only bets in batches created before this time (RFC 3339)
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+/// Bets, stake and cost summed in one currency
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct StakeTotals {
+    pub currency: Currency,
+    pub bets: i64,
+    pub stake: Money,
+    pub cost: Money,
+}
+
+/// One currency's totals, as summed and converted into the base currency
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConvertedTotals {
+    pub currency: Currency,
+    pub bets: i64,
+    pub stake: Money,
+    pub cost: Money,
+    /// Rate applied; `1.000000` for the base currency itself
+    pub rate: ExchangeRate,
+    pub converted_stake: Money,
+    pub converted_cost: Money,
+}
+
+/// Stake and cost across currencies, in the base `currency`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotalsReport {
+    pub currency: Currency,
+    pub bets: i64,
+    pub stake: Money,
+    pub cost: Money,
+    pub by_currency: Vec<ConvertedTotals>,
+}
+
+impl TotalsReport {
+    /// Convert each currency's totals with its rate into `currency`; every
+    /// currency with bets needs a rate to it. Each converted amount is
+    /// rounded to the cent before the totals are summed.
+    pub fn convert(currency: Currency, totals: Vec<StakeTotals>, rates: &[FxRate]) -> Result<Self, ApiError> {
+        let mut by_currency = Vec::with_capacity(totals.len());
+        for totals in totals {
+            let rate = if totals.currency == currency {
+                ExchangeRate::ONE
+            } else {
+                rates
+                    .iter()
+                    .find(|r| r.from == totals.currency && r.to == currency)
+                    .map(|r| r.rate)
+                    .ok_or_else(|| {
+                        ApiError::Unprocessable(format!("No FX rate from {} to {}", totals.currency, currency))
+                    })?
+            };
+            let convert = |amount| {
+                rate.convert(amount)
+                    .ok_or_else(|| ApiError::Internal(format!("{} {} overflows when converted", amount, totals.currency)))
+            };
+            by_currency.push(ConvertedTotals {
+                converted_stake: convert(totals.stake)?,
+                converted_cost: convert(totals.cost)?,
+                currency: totals.currency,
+                bets: totals.bets,
+                stake: totals.stake,
+                cost: totals.cost,
+                rate,
+            });
+        }
+
+        let total = |amounts: Vec<Money>| {
+            amounts
+                .into_iter()
+                .sum::<Option<Money>>()
+                .ok_or_else(|| ApiError::Internal(format!("Totals in {} overflow", currency)))
+        };
+        Ok(TotalsReport {
+            currency,
+            bets: by_currency.iter().map(|t| t.bets).sum(),
+            stake: total(by_currency.iter().map(|t| t.converted_stake).collect())?,
+            cost: total(by_currency.iter().map(|t| t.converted_cost).collect())?,
+            by_currency,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals(currency: Currency, bets: i64, stake: i64, cost: i64) -> StakeTotals {
+        StakeTotals { currency, bets, stake: Money::from_minor(stake), cost: Money::from_minor(cost) }
+    }
+
+    #[test]
+    fn converts_each_currency_into_the_base() {
+        let rates = [FxRate {
+            from: Currency::Nzd,
+            to: Currency::Aud,
+            rate: "0.9215".parse().unwrap(),
+            updated_at: Utc::now(),
+        }];
+        let report = TotalsReport::convert(
+            Currency::Aud,
+            vec![totals(Currency::Aud, 2, 1_050, 500), totals(Currency::Nzd, 3, 1_000, 200)],
+            &rates,
+        )
+        .unwrap();
+
+        assert_eq!(report.bets, 5);
+        assert_eq!(report.stake, Money::from_minor(1_050 + 922));
+        assert_eq!(report.cost, Money::from_minor(500 + 184));
+        assert_eq!(report.by_currency[0].rate, ExchangeRate::ONE);
+        assert_eq!(report.by_currency[1].converted_stake, Money::from_minor(922));
+
+        // The reverse direction is a different rate
+        let missing = TotalsReport::convert(Currency::Nzd, vec![totals(Currency::Aud, 1, 100, 0)], &rates);
+        assert!(matches!(missing, Err(ApiError::Unprocessable(_))));
+    }
+}
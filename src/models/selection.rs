@@ -0,0 +1,221 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+
+/// Kind of bet a batch places, named by `meta.bet_type`. Read
+/// case-insensitively; written in upper case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(type_name = "TEXT", rename_all = "UPPERCASE")]
+pub enum BetType {
+    /// One runner to finish first
+    Win,
+    /// One runner to finish in the places
+    Place,
+    /// Two runners to finish first and second in either order
+    Quinella,
+    /// Two runners to finish first and second in that order
+    Exacta,
+    /// Three runners to finish first, second and third in that order
+    Trifecta,
+}
+
+impl BetType {
+    /// Most places a PLACE bet can pay on
+    pub const PLACES: usize = 3;
+
+    /// Runners a selection names, one per leg
+    pub fn legs(self) -> usize {
+        match self {
+            BetType::Win | BetType::Place => 1,
+            BetType::Quinella | BetType::Exacta => 2,
+            BetType::Trifecta => 3,
+        }
+    }
+
+    /// Whether the runners must finish in the order they are listed
+    pub fn is_ordered(self) -> bool {
+        matches!(self, BetType::Exacta | BetType::Trifecta)
+    }
+}
+
+impl fmt::Display for BetType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BetType::Win => write!(f, "WIN"),
+            BetType::Place => write!(f, "PLACE"),
+            BetType::Quinella => write!(f, "QUINELLA"),
+            BetType::Exacta => write!(f, "EXACTA"),
+            BetType::Trifecta => write!(f, "TRIFECTA"),
+        }
+    }
+}
+
+impl FromStr for BetType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "WIN" => Ok(BetType::Win),
+            "PLACE" => Ok(BetType::Place),
+            "QUINELLA" => Ok(BetType::Quinella),
+            "EXACTA" => Ok(BetType::Exacta),
+            "TRIFECTA" => Ok(BetType::Trifecta),
+            _ => Err(format!("Unknown bet type: {}", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BetType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A bet's selection parsed from its text form: runner numbers separated by
+/// `/`, one per leg, e.g. `"1/2/3"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    /// Runner numbers, in the order they were listed
+    pub runners: Vec<u32>,
+}
+
+impl Selection {
+    pub fn legs(&self) -> usize {
+        self.runners.len()
+    }
+
+    /// Parse `text` as a selection for `bet_type`: the right number of legs
+    /// for the bet type, each a distinct runner
+    pub fn for_bet_type(text: &str, bet_type: BetType) -> Result<Self, String> {
+        let selection: Selection = text.parse()?;
+        if selection.legs() != bet_type.legs() {
+            return Err(format!(
+                "{} takes {} runner{}, got {}",
+                bet_type,
+                bet_type.legs(),
+                if bet_type.legs() == 1 { "" } else { "s" },
+                selection.legs()
+            ));
+        }
+        Ok(selection)
+    }
+
+    /// Whether this and `other` name the same runners for `bet_type`, in
+    /// the same order unless the bet type ignores it
+    pub fn matches(&self, other: &Selection, bet_type: BetType) -> bool {
+        if bet_type.is_ordered() {
+            return self.runners == other.runners;
+        }
+        let mut mine = self.runners.clone();
+        let mut theirs = other.runners.clone();
+        mine.sort_unstable();
+        theirs.sort_unstable();
+        mine == theirs
+    }
+
+    /// Whether the selection wins a `bet_type` bet on a race finishing in
+    /// the order of `placings`. PLACE pays on the first
+    /// [`BetType::PLACES`] runners.
+    pub fn finishes_in(&self, placings: &Selection, bet_type: BetType) -> bool {
+        match bet_type {
+            BetType::Place => placings.runners.iter().take(BetType::PLACES).any(|r| self.runners == [*r]),
+            _ => {
+                let legs = bet_type.legs();
+                placings.legs() >= legs
+                    && self.matches(&Selection { runners: placings.runners[..legs].to_vec() }, bet_type)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, runner) in self.runners.iter().enumerate() {
+            if i > 0 {
+                write!(f, "/")?;
+            }
+            write!(f, "{}", runner)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Selection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut runners = Vec::new();
+        for leg in s.split('/') {
+            if leg.is_empty() || !leg.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(format!("Invalid selection {:?}: runners are numbers separated by '/'", s));
+            }
+            let runner: u32 = leg
+                .parse()
+                .map_err(|_| format!("Invalid selection {:?}: runner number {} is too large", s, leg))?;
+            if runner == 0 {
+                return Err(format!("Invalid selection {:?}: runner numbers start at 1", s));
+            }
+            if runners.contains(&runner) {
+                return Err(format!("Invalid selection {:?}: runner {} is listed twice", s, runner));
+            }
+            runners.push(runner);
+        }
+        Ok(Selection { runners })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bet_types_read_case_insensitively() {
+        assert_eq!("win".parse::<BetType>().unwrap(), BetType::Win);
+        assert_eq!("Trifecta".parse::<BetType>().unwrap(), BetType::Trifecta);
+        assert_eq!(BetType::Quinella.to_string(), "QUINELLA");
+        assert!("DOUBLE".parse::<BetType>().is_err());
+        assert!(BetType::Exacta.is_ordered() && !BetType::Quinella.is_ordered());
+        assert_eq!(serde_json::from_str::<BetType>(r#""place""#).unwrap(), BetType::Place);
+        assert_eq!(serde_json::to_string(&BetType::Place).unwrap(), r#""PLACE""#);
+    }
+
+    #[test]
+    fn selections_parse_runner_numbers() {
+        let selection: Selection = "3/1/12".parse().unwrap();
+        assert_eq!(selection.runners, vec![3, 1, 12]);
+        assert_eq!(selection.to_string(), "3/1/12");
+
+        for text in ["", "A", "1/", "/1", "1//2", "1 /2", "0", "1/-2", "1/1", "99999999999"] {
+            assert!(text.parse::<Selection>().is_err(), "{:?} parsed", text);
+        }
+    }
+
+    #[test]
+    fn selections_win_on_the_placings() {
+        let placings: Selection = "3/1/7/2".parse().unwrap();
+        let wins = |text: &str, bet_type| text.parse::<Selection>().unwrap().finishes_in(&placings, bet_type);
+
+        assert!(wins("3", BetType::Win) && !wins("1", BetType::Win));
+        assert!(wins("7", BetType::Place) && !wins("2", BetType::Place));
+        assert!(wins("1/3", BetType::Quinella) && wins("3/1", BetType::Quinella));
+        assert!(wins("3/1", BetType::Exacta) && !wins("1/3", BetType::Exacta));
+        assert!(wins("3/1/7", BetType::Trifecta) && !wins("3/7/1", BetType::Trifecta));
+
+        // Too few finishers for the bet type
+        let short: Selection = "3/1".parse().unwrap();
+        assert!(!"3/1/7".parse::<Selection>().unwrap().finishes_in(&short, BetType::Trifecta));
+    }
+
+    #[test]
+    fn selections_must_match_the_bet_type() {
+        assert!(Selection::for_bet_type("1", BetType::Win).is_ok());
+        assert!(Selection::for_bet_type("1/2/3", BetType::Trifecta).is_ok());
+
+        assert_eq!(Selection::for_bet_type("1/2", BetType::Win).unwrap_err(), "WIN takes 1 runner, got 2");
+        assert_eq!(
+            Selection::for_bet_type("1/2", BetType::Trifecta).unwrap_err(),
+            "TRIFECTA takes 3 runners, got 2"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::models::account::BrokerEvent;
+
+/// A webhook subscription as shown to administrators; the signing secret is
+/// only returned on creation
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Webhook {
+    pub id: i64,
+    #[schema(example = "https://executor.example.com/hooks/betstream")]
+    pub url: String,
+    /// Event types delivered; `null` means every type
+    pub event_types: Option<Vec<String>>,
+    /// Only events for this account; `null` means every account
+    pub account_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Whether `event` passes this subscription's filters
+    pub fn wants(&self, event: &BrokerEvent) -> bool {
+        self.account_id.is_none_or(|id| event.account_id() == id)
+            && self
+                .event_types
+                .as_ref()
+                .is_none_or(|types| types.iter().any(|t| t == event.event_name()))
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    /// `http` or `https` URL that receives a POST per event
+    pub url: String,
+    /// Only deliver these event types; omit for every type
+    pub event_types: Option<Vec<String>>,
+    /// Only deliver events for this account; omit for every account
+    pub account_id: Option<i64>,
+}
+
+/// Returned once on creation; the secret cannot be retrieved again
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedWebhook {
+    /// HMAC-SHA256 key for the `X-Betstream-Signature` header
+    #[schema(example = "whsec_1a2b3c4d...")]
+    pub secret: String,
+    #[serde(flatten)]
+    pub webhook: Webhook,
+}
+
+/// A subscription ready to be persisted
+#[derive(Debug)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub event_types: Option<Vec<String>>,
+    pub account_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Waiting for its first attempt or a retry
+    Pending,
+    Delivered,
+    /// Gave up after `webhooks.max_attempts` attempts
+    Dead,
+}
+
+impl std::fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeliveryStatus::Pending => write!(f, "pending"),
+            DeliveryStatus::Delivered => write!(f, "delivered"),
+            DeliveryStatus::Dead => write!(f, "dead"),
+        }
+    }
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "delivered" => Ok(DeliveryStatus::Delivered),
+            "dead" => Ok(DeliveryStatus::Dead),
+            _ => Err(format!("Invalid delivery status: {}", s)),
+        }
+    }
+}
+
+/// One event's delivery to one webhook, with the outcome of its last attempt
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    /// Id of the event in the event log, also the SSE `id:`
+    pub event_id: i64,
+    pub event_type: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    /// When the next attempt is due; only set while pending
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// HTTP status of the last attempt, absent if no response was received
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A delivery due for an attempt, with what the worker needs to send it
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event_type: String,
+    pub payload: String,
+    /// Attempts made before this one
+    pub attempts: i32,
+}
+
+/// What became of a delivery attempt
+#[derive(Debug, Clone)]
+pub enum AttemptOutcome {
+    Delivered,
+    Retry { at: DateTime<Utc> },
+    Dead,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeliveryAttempt {
+    pub outcome: AttemptOutcome,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+/// Filters and paging for a webhook's delivery history
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveryQuery {
+    /// Maximum number of deliveries to return (1-200, default 50)
+    pub limit: Option<u32>,
+    /// Cursor returned as `next_cursor` by the previous page
+    pub cursor: Option<String>,
+    /// Only deliveries in this state, e.g. `dead` for the dead-letter queue
+    pub status: Option<DeliveryStatus>,
+}
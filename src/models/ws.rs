@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::ToSchema;
+
+use crate::error::ProblemDetails;
+use crate::models::account::BetStatus;
+
+/// A command sent over `/ws`; the response echoes `request_id`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WsRequest {
+    /// Client-chosen id, returned on the response and recorded as the
+    /// `correlation_id` of the events the command causes
+    pub request_id: String,
+    #[serde(flatten)]
+    pub command: WsCommand,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum WsCommand {
+    /// Same rules as `PATCH /api/v1/accounts/{id}/batches/{batch_id}/bets/{bet_id}`
+    UpdateBetStatus {
+        account_id: i64,
+        batch_id: i64,
+        pid: i64,
+        status: BetStatus,
+        reference: Option<String>,
+        message: Option<String>,
+        /// Skip the transition rules. Admin key only; completed batches stay
+        /// frozen regardless.
+        #[serde(default)]
+        force: bool,
+    },
+    /// Same rules as `DELETE /api/v1/accounts/{id}/batches/{batch_id}`
+    CompleteBatch { account_id: i64, batch_id: i64 },
+    /// Answered with the server time; keeps idle connections open
+    Heartbeat,
+    /// Start receiving the events matching a filter; `None` fields match
+    /// everything. Events matching several subscriptions are sent once.
+    Subscribe {
+        account_id: Option<i64>,
+        batch_id: Option<i64>,
+        /// Comma-separated event types, e.g. `bet_status_updated,batch_created`
+        types: Option<String>,
+    },
+    /// Stop the subscription with this id
+    Unsubscribe { subscription_id: u64 },
+}
+
+/// Response to one [`WsRequest`]: `result` on success, `error` otherwise
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WsResponse {
+    /// Always `response`, to tell responses from events
+    #[serde(rename = "type")]
+    #[schema(example = "response")]
+    pub message_type: &'static str,
+    /// `request_id` of the command; `null` if it could not be parsed
+    pub request_id: Option<String>,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ProblemDetails>,
+}
+
+/// `result` of a `heartbeat` command
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HeartbeatResult {
+    pub server_time: DateTime<Utc>,
+}
+
+/// `result` of a `subscribe` command
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SubscribeResult {
+    /// Pass to `unsubscribe` to stop it
+    pub subscription_id: u64,
+}
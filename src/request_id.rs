@@ -0,0 +1,81 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{request::Parts, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use rand::RngCore;
+
+use crate::auth::Principal;
+use crate::models::event::EventContext;
+
+/// Header carrying the request id, echoed on every response
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Longest client-supplied request id kept; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Id of the current request: the caller's `X-Request-Id` if usable,
+/// otherwise a generated one
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Middleware that assigns every request an id, stores it in the request
+/// extensions and returns it in the `X-Request-Id` response header
+pub async fn assign_request_id(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| is_usable(id))
+        .map(str::to_string)
+        .unwrap_or_else(generate);
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+fn is_usable(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn generate() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// The request id and caller, recorded with the events a handler's change
+/// writes. Missing pieces are left empty rather than rejecting the request.
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for EventContext {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(EventContext {
+            correlation_id: parts.extensions.get::<RequestId>().map(|id| id.0.clone()),
+            actor: parts.extensions.get::<Principal>().map(Principal::actor),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_short_printable_ids_are_kept() {
+        assert!(is_usable("req-123"));
+        assert!(is_usable(&"a".repeat(MAX_REQUEST_ID_LEN)));
+        assert!(!is_usable(""));
+        assert!(!is_usable("has space"));
+        assert!(!is_usable(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+        assert_eq!(generate().len(), 32);
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+use crate::config::{JsonlSinkConfig, SinksConfig};
+use crate::models::event::EventEnvelope;
+
+/// A destination the dispatcher writes every event to, besides SSE
+/// subscribers and webhooks. Events arrive one at a time in id order, after
+/// they are marked dispatched; a failed write is logged and not retried.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Short name used in logs
+    fn name(&self) -> &'static str;
+
+    async fn write(&self, envelope: &EventEnvelope) -> anyhow::Result<()>;
+}
+
+/// Build the sinks enabled in `config`, opening their files
+pub async fn from_config(config: &SinksConfig) -> anyhow::Result<Vec<Box<dyn EventSink>>> {
+    let mut sinks: Vec<Box<dyn EventSink>> = Vec::new();
+    if config.jsonl.enabled {
+        sinks.push(Box::new(JsonlSink::open(&config.jsonl).await?));
+    }
+    if config.stdout.enabled {
+        sinks.push(Box::new(StdoutSink));
+    }
+    Ok(sinks)
+}
+
+/// One line of the JSONL file: the envelope plus when it was dispatched
+#[derive(Debug, Serialize)]
+struct JsonlRecord<'a> {
+    #[serde(flatten)]
+    envelope: &'a EventEnvelope,
+    dispatched_at: DateTime<Utc>,
+}
+
+/// Appends each event as a JSON line, rotating the file by size
+pub struct JsonlSink {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+    file: Mutex<OpenFile>,
+}
+
+struct OpenFile {
+    file: File,
+    len: u64,
+}
+
+impl JsonlSink {
+    /// Open (or create) the active file, appending to what is already there
+    pub async fn open(config: &JsonlSinkConfig) -> anyhow::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        let file = open_append(&path).await?;
+        Ok(JsonlSink {
+            path,
+            max_bytes: config.max_bytes,
+            max_files: config.max_files,
+            file: Mutex::new(file),
+        })
+    }
+
+    // Shift `path.N` to `path.N+1`, dropping the oldest, move the active file
+    // to `path.1` and start a new one
+    async fn rotate(&self, current: &mut OpenFile) -> anyhow::Result<()> {
+        current.file.flush().await?;
+        for n in (1..self.max_files).rev() {
+            rename_if_exists(&rotated_path(&self.path, n), &rotated_path(&self.path, n + 1)).await?;
+        }
+        if self.max_files == 0 {
+            tokio::fs::remove_file(&self.path).await?;
+        } else {
+            tokio::fs::rename(&self.path, rotated_path(&self.path, 1)).await?;
+        }
+        *current = open_append(&self.path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for JsonlSink {
+    fn name(&self) -> &'static str {
+        "jsonl"
+    }
+
+    async fn write(&self, envelope: &EventEnvelope) -> anyhow::Result<()> {
+        let record = JsonlRecord {
+            envelope,
+            dispatched_at: Utc::now(),
+        };
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+
+        let mut current = self.file.lock().await;
+        if current.len > 0 && current.len + line.len() as u64 > self.max_bytes {
+            self.rotate(&mut current).await?;
+        }
+        current.file.write_all(&line).await?;
+        current.file.flush().await?;
+        current.len += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Structured log line on stdout for each event
+#[derive(Debug, Serialize)]
+struct LogRecord<'a> {
+    timestamp: DateTime<Utc>,
+    level: &'static str,
+    target: &'static str,
+    message: &'static str,
+    event_id: i64,
+    event_type: &'a str,
+    account_id: i64,
+    correlation_id: Option<&'a str>,
+    event: &'a EventEnvelope,
+}
+
+/// Prints each event as one JSON object per line, for log shippers
+pub struct StdoutSink;
+
+#[async_trait]
+impl EventSink for StdoutSink {
+    fn name(&self) -> &'static str {
+        "stdout"
+    }
+
+    async fn write(&self, envelope: &EventEnvelope) -> anyhow::Result<()> {
+        let record = LogRecord {
+            timestamp: Utc::now(),
+            level: "INFO",
+            target: "betstream::events",
+            message: "event dispatched",
+            event_id: envelope.event_id,
+            event_type: envelope.event.event_name(),
+            account_id: envelope.event.account_id(),
+            correlation_id: envelope.correlation_id.as_deref(),
+            event: envelope,
+        };
+        println!("{}", serde_json::to_string(&record)?);
+        Ok(())
+    }
+}
+
+async fn open_append(path: &Path) -> anyhow::Result<OpenFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path).await?;
+    let len = file.metadata().await?.len();
+    Ok(OpenFile { file, len })
+}
+
+fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
+
+async fn rename_if_exists(from: &Path, to: &Path) -> anyhow::Result<()> {
+    match tokio::fs::rename(from, to).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::account::BrokerEvent;
+    use crate::models::event::EVENT_SCHEMA_VERSION;
+
+    fn event(id: i64) -> EventEnvelope {
+        EventEnvelope {
+            event_id: id,
+            schema_version: EVENT_SCHEMA_VERSION,
+            emitted_at: Utc::now(),
+            correlation_id: None,
+            actor: None,
+            event: BrokerEvent::AccountDeleted { id },
+        }
+    }
+
+    fn lines(path: &Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn jsonl_sink_rotates_by_size() {
+        let dir = std::env::temp_dir().join(format!("betstream-sink-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("events.jsonl");
+        let config = JsonlSinkConfig {
+            enabled: true,
+            path: path.to_string_lossy().into_owned(),
+            // Room for one record per file
+            max_bytes: 200,
+            max_files: 2,
+        };
+
+        let sink = JsonlSink::open(&config).await.unwrap();
+        for id in 1..=4 {
+            sink.write(&event(id)).await.unwrap();
+        }
+
+        let active = lines(&path);
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0]["event_id"], 4);
+        assert_eq!(active[0]["type"], "account_deleted");
+        assert!(active[0]["dispatched_at"].is_string());
+        let replayed: BrokerEvent = serde_json::from_value(active[0].clone()).unwrap();
+        assert!(matches!(replayed, BrokerEvent::AccountDeleted { id: 4 }));
+        assert_eq!(lines(&rotated_path(&path, 1))[0]["event_id"], 3);
+        assert_eq!(lines(&rotated_path(&path, 2))[0]["event_id"], 2);
+        assert!(!rotated_path(&path, 3).exists());
+
+        // Reopening appends to the active file
+        let config = JsonlSinkConfig { max_bytes: 1024, ..config };
+        let sink = JsonlSink::open(&config).await.unwrap();
+        sink.write(&event(5)).await.unwrap();
+        assert_eq!(lines(&path).len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,529 @@
+pub mod postgres;
+pub mod sqlite;
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use sqlx::FromRow;
+
+use crate::config::DatabaseConfig;
+use crate::error::ApiError;
+use crate::models::account::*;
+use crate::models::api_key::{ApiKey, KeyAccess, NewApiKey};
+use crate::models::event::{Actor, EventContext, EventEnvelope, StateSnapshot, EVENT_SCHEMA_VERSION};
+use crate::models::idempotency::IdempotencyRecord;
+use crate::models::money::{Currency, ExchangeRate, Money};
+use crate::models::pagination::{Page, PageRequest};
+use crate::models::race::{RaceResult, RaceSettlement, SetRaceResultRequest, Settlement};
+use crate::models::report::{FxRate, StakeTotals, TotalsQuery};
+use crate::models::selection::BetType;
+use crate::models::webhook::{
+    AttemptOutcome, DeliveryAttempt, DeliveryStatus, DueDelivery, NewWebhook, Webhook, WebhookDelivery,
+};
+
+pub use postgres::PostgresStore;
+pub use sqlite::SqliteStore;
+
+/// Errors raised by store implementations
+#[derive(Debug)]
+pub enum StoreError {
+    NotFound(String),
+    /// The change is not allowed in the current state, e.g. an illegal bet
+    /// status transition
+    Conflict(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(err: sqlx::Error) -> Self {
+        Self::Database(err)
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(err: StoreError) -> Self {
+        match err {
+            StoreError::NotFound(detail) => ApiError::NotFound(detail),
+            StoreError::Conflict(detail) => ApiError::Conflict(detail),
+            StoreError::Database(e) => ApiError::from(e),
+        }
+    }
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// Persistence for accounts, batches and bets
+///
+/// Handlers only talk to this trait; `connect` picks the backend from the
+/// database URL. Every change writes its [`BrokerEvent`] to the event log in
+/// the same transaction, along with the request's [`EventContext`], where it
+/// waits undispatched until the dispatcher hands it to subscribers.
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// List accounts, limited to `scope` when given
+    async fn list_accounts(
+        &self,
+        filter: &AccountQuery,
+        scope: Option<&[i64]>,
+        page: &PageRequest,
+    ) -> StoreResult<Page<Account>>;
+
+    async fn get_account(&self, id: i64) -> StoreResult<Option<Account>>;
+
+    /// Insert an account and log `AccountCreated`
+    async fn create_account(&self, req: &CreateAccountRequest, ctx: &EventContext) -> StoreResult<Account>;
+
+    /// Update an account and log `AccountUpdated`
+    async fn update_account(
+        &self,
+        id: i64,
+        req: &CreateAccountRequest,
+        ctx: &EventContext,
+    ) -> StoreResult<Option<Account>>;
+
+    /// Delete an account and, by cascade, its batches and bets, and log
+    /// `AccountDeleted`. Returns `false` if the account did not exist.
+    async fn delete_account(&self, id: i64, ctx: &EventContext) -> StoreResult<bool>;
+
+    /// Insert a batch and all of its bets in the account's currency in one
+    /// transaction and log `BatchCreated`
+    async fn create_batch(
+        &self,
+        account_id: i64,
+        req: &CreateBatchRequest,
+        ctx: &EventContext,
+    ) -> StoreResult<BatchResponse>;
+
+    async fn list_batches(
+        &self,
+        account_id: i64,
+        filter: &BatchQuery,
+        page: &PageRequest,
+    ) -> StoreResult<Page<BatchResponse>>;
+
+    async fn get_batch(&self, account_id: i64, batch_id: i64)
+        -> StoreResult<Option<BatchResponse>>;
+
+    /// Mark an open batch completed and log `BatchCompleted`. Returns `false`
+    /// if no open batch matched.
+    async fn complete_batch(&self, account_id: i64, batch_id: i64, ctx: &EventContext) -> StoreResult<bool>;
+
+    async fn get_bet(&self, account_id: i64, batch_id: i64, pid: i64)
+        -> StoreResult<Option<Bet>>;
+
+    /// Apply `change` if the bet is currently in one of `change.from` and its
+    /// batch is still open, logging `BetStatusUpdated`; otherwise fails with
+    /// `Conflict`
+    async fn update_bet_status(
+        &self,
+        account_id: i64,
+        batch_id: i64,
+        change: &BetStatusChange,
+        ctx: &EventContext,
+    ) -> StoreResult<Option<Bet>>;
+
+    /// Apply several changes atomically, under the same rules as
+    /// `update_bet_status`. Returns one entry per change, `None` for bets not
+    /// in the batch. Fails with `NotFound` if the account has no such batch,
+    /// or `Conflict` (changing nothing) if any bet cannot change. Logs one
+    /// `BatchBetsUpdated` for the bets that changed, if any.
+    async fn update_bets_status(
+        &self,
+        account_id: i64,
+        batch_id: i64,
+        changes: &[BetStatusChange],
+        ctx: &EventContext,
+    ) -> StoreResult<Vec<Option<Bet>>>;
+
+    /// Insert a key and its account scope in one transaction
+    async fn create_api_key(&self, key: &NewApiKey) -> StoreResult<ApiKey>;
+
+    async fn list_api_keys(&self) -> StoreResult<Vec<ApiKey>>;
+
+    /// Look up an unrevoked key by the hash of its secret
+    async fn find_api_key(&self, key_hash: &str) -> StoreResult<Option<ApiKey>>;
+
+    /// Revoke a key. Returns `false` if no unrevoked key had this id.
+    async fn revoke_api_key(&self, id: i64) -> StoreResult<bool>;
+
+    /// Claim `key` for a new request after purging records older than `ttl`.
+    /// A claim with no response that is older than `lease` is taken over.
+    /// Returns `None` once claimed, or the existing record if the key is taken.
+    async fn reserve_idempotency_key(
+        &self,
+        account_id: i64,
+        key: &str,
+        request_hash: &str,
+        ttl: Duration,
+        lease: Duration,
+    ) -> StoreResult<Option<IdempotencyRecord>>;
+
+    /// Store the response of a claimed key for replay
+    async fn complete_idempotency_key(
+        &self,
+        account_id: i64,
+        key: &str,
+        status_code: u16,
+        response_body: &str,
+    ) -> StoreResult<()>;
+
+    /// Drop an unfinished claim so the request can be retried
+    async fn release_idempotency_key(&self, account_id: i64, key: &str) -> StoreResult<()>;
+
+    /// Append an event to the event log, undispatched, and return its id. Ids
+    /// only increase, in commit order.
+    async fn append_event(&self, event: &BrokerEvent, ctx: &EventContext) -> StoreResult<i64>;
+
+    /// Up to `limit` events not dispatched yet, oldest first
+    async fn undispatched_events(&self, limit: i64) -> StoreResult<Vec<EventEnvelope>>;
+
+    /// Mark an event dispatched and, in the same transaction, queue a
+    /// delivery to every webhook that wants it. Returns the number of
+    /// deliveries queued, or `None` if the event was already dispatched.
+    async fn dispatch_event(&self, envelope: &EventEnvelope) -> StoreResult<Option<u64>>;
+
+    /// Up to `limit` logged events with an id above `after_id`, oldest first
+    async fn events_after(&self, after_id: i64, limit: i64) -> StoreResult<Vec<EventEnvelope>>;
+
+    /// Like `events_after`, but only dispatched events, stopping before the
+    /// first undispatched one so a relay following the log never skips an
+    /// event that is dispatched late
+    async fn dispatched_events_after(&self, after_id: i64, limit: i64) -> StoreResult<Vec<EventEnvelope>>;
+
+    /// Newest id that, with every earlier event, is dispatched; 0 if none is
+    async fn dispatched_through(&self) -> StoreResult<i64>;
+
+    /// A stream that yields whenever any connection dispatches events, for
+    /// backends that can push (PostgreSQL `LISTEN`); `None` means poll
+    async fn watch_dispatches(&self) -> StoreResult<Option<BoxStream<'static, ()>>>;
+
+    /// Oldest and newest id still in the event log, or `None` if it is empty
+    async fn event_id_range(&self) -> StoreResult<Option<(i64, i64)>>;
+
+    /// Accounts (limited to `scope` when given) and their open batches, read
+    /// in one transaction together with the newest event id, so the snapshot
+    /// reflects exactly the events up to that id
+    async fn snapshot(&self, scope: Option<&[i64]>) -> StoreResult<StateSnapshot>;
+
+    /// Delete dispatched events older than `retention`, always keeping the
+    /// newest one so the log still knows the latest id. Returns the number
+    /// deleted.
+    async fn prune_events(&self, retention: Duration) -> StoreResult<u64>;
+
+    async fn create_webhook(&self, webhook: &NewWebhook) -> StoreResult<Webhook>;
+
+    async fn list_webhooks(&self) -> StoreResult<Vec<Webhook>>;
+
+    async fn get_webhook(&self, id: i64) -> StoreResult<Option<Webhook>>;
+
+    /// Delete a subscription and its delivery history. Returns `false` if it
+    /// did not exist.
+    async fn delete_webhook(&self, id: i64) -> StoreResult<bool>;
+
+    /// Claim up to `limit` pending deliveries due at `now`, oldest first, by
+    /// pushing their next attempt `lease` past `now` in the same statement,
+    /// so instances sharing the database never send the same delivery at
+    /// once. A claim whose attempt is never recorded lapses with its lease.
+    async fn claim_webhook_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        lease: Duration,
+        limit: i64,
+    ) -> StoreResult<Vec<DueDelivery>>;
+
+    /// Record an attempt on a delivery and schedule, finish or dead-letter it
+    async fn record_webhook_attempt(&self, delivery_id: i64, attempt: &DeliveryAttempt) -> StoreResult<()>;
+
+    /// A webhook's deliveries, newest first, optionally in one state only
+    async fn list_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        status: Option<DeliveryStatus>,
+        page: &PageRequest,
+    ) -> StoreResult<Page<WebhookDelivery>>;
+
+    async fn list_fx_rates(&self) -> StoreResult<Vec<FxRate>>;
+
+    /// Insert or replace the rate from `from` to `to`
+    async fn set_fx_rate(&self, from: Currency, to: Currency, rate: ExchangeRate) -> StoreResult<FxRate>;
+
+    /// Returns `false` if there was no such rate
+    async fn delete_fx_rate(&self, from: Currency, to: Currency) -> StoreResult<bool>;
+
+    /// Bets, stake and cost per currency for the bets matching `filter`
+    /// (its `currency` aside), limited to the accounts in `scope` if given
+    async fn stake_totals(&self, filter: &TotalsQuery, scope: Option<&[i64]>) -> StoreResult<Vec<StakeTotals>>;
+
+    async fn get_race_result(&self, race_id: i64) -> StoreResult<Option<RaceResult>>;
+
+    /// Distinct bet type and selection of the successful bets in the race's
+    /// batches, for checking a result declares a dividend for every winner
+    async fn race_selections(&self, race_id: i64) -> StoreResult<Vec<(BetType, String)>>;
+
+    /// Insert or replace a race's result and, in the same transaction,
+    /// settle every successful bet in the race's batches under it. A winning
+    /// bet without a declared dividend is left as it is. Logs
+    /// `BetSettled` for each bet whose settlement changed, and
+    /// `BatchSettled` for each of their batches left with no unsettled bet.
+    /// Writes nothing and fails with a conflict if a batch would be paid more
+    /// than `Money::MAX` in all.
+    async fn set_race_result(
+        &self,
+        race_id: i64,
+        req: &SetRaceResultRequest,
+        ctx: &EventContext,
+    ) -> StoreResult<RaceSettlement>;
+}
+
+// A successful bet in one of a race's batches, with its batch's account and
+// bet type. Batches from before bet types were required may have none.
+#[derive(FromRow)]
+struct RaceBetRow {
+    #[sqlx(flatten)]
+    bet: Bet,
+    account_id: i64,
+    bet_type: Option<BetType>,
+}
+
+// Bets whose settlement under `result` differs from what they have stored,
+// with their new settlement, and how many bets cannot be settled: their
+// batch has no bet type, their selection no longer parses, or they won
+// without a declared dividend. Those are left as they are. A conflict if
+// any batch would then be paid more than `Money::MAX` in all, so that no
+// payout or batch total is written past the amounts requests are held to.
+fn settlement_changes(result: &RaceResult, rows: Vec<RaceBetRow>) -> StoreResult<(Vec<(RaceBetRow, Settlement)>, i64)> {
+    let mut unsettled = 0;
+    let mut changes = Vec::new();
+    let mut batch_payouts: Vec<(i64, Option<Money>)> = Vec::new();
+    for row in rows {
+        let settlement = row.bet_type.and_then(|bet_type| result.settle(bet_type, &row.bet.selection, row.bet.stake));
+        let payout = settlement.map(|s| s.payout).or(row.bet.payout).unwrap_or(Money::ZERO);
+        match batch_payouts.iter_mut().find(|(id, _)| *id == row.bet.batch_id) {
+            Some((_, total)) => *total = total.and_then(|total| total.checked_add(payout)),
+            None => batch_payouts.push((row.bet.batch_id, Some(payout))),
+        }
+        match settlement {
+            None => unsettled += 1,
+            Some(settlement) => {
+                let unchanged = row.bet.result == Some(settlement.result) && row.bet.payout == Some(settlement.payout);
+                if !unchanged {
+                    changes.push((row, settlement));
+                }
+            }
+        }
+    }
+
+    if let Some((batch_id, _)) = batch_payouts.iter().find(|(_, total)| total.is_none_or(|total| total > Money::MAX)) {
+        return Err(StoreError::Conflict(format!(
+            "The result for race {} would pay batch {} more than {}",
+            result.race_id,
+            batch_id,
+            Money::MAX
+        )));
+    }
+    Ok((changes, unsettled))
+}
+
+// A batch's bets counted for `BatchSettled`
+#[derive(FromRow)]
+struct BatchSettlementRow {
+    unsettled: i64,
+    settled: i64,
+    won: i64,
+    stake: Money,
+    payout: Money,
+}
+
+// `api_keys` row; the scope lives in `api_key_accounts`
+#[derive(FromRow)]
+struct ApiKeyRow {
+    id: i64,
+    name: String,
+    key_prefix: String,
+    access: String,
+    all_accounts: bool,
+    created_at: DateTime<Utc>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKeyRow {
+    fn into_api_key(self, account_ids: Vec<i64>) -> StoreResult<ApiKey> {
+        let access = self
+            .access
+            .parse::<KeyAccess>()
+            .map_err(|e| StoreError::Database(sqlx::Error::Decode(e.into())))?;
+
+        Ok(ApiKey {
+            id: self.id,
+            name: self.name,
+            key_prefix: self.key_prefix,
+            access,
+            account_ids: (!self.all_accounts).then_some(account_ids),
+            created_at: self.created_at,
+            revoked_at: self.revoked_at,
+        })
+    }
+}
+
+// Join key rows with their `(api_key_id, account_id)` scope rows
+fn assemble_api_keys(rows: Vec<ApiKeyRow>, scopes: Vec<(i64, i64)>) -> StoreResult<Vec<ApiKey>> {
+    let mut ids_by_key: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (key_id, account_id) in scopes {
+        ids_by_key.entry(key_id).or_default().push(account_id);
+    }
+
+    rows.into_iter()
+        .map(|row| {
+            let ids = ids_by_key.remove(&row.id).unwrap_or_default();
+            row.into_api_key(ids)
+        })
+        .collect()
+}
+
+// Serialize an event, actor or envelope for a JSON text column
+fn encode_json<T: serde::Serialize>(value: &T) -> StoreResult<String> {
+    serde_json::to_string(value).map_err(|e| StoreError::Database(sqlx::Error::Protocol(e.to_string())))
+}
+
+// `webhooks` row; `event_types` is stored comma-separated
+#[derive(FromRow)]
+struct WebhookRow {
+    id: i64,
+    url: String,
+    event_types: Option<String>,
+    account_id: Option<i64>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<WebhookRow> for Webhook {
+    fn from(row: WebhookRow) -> Self {
+        Webhook {
+            id: row.id,
+            url: row.url,
+            event_types: row
+                .event_types
+                .map(|types| types.split(',').map(str::to_string).collect()),
+            account_id: row.account_id,
+            created_at: row.created_at,
+        }
+    }
+}
+
+// `webhook_deliveries` row without its payload
+#[derive(FromRow)]
+struct DeliveryRow {
+    id: i64,
+    webhook_id: i64,
+    event_id: i64,
+    event_type: String,
+    status: String,
+    attempts: i32,
+    next_attempt_at: i64,
+    last_status_code: Option<i32>,
+    last_error: Option<String>,
+    last_attempt_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl DeliveryRow {
+    fn into_delivery(self) -> StoreResult<WebhookDelivery> {
+        let status = self
+            .status
+            .parse::<DeliveryStatus>()
+            .map_err(|e| StoreError::Database(sqlx::Error::Decode(e.into())))?;
+
+        Ok(WebhookDelivery {
+            id: self.id,
+            webhook_id: self.webhook_id,
+            event_id: self.event_id,
+            event_type: self.event_type,
+            status,
+            attempts: self.attempts,
+            next_attempt_at: (status == DeliveryStatus::Pending)
+                .then(|| DateTime::from_timestamp_millis(self.next_attempt_at))
+                .flatten(),
+            last_status_code: self.last_status_code,
+            last_error: self.last_error,
+            last_attempt_at: self.last_attempt_at,
+            created_at: self.created_at,
+        })
+    }
+}
+
+// New status and, for retries, the next due time in unix milliseconds
+fn attempt_schedule(outcome: &AttemptOutcome) -> (DeliveryStatus, Option<i64>) {
+    match outcome {
+        AttemptOutcome::Delivered => (DeliveryStatus::Delivered, None),
+        AttemptOutcome::Retry { at } => (DeliveryStatus::Pending, Some(at.timestamp_millis())),
+        AttemptOutcome::Dead => (DeliveryStatus::Dead, None),
+    }
+}
+
+// Join a webhook's event types for the `event_types` column
+fn join_event_types(types: &Option<Vec<String>>) -> Option<String> {
+    types.as_ref().map(|types| types.join(","))
+}
+
+// Event log row; `payload` is the `BrokerEvent` and `actor` an `Actor`, as JSON
+#[derive(FromRow)]
+struct EventRow {
+    id: i64,
+    payload: String,
+    created_at: DateTime<Utc>,
+    correlation_id: Option<String>,
+    actor: Option<String>,
+}
+
+impl EventRow {
+    fn into_envelope(self) -> StoreResult<EventEnvelope> {
+        let decode = |e: serde_json::Error| StoreError::Database(sqlx::Error::Decode(Box::new(e)));
+        let actor = match self.actor {
+            Some(actor) => Some(serde_json::from_str::<Actor>(&actor).map_err(decode)?),
+            None => None,
+        };
+        Ok(EventEnvelope {
+            event_id: self.id,
+            schema_version: EVENT_SCHEMA_VERSION,
+            emitted_at: self.created_at,
+            correlation_id: self.correlation_id,
+            actor,
+            event: serde_json::from_str(&self.payload).map_err(decode)?,
+        })
+    }
+}
+
+// Decode rows of the event log
+fn decode_events(rows: Vec<EventRow>) -> StoreResult<Vec<EventEnvelope>> {
+    rows.into_iter().map(EventRow::into_envelope).collect()
+}
+
+// Explain why a guarded status update matched no row, given the bet's
+// current status and whether its batch is completed
+fn status_conflict(pid: i64, batch_id: i64, current: &str, completed: bool, next: BetStatus) -> StoreError {
+    if completed {
+        StoreError::Conflict(format!("Batch {} is completed; its bets can no longer change", batch_id))
+    } else {
+        StoreError::Conflict(format!("Bet {} cannot change from {} to {}", pid, current, next))
+    }
+}
+
+/// Connect to the backend named by `config.url` and run its migrations
+///
+/// `postgres://` and `postgresql://` URLs select PostgreSQL, anything else is
+/// treated as SQLite.
+pub async fn connect(config: &DatabaseConfig) -> anyhow::Result<Arc<dyn Store>> {
+    if is_postgres_url(&config.url) {
+        Ok(Arc::new(PostgresStore::connect(config).await?))
+    } else {
+        Ok(Arc::new(SqliteStore::connect(config).await?))
+    }
+}
+
+pub fn is_postgres_url(database_url: &str) -> bool {
+    database_url.starts_with("postgres://") || database_url.starts_with("postgresql://")
+}
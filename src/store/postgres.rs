@@ -0,0 +1,1250 @@
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use sqlx::{
+    postgres::{PgListener, PgPoolOptions},
+    PgConnection, PgPool, Postgres, QueryBuilder,
+};
+
+use super::{
+    assemble_api_keys, attempt_schedule, decode_events, encode_json, join_event_types,
+    settlement_changes, status_conflict, ApiKeyRow, BatchSettlementRow, DeliveryRow, EventRow, RaceBetRow, Store,
+    StoreError, StoreResult, WebhookRow,
+};
+use crate::config::DatabaseConfig;
+use crate::models::account::*;
+use crate::models::api_key::{ApiKey, NewApiKey};
+use crate::models::event::{EventContext, EventEnvelope, StateSnapshot};
+use crate::models::idempotency::IdempotencyRecord;
+use crate::models::money::{Currency, ExchangeRate};
+use crate::models::pagination::{Cursor, Page, PageRequest};
+use crate::models::race::{BetResult, RaceResult, RaceSettlement, SetRaceResultRequest};
+use crate::models::selection::BetType;
+use crate::models::report::{FxRate, StakeTotals, TotalsQuery};
+use crate::models::webhook::{
+    DeliveryAttempt, DeliveryStatus, DueDelivery, NewWebhook, Webhook, WebhookDelivery,
+};
+
+// Advisory lock key held by transactions that write to the event log
+const EVENT_LOG_LOCK: i64 = 0x6265_7473_7472_6d01;
+
+// Channel notified, with the event id, when an event is dispatched
+const DISPATCH_CHANNEL: &str = "betstream_dispatched";
+
+/// PostgreSQL-backed store for shared or multi-node deployments
+#[derive(Clone)]
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn connect(config: &DatabaseConfig) -> anyhow::Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect(&config.url)
+            .await?;
+        Self::from_pool(pool).await
+    }
+
+    /// Wrap an existing pool, running migrations first
+    pub async fn from_pool(pool: PgPool) -> anyhow::Result<Self> {
+        sqlx::migrate!("./migrations/postgres").run(&pool).await?;
+        Ok(PostgresStore { pool })
+    }
+}
+
+#[async_trait]
+impl Store for PostgresStore {
+    async fn list_accounts(
+        &self,
+        filter: &AccountQuery,
+        scope: Option<&[i64]>,
+        page: &PageRequest,
+    ) -> StoreResult<Page<Account>> {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM accounts WHERE 1 = 1");
+        if let Some(name) = &filter.name {
+            qb.push(" AND name = ").push_bind(name);
+        }
+        if let Some(hostname) = &filter.hostname {
+            qb.push(" AND hostname = ").push_bind(hostname);
+        }
+        if let Some(ids) = scope {
+            qb.push(" AND id = ANY(").push_bind(ids.to_vec()).push(")");
+        }
+        push_page(&mut qb, page);
+
+        let accounts = qb
+            .build_query_as::<Account>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(Page::from_probe(accounts, page.limit, |a| Cursor {
+            created_at: a.created_at,
+            id: a.id,
+        }))
+    }
+
+    async fn get_account(&self, id: i64) -> StoreResult<Option<Account>> {
+        let account = sqlx::query_as::<_, Account>("SELECT * FROM accounts WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(account)
+    }
+
+    async fn create_account(&self, req: &CreateAccountRequest, ctx: &EventContext) -> StoreResult<Account> {
+        let mut tx = self.pool.begin().await?;
+
+        let account = sqlx::query_as::<_, Account>(
+            r#"
+            INSERT INTO accounts (name, hostname, currency, created_at, updated_at)
+            VALUES ($1, $2, $3, now(), now())
+            RETURNING *
+            "#,
+        )
+        .bind(&req.name)
+        .bind(&req.hostname)
+        .bind(req.currency)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Self::log_event(&mut tx, &BrokerEvent::AccountCreated { account: account.clone() }, ctx).await?;
+        tx.commit().await?;
+
+        Ok(account)
+    }
+
+    async fn update_account(
+        &self,
+        id: i64,
+        req: &CreateAccountRequest,
+        ctx: &EventContext,
+    ) -> StoreResult<Option<Account>> {
+        let mut tx = self.pool.begin().await?;
+
+        let account = sqlx::query_as::<_, Account>(
+            r#"
+            UPDATE accounts
+            SET name = $1, hostname = $2, currency = $3, updated_at = now()
+            WHERE id = $4
+            RETURNING *
+            "#,
+        )
+        .bind(&req.name)
+        .bind(&req.hostname)
+        .bind(req.currency)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(account) = &account {
+            Self::log_event(&mut tx, &BrokerEvent::AccountUpdated { account: account.clone() }, ctx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(account)
+    }
+
+    async fn delete_account(&self, id: i64, ctx: &EventContext) -> StoreResult<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // CASCADE will handle batches and bets automatically
+        let result = sqlx::query("DELETE FROM accounts WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let deleted = result.rows_affected() > 0;
+        if deleted {
+            Self::log_event(&mut tx, &BrokerEvent::AccountDeleted { id }, ctx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+
+    async fn create_batch(
+        &self,
+        account_id: i64,
+        req: &CreateBatchRequest,
+        ctx: &EventContext,
+    ) -> StoreResult<BatchResponse> {
+        let mut tx = self.pool.begin().await?;
+
+        let currency = sqlx::query_scalar::<_, Currency>("SELECT currency FROM accounts WHERE id = $1")
+            .bind(account_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            // A missing account fails the insert below on its foreign key
+            .unwrap_or_default();
+
+        let batch = sqlx::query_as::<_, Batch>(
+            r#"
+            INSERT INTO batches (race_id, bet_type, meeting, venue, strategy, meta, account_id, currency, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now(), now())
+            RETURNING *
+            "#,
+        )
+        .bind(req.meta.race_id)
+        .bind(req.meta.bet_type)
+        .bind(&req.meta.meeting)
+        .bind(&req.meta.venue)
+        .bind(&req.meta.strategy)
+        .bind(sqlx::types::Json(&req.meta.extra))
+        .bind(account_id)
+        .bind(currency)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut bets = Vec::with_capacity(req.bets.len());
+        for bet_request in &req.bets {
+            let bet = sqlx::query_as::<_, Bet>(
+                r#"
+                INSERT INTO bets (id, selection, stake, cost, currency, batch_id)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING *
+                "#,
+            )
+            .bind(bet_request.id)
+            .bind(&bet_request.selection)
+            .bind(bet_request.stake)
+            .bind(bet_request.cost)
+            .bind(currency)
+            .bind(batch.id)
+            .fetch_one(&mut *tx)
+            .await?;
+            bets.push(bet);
+        }
+
+        let batch = BatchResponse::new(batch, bets);
+        Self::log_event(&mut tx, &BrokerEvent::BatchCreated { batch: batch.clone() }, ctx).await?;
+        tx.commit().await?;
+
+        Ok(batch)
+    }
+
+    async fn list_batches(
+        &self,
+        account_id: i64,
+        filter: &BatchQuery,
+        page: &PageRequest,
+    ) -> StoreResult<Page<BatchResponse>> {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM batches WHERE account_id = ");
+        qb.push_bind(account_id);
+        if let Some(completed) = filter.completed {
+            qb.push(" AND completed = ").push_bind(completed);
+        }
+        if let Some(after) = filter.created_after {
+            qb.push(" AND created_at >= ").push_bind(after);
+        }
+        if let Some(before) = filter.created_before {
+            qb.push(" AND created_at < ").push_bind(before);
+        }
+        if let Some(race_id) = filter.race_id {
+            qb.push(" AND race_id = ").push_bind(race_id);
+        }
+        if let Some(bet_type) = filter.bet_type {
+            qb.push(" AND bet_type = ").push_bind(bet_type);
+        }
+        if let Some(meeting) = &filter.meeting {
+            qb.push(" AND meeting = ").push_bind(meeting);
+        }
+        if let Some(venue) = &filter.venue {
+            qb.push(" AND venue = ").push_bind(venue);
+        }
+        if let Some(strategy) = &filter.strategy {
+            qb.push(" AND strategy = ").push_bind(strategy);
+        }
+        push_page(&mut qb, page);
+
+        let batches = qb
+            .build_query_as::<Batch>()
+            .fetch_all(&self.pool)
+            .await?;
+        let batches = Page::from_probe(batches, page.limit, |b| Cursor {
+            created_at: b.created_at,
+            id: b.id,
+        });
+
+        let mut conn = self.pool.acquire().await?;
+        let items = Self::attach_bets(&mut conn, batches.items).await?;
+        Ok(Page {
+            items,
+            next_cursor: batches.next_cursor,
+            has_more: batches.has_more,
+            limit: batches.limit,
+        })
+    }
+
+    async fn get_batch(
+        &self,
+        account_id: i64,
+        batch_id: i64,
+    ) -> StoreResult<Option<BatchResponse>> {
+        let batch = sqlx::query_as::<_, Batch>(
+            "SELECT * FROM batches WHERE id = $1 AND account_id = $2",
+        )
+        .bind(batch_id)
+        .bind(account_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match batch {
+            Some(batch) => {
+                let mut conn = self.pool.acquire().await?;
+                Ok(Self::attach_bets(&mut conn, vec![batch]).await?.pop())
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn complete_batch(&self, account_id: i64, batch_id: i64, ctx: &EventContext) -> StoreResult<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE batches
+            SET completed = TRUE, updated_at = now()
+            WHERE id = $1 AND account_id = $2 AND completed = FALSE
+            "#,
+        )
+        .bind(batch_id)
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let completed = result.rows_affected() > 0;
+        if completed {
+            Self::log_event(&mut tx, &BrokerEvent::BatchCompleted { id: batch_id, account_id }, ctx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(completed)
+    }
+
+    async fn get_bet(
+        &self,
+        account_id: i64,
+        batch_id: i64,
+        pid: i64,
+    ) -> StoreResult<Option<Bet>> {
+        let bet = sqlx::query_as::<_, Bet>(
+            r#"
+            SELECT bets.* FROM bets
+            JOIN batches ON batches.id = bets.batch_id
+            WHERE bets.pid = $1 AND bets.batch_id = $2 AND batches.account_id = $3
+            "#,
+        )
+        .bind(pid)
+        .bind(batch_id)
+        .bind(account_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(bet)
+    }
+
+    async fn update_bet_status(
+        &self,
+        account_id: i64,
+        batch_id: i64,
+        change: &BetStatusChange,
+        ctx: &EventContext,
+    ) -> StoreResult<Option<Bet>> {
+        let mut tx = self.pool.begin().await?;
+
+        let bet = Self::set_bet_status(&mut tx, account_id, batch_id, change).await?;
+        if let Some(bet) = &bet {
+            Self::log_event(&mut tx, &BrokerEvent::BetStatusUpdated { account_id, bet: bet.clone() }, ctx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(bet)
+    }
+
+    async fn update_bets_status(
+        &self,
+        account_id: i64,
+        batch_id: i64,
+        changes: &[BetStatusChange],
+        ctx: &EventContext,
+    ) -> StoreResult<Vec<Option<Bet>>> {
+        let mut tx = self.pool.begin().await?;
+
+        let completed = sqlx::query_scalar::<_, bool>("SELECT completed FROM batches WHERE id = $1 AND account_id = $2")
+            .bind(batch_id)
+            .bind(account_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                StoreError::NotFound(format!(
+                    "Batch {} not found for account {}",
+                    batch_id, account_id
+                ))
+            })?;
+        if completed {
+            return Err(StoreError::Conflict(format!(
+                "Batch {} is completed; its bets can no longer change",
+                batch_id
+            )));
+        }
+
+        let mut updated_bets = Vec::with_capacity(changes.len());
+        for change in changes {
+            updated_bets.push(Self::set_bet_status(&mut tx, account_id, batch_id, change).await?);
+        }
+
+        let bets: Vec<Bet> = updated_bets.iter().flatten().cloned().collect();
+        if !bets.is_empty() {
+            Self::log_event(&mut tx, &BrokerEvent::BatchBetsUpdated { batch_id, account_id, bets }, ctx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(updated_bets)
+    }
+
+    async fn create_api_key(&self, key: &NewApiKey) -> StoreResult<ApiKey> {
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query_as::<_, ApiKeyRow>(
+            r#"
+            INSERT INTO api_keys (name, key_hash, key_prefix, access, all_accounts, created_at)
+            VALUES ($1, $2, $3, $4, $5, now())
+            RETURNING *
+            "#,
+        )
+        .bind(&key.name)
+        .bind(&key.key_hash)
+        .bind(&key.key_prefix)
+        .bind(key.access.to_string())
+        .bind(key.account_ids.is_none())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let account_ids = key.account_ids.clone().unwrap_or_default();
+        for &account_id in &account_ids {
+            sqlx::query("INSERT INTO api_key_accounts (api_key_id, account_id) VALUES ($1, $2)")
+                .bind(row.id)
+                .bind(account_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        row.into_api_key(account_ids)
+    }
+
+    async fn list_api_keys(&self) -> StoreResult<Vec<ApiKey>> {
+        let rows = sqlx::query_as::<_, ApiKeyRow>("SELECT * FROM api_keys ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        let scopes = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT api_key_id, account_id FROM api_key_accounts ORDER BY account_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        assemble_api_keys(rows, scopes)
+    }
+
+    async fn find_api_key(&self, key_hash: &str) -> StoreResult<Option<ApiKey>> {
+        let row = sqlx::query_as::<_, ApiKeyRow>(
+            "SELECT * FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+        )
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let scopes = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT api_key_id, account_id FROM api_key_accounts WHERE api_key_id = $1 ORDER BY account_id",
+        )
+        .bind(row.id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(assemble_api_keys(vec![row], scopes)?.pop())
+    }
+
+    async fn revoke_api_key(&self, id: i64) -> StoreResult<bool> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET revoked_at = now() WHERE id = $1 AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn reserve_idempotency_key(
+        &self,
+        account_id: i64,
+        key: &str,
+        request_hash: &str,
+        ttl: Duration,
+        lease: Duration,
+    ) -> StoreResult<Option<IdempotencyRecord>> {
+        sqlx::query(
+            "DELETE FROM idempotency_keys WHERE created_at < now() - make_interval(secs => $1)",
+        )
+        .bind(ttl.as_secs() as f64)
+        .execute(&self.pool)
+        .await?;
+
+        // Retry if the holder released the key between our insert and select
+        loop {
+            let claimed = sqlx::query(
+                r#"
+                INSERT INTO idempotency_keys (account_id, idempotency_key, request_hash, claimed_at)
+                VALUES ($1, $2, $3, now())
+                ON CONFLICT (account_id, idempotency_key) DO NOTHING
+                "#,
+            )
+            .bind(account_id)
+            .bind(key)
+            .bind(request_hash)
+            .execute(&self.pool)
+            .await?;
+            if claimed.rows_affected() > 0 {
+                return Ok(None);
+            }
+
+            // A claim whose request never finished lapses with its lease
+            let taken_over = sqlx::query(
+                r#"
+                UPDATE idempotency_keys SET request_hash = $1, claimed_at = now()
+                WHERE account_id = $2 AND idempotency_key = $3
+                  AND status_code IS NULL AND claimed_at < now() - make_interval(secs => $4)
+                "#,
+            )
+            .bind(request_hash)
+            .bind(account_id)
+            .bind(key)
+            .bind(lease.as_secs() as f64)
+            .execute(&self.pool)
+            .await?;
+            if taken_over.rows_affected() > 0 {
+                return Ok(None);
+            }
+
+            let existing = sqlx::query_as::<_, IdempotencyRecord>(
+                r#"
+                SELECT request_hash, status_code, response_body FROM idempotency_keys
+                WHERE account_id = $1 AND idempotency_key = $2
+                "#,
+            )
+            .bind(account_id)
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+            if existing.is_some() {
+                return Ok(existing);
+            }
+        }
+    }
+
+    async fn complete_idempotency_key(
+        &self,
+        account_id: i64,
+        key: &str,
+        status_code: u16,
+        response_body: &str,
+    ) -> StoreResult<()> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys SET status_code = $1, response_body = $2
+            WHERE account_id = $3 AND idempotency_key = $4
+            "#,
+        )
+        .bind(status_code as i32)
+        .bind(response_body)
+        .bind(account_id)
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, account_id: i64, key: &str) -> StoreResult<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE account_id = $1 AND idempotency_key = $2 AND status_code IS NULL
+            "#,
+        )
+        .bind(account_id)
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn append_event(&self, event: &BrokerEvent, ctx: &EventContext) -> StoreResult<i64> {
+        let mut tx = self.pool.begin().await?;
+        let id = Self::log_event(&mut tx, event, ctx).await?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn undispatched_events(&self, limit: i64) -> StoreResult<Vec<EventEnvelope>> {
+        let rows = sqlx::query_as::<_, EventRow>(
+            "SELECT id, payload::text AS payload, created_at, correlation_id, actor::text AS actor FROM events WHERE dispatched_at IS NULL ORDER BY id LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        decode_events(rows)
+    }
+
+    async fn dispatch_event(&self, envelope: &EventEnvelope) -> StoreResult<Option<u64>> {
+        let mut tx = self.pool.begin().await?;
+
+        let claimed = sqlx::query(
+            "UPDATE events SET dispatched_at = now() WHERE id = $1 AND dispatched_at IS NULL",
+        )
+        .bind(envelope.event_id)
+        .execute(&mut *tx)
+        .await?;
+        if claimed.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let queued = Self::enqueue_webhook_deliveries(&mut tx, envelope).await?;
+        // Delivered to listeners on commit
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(DISPATCH_CHANNEL)
+            .bind(envelope.event_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(Some(queued))
+    }
+
+    async fn events_after(&self, after_id: i64, limit: i64) -> StoreResult<Vec<EventEnvelope>> {
+        let rows = sqlx::query_as::<_, EventRow>(
+            "SELECT id, payload::text AS payload, created_at, correlation_id, actor::text AS actor FROM events WHERE id > $1 ORDER BY id LIMIT $2",
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        decode_events(rows)
+    }
+
+    async fn dispatched_events_after(&self, after_id: i64, limit: i64) -> StoreResult<Vec<EventEnvelope>> {
+        let rows = sqlx::query_as::<_, EventRow>(
+            r#"
+            SELECT id, payload::text AS payload, created_at, correlation_id, actor::text AS actor
+            FROM events
+            WHERE id > $1
+              AND id < COALESCE(
+                (SELECT MIN(id) FROM events WHERE id > $1 AND dispatched_at IS NULL),
+                9223372036854775807)
+            ORDER BY id LIMIT $2
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        decode_events(rows)
+    }
+
+    async fn dispatched_through(&self) -> StoreResult<i64> {
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            r#"
+            SELECT COALESCE(
+                (SELECT MIN(id) FROM events WHERE dispatched_at IS NULL) - 1,
+                (SELECT MAX(id) FROM events),
+                0)
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn watch_dispatches(&self) -> StoreResult<Option<BoxStream<'static, ()>>> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(DISPATCH_CHANNEL).await?;
+
+        // The listener reconnects by itself; a notification lost meanwhile is
+        // caught by the relay's polling
+        let stream = futures::stream::unfold(listener, |mut listener| async move {
+            loop {
+                match listener.recv().await {
+                    Ok(_) => return Some(((), listener)),
+                    Err(e) => {
+                        eprintln!("Event notification listener failed: {:?}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        });
+        Ok(Some(Box::pin(stream)))
+    }
+
+    async fn event_id_range(&self) -> StoreResult<Option<(i64, i64)>> {
+        let (oldest, newest) =
+            sqlx::query_as::<_, (Option<i64>, Option<i64>)>("SELECT MIN(id), MAX(id) FROM events")
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(oldest.zip(newest))
+    }
+
+    async fn snapshot(&self, scope: Option<&[i64]>) -> StoreResult<StateSnapshot> {
+        // One repeatable-read snapshot for every query. Event writers commit
+        // in id order, so the newest visible event id covers exactly the
+        // changes visible here.
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+
+        let (event_id,) = sqlx::query_as::<_, (i64,)>("SELECT COALESCE(MAX(id), 0) FROM events")
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM accounts WHERE 1 = 1");
+        if let Some(ids) = scope {
+            qb.push(" AND id = ANY(").push_bind(ids.to_vec()).push(")");
+        }
+        qb.push(" ORDER BY id");
+        let accounts = qb.build_query_as::<Account>().fetch_all(&mut *tx).await?;
+
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM batches WHERE NOT completed");
+        if let Some(ids) = scope {
+            qb.push(" AND account_id = ANY(").push_bind(ids.to_vec()).push(")");
+        }
+        qb.push(" ORDER BY id");
+        let batches = qb.build_query_as::<Batch>().fetch_all(&mut *tx).await?;
+        let batches = Self::attach_bets(&mut tx, batches).await?;
+        tx.commit().await?;
+
+        Ok(StateSnapshot { event_id, accounts, batches })
+    }
+
+    async fn prune_events(&self, retention: Duration) -> StoreResult<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM events
+            WHERE created_at < now() - make_interval(secs => $1) AND dispatched_at IS NOT NULL
+              AND id < (SELECT MAX(id) FROM events)
+            "#,
+        )
+        .bind(retention.as_secs() as f64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn create_webhook(&self, webhook: &NewWebhook) -> StoreResult<Webhook> {
+        let row = sqlx::query_as::<_, WebhookRow>(
+            r#"
+            INSERT INTO webhooks (url, secret, event_types, account_id, created_at)
+            VALUES ($1, $2, $3, $4, now())
+            RETURNING *
+            "#,
+        )
+        .bind(&webhook.url)
+        .bind(&webhook.secret)
+        .bind(join_event_types(&webhook.event_types))
+        .bind(webhook.account_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.into())
+    }
+
+    async fn list_webhooks(&self) -> StoreResult<Vec<Webhook>> {
+        let rows = sqlx::query_as::<_, WebhookRow>("SELECT * FROM webhooks ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(Webhook::from).collect())
+    }
+
+    async fn get_webhook(&self, id: i64) -> StoreResult<Option<Webhook>> {
+        let row = sqlx::query_as::<_, WebhookRow>("SELECT * FROM webhooks WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(Webhook::from))
+    }
+
+    async fn delete_webhook(&self, id: i64) -> StoreResult<bool> {
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn claim_webhook_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        lease: Duration,
+        limit: i64,
+    ) -> StoreResult<Vec<DueDelivery>> {
+        // Rows another instance is claiming are skipped, not waited on
+        let lease_until = now.timestamp_millis().saturating_add(lease.as_millis() as i64);
+        let mut rows = sqlx::query_as::<_, (i64, String, String, String, String, i32)>(
+            r#"
+            UPDATE webhook_deliveries d SET next_attempt_at = $1
+            FROM webhooks w
+            WHERE w.id = d.webhook_id AND d.id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= $2
+                ORDER BY next_attempt_at, id
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING d.id, w.url, w.secret, d.event_type, d.payload, d.attempts
+            "#,
+        )
+        .bind(lease_until)
+        .bind(now.timestamp_millis())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.sort_by_key(|row| row.0);
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, url, secret, event_type, payload, attempts)| DueDelivery {
+                id,
+                url,
+                secret,
+                event_type,
+                payload,
+                attempts,
+            })
+            .collect())
+    }
+
+    async fn record_webhook_attempt(&self, delivery_id: i64, attempt: &DeliveryAttempt) -> StoreResult<()> {
+        let (status, next_attempt_at) = attempt_schedule(&attempt.outcome);
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $1, next_attempt_at = COALESCE($2, next_attempt_at), attempts = attempts + 1,
+                last_status_code = $3, last_error = $4, last_attempt_at = now()
+            WHERE id = $5
+            "#,
+        )
+        .bind(status.to_string())
+        .bind(next_attempt_at)
+        .bind(attempt.status_code.map(i32::from))
+        .bind(attempt.error.as_deref())
+        .bind(delivery_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        status: Option<DeliveryStatus>,
+        page: &PageRequest,
+    ) -> StoreResult<Page<WebhookDelivery>> {
+        let mut qb = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT id, webhook_id, event_id, event_type, status, attempts, next_attempt_at,
+                   last_status_code, last_error, last_attempt_at, created_at
+            FROM webhook_deliveries WHERE webhook_id = "#,
+        );
+        qb.push_bind(webhook_id);
+        if let Some(status) = status {
+            qb.push(" AND status = ").push_bind(status.to_string());
+        }
+        push_page(&mut qb, page);
+
+        let rows = qb.build_query_as::<DeliveryRow>().fetch_all(&self.pool).await?;
+        let deliveries = rows
+            .into_iter()
+            .map(DeliveryRow::into_delivery)
+            .collect::<StoreResult<Vec<_>>>()?;
+
+        Ok(Page::from_probe(deliveries, page.limit, |d| Cursor {
+            created_at: d.created_at,
+            id: d.id,
+        }))
+    }
+
+    async fn list_fx_rates(&self) -> StoreResult<Vec<FxRate>> {
+        let rates = sqlx::query_as::<_, FxRate>("SELECT * FROM fx_rates ORDER BY from_currency, to_currency")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rates)
+    }
+
+    async fn set_fx_rate(&self, from: Currency, to: Currency, rate: ExchangeRate) -> StoreResult<FxRate> {
+        let rate = sqlx::query_as::<_, FxRate>(
+            r#"
+            INSERT INTO fx_rates (from_currency, to_currency, rate, updated_at)
+            VALUES ($1, $2, $3, now())
+            ON CONFLICT (from_currency, to_currency)
+            DO UPDATE SET rate = excluded.rate, updated_at = excluded.updated_at
+            RETURNING *
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(rate)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(rate)
+    }
+
+    async fn delete_fx_rate(&self, from: Currency, to: Currency) -> StoreResult<bool> {
+        let result = sqlx::query("DELETE FROM fx_rates WHERE from_currency = $1 AND to_currency = $2")
+            .bind(from)
+            .bind(to)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn stake_totals(&self, filter: &TotalsQuery, scope: Option<&[i64]>) -> StoreResult<Vec<StakeTotals>> {
+        let mut qb = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT bets.currency, COUNT(*) AS bets,
+                   COALESCE(SUM(bets.stake), 0)::BIGINT AS stake, COALESCE(SUM(bets.cost), 0)::BIGINT AS cost
+            FROM bets JOIN batches ON batches.id = bets.batch_id
+            WHERE 1 = 1
+            "#,
+        );
+        if let Some(account_id) = filter.account_id {
+            qb.push(" AND batches.account_id = ").push_bind(account_id);
+        }
+        if let Some(status) = filter.status {
+            qb.push(" AND bets.status = ").push_bind(status.to_string());
+        }
+        if let Some(after) = filter.created_after {
+            qb.push(" AND batches.created_at >= ").push_bind(after);
+        }
+        if let Some(before) = filter.created_before {
+            qb.push(" AND batches.created_at < ").push_bind(before);
+        }
+        if let Some(ids) = scope {
+            qb.push(" AND batches.account_id = ANY(").push_bind(ids.to_vec()).push(")");
+        }
+        qb.push(" GROUP BY bets.currency ORDER BY bets.currency");
+
+        let totals = qb.build_query_as::<StakeTotals>().fetch_all(&self.pool).await?;
+        Ok(totals)
+    }
+
+    async fn get_race_result(&self, race_id: i64) -> StoreResult<Option<RaceResult>> {
+        let result = sqlx::query_as::<_, RaceResult>("SELECT * FROM race_results WHERE race_id = $1")
+            .bind(race_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(result)
+    }
+
+    async fn race_selections(&self, race_id: i64) -> StoreResult<Vec<(BetType, String)>> {
+        let selections = sqlx::query_as::<_, (BetType, String)>(
+            r#"
+            SELECT DISTINCT batches.bet_type, bets.selection FROM bets
+            JOIN batches ON batches.id = bets.batch_id
+            WHERE batches.race_id = $1 AND batches.bet_type IS NOT NULL AND bets.status = 'successful'
+            ORDER BY batches.bet_type, bets.selection
+            "#,
+        )
+        .bind(race_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(selections)
+    }
+
+    async fn set_race_result(
+        &self,
+        race_id: i64,
+        req: &SetRaceResultRequest,
+        ctx: &EventContext,
+    ) -> StoreResult<RaceSettlement> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query_as::<_, RaceResult>(
+            r#"
+            INSERT INTO race_results (race_id, placings, dividends, updated_at)
+            VALUES ($1, $2, $3, now())
+            ON CONFLICT (race_id)
+            DO UPDATE SET placings = excluded.placings, dividends = excluded.dividends, updated_at = excluded.updated_at
+            RETURNING *
+            "#,
+        )
+        .bind(race_id)
+        .bind(sqlx::types::Json(&req.placings))
+        .bind(sqlx::types::Json(&req.dividends))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // Lock the race's successful bets so a concurrent result for the
+        // same race settles them after this one
+        let rows = sqlx::query_as::<_, RaceBetRow>(
+            r#"
+            SELECT bets.*, batches.account_id, batches.bet_type FROM bets
+            JOIN batches ON batches.id = bets.batch_id
+            WHERE batches.race_id = $1 AND bets.status = 'successful'
+            ORDER BY bets.batch_id, bets.id
+            FOR UPDATE OF bets
+            "#,
+        )
+        .bind(race_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let (mut settled, mut won) = (0, 0);
+        let mut batches: Vec<(i64, i64, Currency)> = Vec::new();
+        let (changes, unsettled) = settlement_changes(&result, rows)?;
+        for (row, settlement) in changes {
+            let bet = sqlx::query_as::<_, Bet>(
+                "UPDATE bets SET result = $1, payout = $2, settled_at = now() WHERE pid = $3 RETURNING *",
+            )
+            .bind(settlement.result)
+            .bind(settlement.payout)
+            .bind(row.bet.pid)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            settled += 1;
+            if settlement.result == BetResult::Won {
+                won += 1;
+            }
+            if !batches.iter().any(|(id, ..)| *id == bet.batch_id) {
+                batches.push((bet.batch_id, row.account_id, bet.currency));
+            }
+            Self::log_event(&mut tx, &BrokerEvent::BetSettled { account_id: row.account_id, race_id, bet }, ctx).await?;
+        }
+
+        for (batch_id, account_id, currency) in batches {
+            let totals = sqlx::query_as::<_, BatchSettlementRow>(
+                r#"
+                SELECT
+                    COUNT(*) FILTER (WHERE status = 'pending' OR (status = 'successful' AND result IS NULL)) AS unsettled,
+                    COUNT(*) FILTER (WHERE result IS NOT NULL) AS settled,
+                    COUNT(*) FILTER (WHERE result = 'won') AS won,
+                    COALESCE(SUM(stake) FILTER (WHERE result IS NOT NULL), 0)::BIGINT AS stake,
+                    COALESCE(SUM(payout), 0)::BIGINT AS payout
+                FROM bets WHERE batch_id = $1
+                "#,
+            )
+            .bind(batch_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if totals.unsettled == 0 {
+                let event = BrokerEvent::BatchSettled {
+                    batch_id,
+                    account_id,
+                    race_id,
+                    settled: totals.settled,
+                    won: totals.won,
+                    stake: totals.stake,
+                    payout: totals.payout,
+                    currency,
+                };
+                Self::log_event(&mut tx, &event, ctx).await?;
+            }
+        }
+        tx.commit().await?;
+
+        Ok(RaceSettlement { result, settled, won, unsettled })
+    }
+}
+
+impl PostgresStore {
+    // Insert an undispatched event inside the caller's transaction. The
+    // transaction-scoped lock serialises event writers until they commit, so
+    // ids become visible in order and the dispatcher never skips a late one.
+    async fn log_event(conn: &mut PgConnection, event: &BrokerEvent, ctx: &EventContext) -> StoreResult<i64> {
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(EVENT_LOG_LOCK)
+            .execute(&mut *conn)
+            .await?;
+
+        let payload = encode_json(event)?;
+        let actor = ctx.actor.as_ref().map(encode_json).transpose()?;
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            r#"
+            INSERT INTO events (event_type, account_id, payload, correlation_id, actor)
+            VALUES ($1, $2, $3::jsonb, $4, $5::jsonb)
+            RETURNING id
+            "#,
+        )
+        .bind(event.event_name())
+        .bind(event.account_id())
+        .bind(payload)
+        .bind(ctx.correlation_id.as_deref())
+        .bind(actor)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(id)
+    }
+
+    // Queue a delivery of the envelope, due now, to every webhook that wants
+    // its event
+    async fn enqueue_webhook_deliveries(
+        conn: &mut PgConnection,
+        envelope: &EventEnvelope,
+    ) -> StoreResult<u64> {
+        let event = &envelope.event;
+        let webhooks = sqlx::query_as::<_, WebhookRow>("SELECT * FROM webhooks ORDER BY id")
+            .fetch_all(&mut *conn)
+            .await?;
+        let targets: Vec<Webhook> = webhooks
+            .into_iter()
+            .map(Webhook::from)
+            .filter(|w| w.wants(event))
+            .collect();
+        if targets.is_empty() {
+            return Ok(0);
+        }
+
+        let payload = encode_json(envelope)?;
+        let now = Utc::now().timestamp_millis();
+        let mut qb = QueryBuilder::<Postgres>::new(
+            "INSERT INTO webhook_deliveries (webhook_id, event_id, event_type, payload, next_attempt_at) ",
+        );
+        qb.push_values(&targets, |mut row, webhook| {
+            row.push_bind(webhook.id)
+                .push_bind(envelope.event_id)
+                .push_bind(event.event_name())
+                .push_bind(&payload)
+                .push_bind(now);
+        });
+        let result = qb.build().execute(&mut *conn).await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Guarded status update; when nothing matches, look the bet up again to
+    // tell a missing bet (`None`) from a refused change (`Conflict`)
+    async fn set_bet_status(
+        conn: &mut PgConnection,
+        account_id: i64,
+        batch_id: i64,
+        change: &BetStatusChange,
+    ) -> StoreResult<Option<Bet>> {
+        let BetStatusChange { pid, status, .. } = *change;
+
+        let from: Vec<String> = change.from.iter().map(ToString::to_string).collect();
+        let bet = sqlx::query_as::<_, Bet>(
+            r#"
+            UPDATE bets
+            SET status = $1, reference = COALESCE($2, reference), message = COALESCE($3, message),
+                -- Only successful bets are settled
+                result = CASE WHEN $1 = 'successful' THEN result END,
+                payout = CASE WHEN $1 = 'successful' THEN payout END,
+                settled_at = CASE WHEN $1 = 'successful' THEN settled_at END
+            WHERE pid = $4 AND batch_id = $5
+              AND batch_id IN (SELECT id FROM batches WHERE account_id = $6 AND NOT completed)
+              AND status = ANY($7)
+            RETURNING *
+            "#,
+        )
+        .bind(status.to_string())
+        .bind(change.reference.as_deref())
+        .bind(change.message.as_deref())
+        .bind(pid)
+        .bind(batch_id)
+        .bind(account_id)
+        .bind(&from)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if let Some(bet) = bet {
+            return Ok(Some(bet));
+        }
+
+        let current = sqlx::query_as::<_, (String, bool)>(
+            r#"
+            SELECT bets.status, batches.completed FROM bets
+            JOIN batches ON batches.id = bets.batch_id
+            WHERE bets.pid = $1 AND bets.batch_id = $2 AND batches.account_id = $3
+            "#,
+        )
+        .bind(pid)
+        .bind(batch_id)
+        .bind(account_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        match current {
+            Some((current, completed)) => {
+                Err(status_conflict(pid, batch_id, &current, completed, status))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Load the bets for all given batches in one query and group them per batch
+    async fn attach_bets(conn: &mut PgConnection, batches: Vec<Batch>) -> StoreResult<Vec<BatchResponse>> {
+        if batches.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<i64> = batches.iter().map(|b| b.id).collect();
+        let bets = sqlx::query_as::<_, Bet>(
+            "SELECT * FROM bets WHERE batch_id = ANY($1) ORDER BY batch_id, id",
+        )
+        .bind(&ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut bets_by_batch: HashMap<i64, Vec<Bet>> = HashMap::new();
+        for bet in bets {
+            bets_by_batch.entry(bet.batch_id).or_default().push(bet);
+        }
+
+        Ok(batches
+            .into_iter()
+            .map(|batch| {
+                let bets = bets_by_batch.remove(&batch.id).unwrap_or_default();
+                BatchResponse::new(batch, bets)
+            })
+            .collect())
+    }
+}
+
+// Append keyset pagination over (created_at, id), fetching one probe row
+fn push_page(qb: &mut QueryBuilder<'_, Postgres>, page: &PageRequest) {
+    if let Some(cursor) = &page.cursor {
+        qb.push(" AND (created_at, id) < (")
+            .push_bind(cursor.created_at)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+    qb.push(" ORDER BY created_at DESC, id DESC LIMIT ")
+        .push_bind(page.probe_limit());
+}
@@ -0,0 +1,1119 @@
+//! Conformance suite run against every store backend.
+//!
+//! SQLite always runs in memory. PostgreSQL runs when `TEST_POSTGRES_URL`
+//! points at a server where the user may create databases, e.g.
+//! `make test-postgres`; each test gets a fresh database.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{ConnectOptions, Executor};
+
+use super::*;
+use crate::models::api_key::KeyAccess;
+use crate::models::money::{Currency, ExchangeRate, Money};
+use crate::models::pagination::Cursor;
+use crate::models::race::{BetResult, Dividend};
+use crate::models::report::{StakeTotals, TotalsQuery};
+use crate::models::selection::BetType;
+use crate::models::webhook::{AttemptOutcome, DeliveryAttempt, DeliveryStatus, NewWebhook};
+
+async fn sqlite_store() -> SqliteStore {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("open in-memory sqlite");
+    SqliteStore::from_pool(pool).await.expect("migrate sqlite")
+}
+
+/// A throwaway PostgreSQL database, dropped by `cleanup`
+struct PgTestDb {
+    admin: PgConnectOptions,
+    name: String,
+}
+
+impl PgTestDb {
+    async fn create() -> Option<(Self, PostgresStore)> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let url = std::env::var("TEST_POSTGRES_URL").ok()?;
+        let admin = PgConnectOptions::from_str(&url).expect("valid TEST_POSTGRES_URL");
+        let name = format!(
+            "betstream_test_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        );
+
+        let mut conn = admin.connect().await.expect("connect to TEST_POSTGRES_URL");
+        conn.execute(format!(r#"DROP DATABASE IF EXISTS "{}""#, name).as_str())
+            .await
+            .expect("drop stale test database");
+        conn.execute(format!(r#"CREATE DATABASE "{}""#, name).as_str())
+            .await
+            .expect("create test database");
+
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_with(admin.clone().database(&name))
+            .await
+            .expect("connect to test database");
+        let store = PostgresStore::from_pool(pool).await.expect("migrate postgres");
+
+        Some((PgTestDb { admin, name }, store))
+    }
+
+    async fn cleanup(self) {
+        if let Ok(mut conn) = self.admin.connect().await {
+            let _ = conn
+                .execute(format!(r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE)"#, self.name).as_str())
+                .await;
+        }
+    }
+}
+
+macro_rules! store_suite {
+    ($($name:ident),* $(,)?) => {
+        mod sqlite_backend {
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    let store = super::sqlite_store().await;
+                    super::$name(&store).await;
+                }
+            )*
+        }
+
+        mod postgres_backend {
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    let Some((db, store)) = super::PgTestDb::create().await else {
+                        eprintln!("skipping: TEST_POSTGRES_URL is not set");
+                        return;
+                    };
+                    super::$name(&store).await;
+                    drop(store);
+                    db.cleanup().await;
+                }
+            )*
+        }
+    };
+}
+
+store_suite!(
+    account_round_trip,
+    duplicate_account_name_is_unique_violation,
+    list_accounts_pages_and_filters,
+    delete_account_cascades,
+    create_batch_with_bets,
+    create_batch_for_missing_account_is_fk_violation,
+    list_batches_filters_on_meta_and_completion,
+    batch_meta_round_trips,
+    list_batches_pages_through_every_batch,
+    complete_batch_only_once,
+    update_single_bet_status,
+    bulk_update_is_all_or_nothing,
+    status_updates_are_guarded,
+    api_key_lifecycle,
+    list_accounts_respects_scope,
+    idempotency_key_lifecycle,
+    event_log_appends_replays_and_prunes,
+    changes_log_events_in_the_same_transaction,
+    snapshot_matches_the_event_log,
+    dispatched_events_stop_at_the_first_undispatched,
+    fx_rates_and_stake_totals,
+    race_results_settle_successful_bets,
+    webhook_deliveries_lifecycle,
+);
+
+const PENDING: &[BetStatus] = &[BetStatus::Pending];
+const ANY: &[BetStatus] = &BetStatus::ALL;
+
+fn change(pid: i64, from: &[BetStatus], status: BetStatus) -> BetStatusChange {
+    BetStatusChange {
+        pid,
+        from: from.to_vec(),
+        status,
+        reference: None,
+        message: None,
+    }
+}
+
+fn account(name: &str, hostname: &str) -> CreateAccountRequest {
+    CreateAccountRequest {
+        name: name.to_string(),
+        hostname: hostname.to_string(),
+        currency: Currency::Aud,
+    }
+}
+
+fn batch(race_id: i64, bet_type: &str, selections: &[&str]) -> CreateBatchRequest {
+    CreateBatchRequest {
+        meta: BatchMeta {
+            race_id: Some(race_id),
+            bet_type: Some(bet_type.parse().unwrap()),
+            ..Default::default()
+        },
+        bets: selections
+            .iter()
+            .enumerate()
+            .map(|(i, selection)| CreateBetRequest {
+                id: i as i64 + 1,
+                selection: selection.to_string(),
+                stake: Money::from_minor(150),
+                cost: Money::from_minor(100),
+            })
+            .collect(),
+    }
+}
+
+fn ctx() -> EventContext {
+    EventContext::default()
+}
+
+fn first_page(limit: u32) -> PageRequest {
+    PageRequest::new(Some(limit), None).unwrap()
+}
+
+fn next_page(limit: u32, cursor: &str) -> PageRequest {
+    PageRequest::new(Some(limit), Some(cursor)).unwrap()
+}
+
+async fn account_round_trip(store: &dyn Store) {
+    let created = store.create_account(&account("alpha", "host-a"), &ctx()).await.unwrap();
+    assert_eq!(created.name, "alpha");
+
+    let fetched = store.get_account(created.id).await.unwrap().unwrap();
+    assert_eq!(fetched.hostname, "host-a");
+
+    let updated = store
+        .update_account(created.id, &account("alpha", "host-b"), &ctx())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(updated.hostname, "host-b");
+
+    assert!(store.get_account(created.id + 100).await.unwrap().is_none());
+    assert!(store
+        .update_account(created.id + 100, &account("ghost", "h"), &ctx())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn duplicate_account_name_is_unique_violation(store: &dyn Store) {
+    store.create_account(&account("dup", "h1"), &ctx()).await.unwrap();
+    let err = store.create_account(&account("dup", "h2"), &ctx()).await.unwrap_err();
+
+    assert!(matches!(ApiError::from(err), ApiError::UniqueViolation(_)));
+}
+
+async fn list_accounts_pages_and_filters(store: &dyn Store) {
+    for i in 0..5 {
+        let host = if i % 2 == 0 { "even" } else { "odd" };
+        store.create_account(&account(&format!("acc{}", i), host), &ctx()).await.unwrap();
+    }
+
+    let no_filter = AccountQuery::default();
+    let first = store.list_accounts(&no_filter, None, &first_page(2)).await.unwrap();
+    assert_eq!(first.items.len(), 2);
+    assert!(first.has_more);
+
+    let second = store
+        .list_accounts(&no_filter, None, &next_page(2, first.next_cursor.as_ref().unwrap()))
+        .await
+        .unwrap();
+    let third = store
+        .list_accounts(&no_filter, None, &next_page(2, second.next_cursor.as_ref().unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(third.items.len(), 1);
+    assert!(!third.has_more);
+    assert!(third.next_cursor.is_none());
+
+    let mut seen: Vec<i64> = first
+        .items
+        .iter()
+        .chain(&second.items)
+        .chain(&third.items)
+        .map(|a| a.id)
+        .collect();
+    let in_order = seen.clone();
+    seen.sort_unstable_by(|a, b| b.cmp(a));
+    seen.dedup();
+    assert_eq!(seen.len(), 5, "every account exactly once");
+    assert_eq!(seen, in_order, "newest first");
+
+    let by_host = AccountQuery {
+        hostname: Some("even".to_string()),
+        ..Default::default()
+    };
+    let even = store.list_accounts(&by_host, None, &first_page(50)).await.unwrap();
+    assert_eq!(even.items.len(), 3);
+
+    let by_name = AccountQuery {
+        name: Some("acc3".to_string()),
+        ..Default::default()
+    };
+    let named = store.list_accounts(&by_name, None, &first_page(50)).await.unwrap();
+    assert_eq!(named.items.len(), 1);
+    assert_eq!(named.items[0].name, "acc3");
+}
+
+async fn delete_account_cascades(store: &dyn Store) {
+    let acc = store.create_account(&account("gone", "h"), &ctx()).await.unwrap();
+    let created = store.create_batch(acc.id, &batch(1, "WIN", &["1"]), &ctx()).await.unwrap();
+
+    assert!(store.delete_account(acc.id, &ctx()).await.unwrap());
+    assert!(!store.delete_account(acc.id, &ctx()).await.unwrap());
+    assert!(store.get_batch(acc.id, created.id).await.unwrap().is_none());
+    assert!(store
+        .get_bet(acc.id, created.id, created.bets[0].pid)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn create_batch_with_bets(store: &dyn Store) {
+    let acc = store.create_account(&account("batcher", "h"), &ctx()).await.unwrap();
+    let created = store
+        .create_batch(acc.id, &batch(7, "QUINELLA", &["1/2", "3/4"]), &ctx())
+        .await
+        .unwrap();
+
+    assert!(!created.completed);
+    assert_eq!(created.meta.race_id, Some(7));
+    assert_eq!(created.meta.bet_type, Some(BetType::Quinella));
+    assert_eq!(created.bets.len(), 2);
+    assert!(created.bets.iter().all(|b| b.status == "pending"));
+
+    let fetched = store.get_batch(acc.id, created.id).await.unwrap().unwrap();
+    assert_eq!(fetched.bets.len(), 2);
+    assert_eq!(fetched.bets[1].selection, "3/4");
+    assert_eq!(fetched.bets[1].stake, Money::from_minor(150));
+
+    let bet = store
+        .get_bet(acc.id, created.id, created.bets[0].pid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bet.selection, "1/2");
+
+    // A batch is only visible through its own account
+    let other = store.create_account(&account("other", "h"), &ctx()).await.unwrap();
+    assert!(store.get_batch(other.id, created.id).await.unwrap().is_none());
+    assert!(store
+        .get_bet(other.id, created.id, bet.pid)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn create_batch_for_missing_account_is_fk_violation(store: &dyn Store) {
+    let err = store
+        .create_batch(999, &batch(1, "WIN", &["1"]), &ctx())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(ApiError::from(err), ApiError::ForeignKeyViolation(_)));
+}
+
+async fn list_batches_filters_on_meta_and_completion(store: &dyn Store) {
+    let acc = store.create_account(&account("filters", "h"), &ctx()).await.unwrap();
+    let win = store.create_batch(acc.id, &batch(1, "WIN", &["1"]), &ctx()).await.unwrap();
+    store.create_batch(acc.id, &batch(2, "PLACE", &["2"]), &ctx()).await.unwrap();
+    store.create_batch(acc.id, &batch(2, "WIN", &["3"]), &ctx()).await.unwrap();
+    assert!(store.complete_batch(acc.id, win.id, &ctx()).await.unwrap());
+
+    let list = |filter: BatchQuery| async move {
+        store
+            .list_batches(acc.id, &filter, &first_page(50))
+            .await
+            .unwrap()
+            .items
+    };
+
+    assert_eq!(list(BatchQuery::default()).await.len(), 3);
+    assert_eq!(
+        list(BatchQuery { completed: Some(true), ..Default::default() }).await.len(),
+        1
+    );
+    assert_eq!(
+        list(BatchQuery { completed: Some(false), ..Default::default() }).await.len(),
+        2
+    );
+    assert_eq!(
+        list(BatchQuery { race_id: Some(2), ..Default::default() }).await.len(),
+        2
+    );
+    assert_eq!(
+        list(BatchQuery { bet_type: Some(BetType::Win), ..Default::default() }).await.len(),
+        2
+    );
+
+    let past = chrono::Utc::now() - chrono::Duration::days(1);
+    let future = chrono::Utc::now() + chrono::Duration::days(1);
+    assert_eq!(
+        list(BatchQuery { created_after: Some(past), ..Default::default() }).await.len(),
+        3
+    );
+    assert_eq!(
+        list(BatchQuery { created_after: Some(future), ..Default::default() }).await.len(),
+        0
+    );
+    assert_eq!(
+        list(BatchQuery { created_before: Some(past), ..Default::default() }).await.len(),
+        0
+    );
+
+    let bets_attached = list(BatchQuery::default()).await;
+    assert!(bets_attached.iter().all(|b| b.bets.len() == 1));
+}
+
+async fn batch_meta_round_trips(store: &dyn Store) {
+    let acc = store.create_account(&account("meta", "h"), &ctx()).await.unwrap();
+    let mut request = batch(4, "EXACTA", &["2/3"]);
+    request.meta.meeting = Some("Flemington R4".to_string());
+    request.meta.venue = Some("Flemington".to_string());
+    request.meta.strategy = Some("value".to_string());
+    request.meta.extra.insert("name".to_string(), serde_json::json!("Morning run"));
+    let created = store.create_batch(acc.id, &request, &ctx()).await.unwrap();
+    store.create_batch(acc.id, &batch(4, "WIN", &["1"]), &ctx()).await.unwrap();
+
+    assert_eq!(created.meta, request.meta);
+    let fetched = store.get_batch(acc.id, created.id).await.unwrap().unwrap();
+    assert_eq!(fetched.meta, request.meta);
+
+    let list = |filter: BatchQuery| async move {
+        store
+            .list_batches(acc.id, &filter, &first_page(50))
+            .await
+            .unwrap()
+            .items
+    };
+    let by_venue = list(BatchQuery { venue: Some("Flemington".to_string()), ..Default::default() }).await;
+    assert_eq!(by_venue.len(), 1);
+    assert_eq!(by_venue[0].id, created.id);
+    let by_strategy = list(BatchQuery { strategy: Some("value".to_string()), ..Default::default() }).await;
+    assert_eq!(by_strategy.len(), 1);
+    assert_eq!(
+        list(BatchQuery { meeting: Some("Flemington R5".to_string()), ..Default::default() }).await.len(),
+        0
+    );
+}
+
+async fn list_batches_pages_through_every_batch(store: &dyn Store) {
+    let acc = store.create_account(&account("pager", "h"), &ctx()).await.unwrap();
+    for race in 0..7 {
+        store.create_batch(acc.id, &batch(race, "WIN", &["1", "2"]), &ctx()).await.unwrap();
+    }
+
+    let mut ids = Vec::new();
+    let mut page = first_page(3);
+    loop {
+        let result = store
+            .list_batches(acc.id, &BatchQuery::default(), &page)
+            .await
+            .unwrap();
+        assert!(result.items.len() <= 3);
+        assert!(result.items.iter().all(|b| b.bets.len() == 2));
+        ids.extend(result.items.iter().map(|b| b.id));
+        match result.next_cursor {
+            Some(cursor) => {
+                assert!(Cursor::decode(&cursor).is_ok());
+                page = next_page(3, &cursor);
+            }
+            None => break,
+        }
+    }
+
+    let mut expected = ids.clone();
+    expected.sort_unstable_by(|a, b| b.cmp(a));
+    expected.dedup();
+    assert_eq!(ids.len(), 7);
+    assert_eq!(ids, expected);
+}
+
+async fn complete_batch_only_once(store: &dyn Store) {
+    let acc = store.create_account(&account("completer", "h"), &ctx()).await.unwrap();
+    let created = store.create_batch(acc.id, &batch(1, "WIN", &["1"]), &ctx()).await.unwrap();
+
+    assert!(!store.complete_batch(acc.id + 1, created.id, &ctx()).await.unwrap());
+    assert!(store.complete_batch(acc.id, created.id, &ctx()).await.unwrap());
+    assert!(!store.complete_batch(acc.id, created.id, &ctx()).await.unwrap());
+
+    let fetched = store.get_batch(acc.id, created.id).await.unwrap().unwrap();
+    assert!(fetched.completed);
+}
+
+async fn update_single_bet_status(store: &dyn Store) {
+    let acc = store.create_account(&account("single", "h"), &ctx()).await.unwrap();
+    let created = store.create_batch(acc.id, &batch(1, "WIN", &["1"]), &ctx()).await.unwrap();
+    let pid = created.bets[0].pid;
+
+    let bet = store
+        .update_bet_status(acc.id, created.id, &change(pid, PENDING, BetStatus::Failed), &ctx())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bet.status, "failed");
+
+    assert!(store
+        .update_bet_status(acc.id + 1, created.id, &change(pid, ANY, BetStatus::Successful), &ctx())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(store
+        .update_bet_status(acc.id, created.id, &change(pid + 100, ANY, BetStatus::Successful), &ctx())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn bulk_update_is_all_or_nothing(store: &dyn Store) {
+    let acc = store.create_account(&account("bulk", "h"), &ctx()).await.unwrap();
+    let created = store
+        .create_batch(acc.id, &batch(1, "WIN", &["1", "2", "3"]), &ctx())
+        .await
+        .unwrap();
+    let pids: Vec<i64> = created.bets.iter().map(|b| b.pid).collect();
+
+    let other = store.create_account(&account("other", "h"), &ctx()).await.unwrap();
+    let err = store
+        .update_bets_status(other.id, created.id, &[change(pids[0], PENDING, BetStatus::Successful)], &ctx())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, StoreError::NotFound(_)), "batch belongs to another account");
+
+    // Mixed statuses and result fields; unknown bets come back as `None`
+    let mut failed = change(pids[1], PENDING, BetStatus::Failed);
+    failed.message = Some("price moved".to_string());
+    let mut placed = change(pids[0], PENDING, BetStatus::Successful);
+    placed.reference = Some("TKT-1".to_string());
+    let updated = store
+        .update_bets_status(
+            acc.id,
+            created.id,
+            &[placed, change(9999, PENDING, BetStatus::Successful), failed],
+            &ctx(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.len(), 3);
+    let placed = updated[0].as_ref().unwrap();
+    assert_eq!(placed.status, "successful");
+    assert_eq!(placed.reference.as_deref(), Some("TKT-1"));
+    assert!(updated[1].is_none());
+    let failed = updated[2].as_ref().unwrap();
+    assert_eq!(failed.status, "failed");
+    assert_eq!(failed.message.as_deref(), Some("price moved"));
+
+    let after = store.get_batch(acc.id, created.id).await.unwrap().unwrap();
+    assert_eq!(after.bets[2].status, "pending");
+    assert_eq!(after.bets[0].reference.as_deref(), Some("TKT-1"));
+}
+
+async fn status_updates_are_guarded(store: &dyn Store) {
+    let acc = store.create_account(&account("guarded", "h"), &ctx()).await.unwrap();
+    let created = store
+        .create_batch(acc.id, &batch(1, "WIN", &["1", "2"]), &ctx())
+        .await
+        .unwrap();
+    let (a, b) = (created.bets[0].pid, created.bets[1].pid);
+
+    store
+        .update_bet_status(acc.id, created.id, &change(a, PENDING, BetStatus::Successful), &ctx())
+        .await
+        .unwrap()
+        .unwrap();
+    let err = store
+        .update_bet_status(acc.id, created.id, &change(a, PENDING, BetStatus::Failed), &ctx())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, StoreError::Conflict(_)));
+
+    // A refused bet rolls back the whole bulk update
+    let err = store
+        .update_bets_status(
+            acc.id,
+            created.id,
+            &[change(b, PENDING, BetStatus::Successful), change(a, PENDING, BetStatus::Successful)],
+            &ctx(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, StoreError::Conflict(_)));
+    let after = store.get_bet(acc.id, created.id, b).await.unwrap().unwrap();
+    assert_eq!(after.status, "pending");
+
+    // Any current status is accepted when the caller allows it
+    let bet = store
+        .update_bet_status(acc.id, created.id, &change(a, ANY, BetStatus::Pending), &ctx())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bet.status, "pending");
+
+    assert!(store.complete_batch(acc.id, created.id, &ctx()).await.unwrap());
+    let err = store
+        .update_bet_status(acc.id, created.id, &change(b, ANY, BetStatus::Failed), &ctx())
+        .await
+        .unwrap_err();
+    match err {
+        StoreError::Conflict(detail) => assert!(detail.contains("completed"), "{}", detail),
+        other => panic!("expected conflict, got {:?}", other),
+    }
+}
+
+async fn api_key_lifecycle(store: &dyn Store) {
+    let acc = store.create_account(&account("scoped", "h"), &ctx()).await.unwrap();
+
+    let scoped = store
+        .create_api_key(&NewApiKey {
+            name: "ops".to_string(),
+            key_hash: "hash-scoped".to_string(),
+            key_prefix: "bsk_scoped".to_string(),
+            access: KeyAccess::Read,
+            account_ids: Some(vec![acc.id]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(scoped.account_ids, Some(vec![acc.id]));
+    assert!(scoped.revoked_at.is_none());
+
+    let global = store
+        .create_api_key(&NewApiKey {
+            name: "trader".to_string(),
+            key_hash: "hash-global".to_string(),
+            key_prefix: "bsk_global".to_string(),
+            access: KeyAccess::Write,
+            account_ids: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(global.account_ids, None);
+
+    let found = store.find_api_key("hash-scoped").await.unwrap().unwrap();
+    assert_eq!(found.id, scoped.id);
+    assert_eq!(found.access, KeyAccess::Read);
+    assert_eq!(found.account_ids, Some(vec![acc.id]));
+    assert!(store.find_api_key("unknown").await.unwrap().is_none());
+
+    let listed = store.list_api_keys().await.unwrap();
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0].account_ids, Some(vec![acc.id]));
+    assert_eq!(listed[1].account_ids, None);
+
+    assert!(store.revoke_api_key(scoped.id).await.unwrap());
+    assert!(!store.revoke_api_key(scoped.id).await.unwrap());
+    assert!(store.find_api_key("hash-scoped").await.unwrap().is_none());
+    let listed = store.list_api_keys().await.unwrap();
+    assert!(listed[0].revoked_at.is_some());
+
+    let err = store
+        .create_api_key(&NewApiKey {
+            name: "dangling".to_string(),
+            key_hash: "hash-dangling".to_string(),
+            key_prefix: "bsk_dangling".to_string(),
+            access: KeyAccess::Read,
+            account_ids: Some(vec![acc.id + 100]),
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(ApiError::from(err), ApiError::ForeignKeyViolation(_)));
+}
+
+async fn list_accounts_respects_scope(store: &dyn Store) {
+    let a = store.create_account(&account("a", "h"), &ctx()).await.unwrap();
+    store.create_account(&account("b", "h"), &ctx()).await.unwrap();
+    let c = store.create_account(&account("c", "h"), &ctx()).await.unwrap();
+
+    let no_filter = AccountQuery::default();
+    let scoped = store
+        .list_accounts(&no_filter, Some(&[a.id, c.id]), &first_page(50))
+        .await
+        .unwrap();
+    let ids: Vec<i64> = scoped.items.iter().map(|acc| acc.id).collect();
+    assert_eq!(ids, vec![c.id, a.id]);
+
+    let none = store
+        .list_accounts(&no_filter, Some(&[]), &first_page(50))
+        .await
+        .unwrap();
+    assert!(none.items.is_empty());
+}
+
+async fn idempotency_key_lifecycle(store: &dyn Store) {
+    let ttl = Duration::from_secs(3600);
+    let lease = Duration::from_secs(60);
+
+    assert!(store.reserve_idempotency_key(1, "k", "hash", ttl, lease).await.unwrap().is_none());
+    let pending = store.reserve_idempotency_key(1, "k", "hash", ttl, lease).await.unwrap().unwrap();
+    assert_eq!(pending.request_hash, "hash");
+    assert_eq!(pending.status_code, None);
+
+    // Keys are per account
+    assert!(store.reserve_idempotency_key(2, "k", "other", ttl, lease).await.unwrap().is_none());
+
+    store.complete_idempotency_key(1, "k", 200, "{\"id\":7}").await.unwrap();
+    let done = store.reserve_idempotency_key(1, "k", "hash", ttl, lease).await.unwrap().unwrap();
+    assert_eq!(done.status_code, Some(200));
+    assert_eq!(done.response_body.as_deref(), Some("{\"id\":7}"));
+
+    // Completed keys survive a release; unfinished ones are dropped
+    store.release_idempotency_key(1, "k").await.unwrap();
+    assert!(store.reserve_idempotency_key(1, "k", "hash", ttl, lease).await.unwrap().is_some());
+    store.release_idempotency_key(2, "k").await.unwrap();
+    assert!(store.reserve_idempotency_key(2, "k", "other", ttl, lease).await.unwrap().is_none());
+
+    // A claim left without a response is taken over once its lease runs
+    // out; a completed one never is
+    assert!(store.reserve_idempotency_key(3, "k", "lost", ttl, lease).await.unwrap().is_none());
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(store.reserve_idempotency_key(3, "k", "retry", ttl, Duration::ZERO).await.unwrap().is_none());
+    let retried = store.reserve_idempotency_key(3, "k", "retry", ttl, lease).await.unwrap().unwrap();
+    assert_eq!((retried.request_hash.as_str(), retried.status_code), ("retry", None));
+    let done = store.reserve_idempotency_key(1, "k", "hash", ttl, Duration::ZERO).await.unwrap().unwrap();
+    assert_eq!(done.status_code, Some(200));
+
+    // Expired records are purged and the key can be claimed again
+    assert!(store
+        .reserve_idempotency_key(1, "k", "new", Duration::ZERO, lease)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn event_log_appends_replays_and_prunes(store: &dyn Store) {
+    assert_eq!(store.event_id_range().await.unwrap(), None);
+
+    let mut ids = Vec::new();
+    for id in 1..=3 {
+        ids.push(store.append_event(&BrokerEvent::AccountDeleted { id }, &ctx()).await.unwrap());
+    }
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(store.event_id_range().await.unwrap(), Some((ids[0], ids[2])));
+
+    let replay = store.events_after(ids[0], 10).await.unwrap();
+    assert_eq!(replay.iter().map(|e| e.event_id).collect::<Vec<_>>(), ids[1..]);
+    assert!(matches!(replay[0].event, BrokerEvent::AccountDeleted { id: 2 }));
+    assert_eq!(store.events_after(ids[0], 1).await.unwrap().len(), 1);
+
+    // Nothing is old enough yet
+    assert_eq!(store.prune_events(Duration::from_secs(3600)).await.unwrap(), 0);
+
+    // Pruning keeps undispatched events, and the newest event so the latest
+    // id is still known
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let logged = store.events_after(0, 10).await.unwrap();
+    store.dispatch_event(&logged[0]).await.unwrap();
+    assert_eq!(store.prune_events(Duration::ZERO).await.unwrap(), 1);
+    assert_eq!(store.event_id_range().await.unwrap(), Some((ids[1], ids[2])));
+    for envelope in &logged[1..] {
+        store.dispatch_event(envelope).await.unwrap();
+    }
+    assert_eq!(store.prune_events(Duration::ZERO).await.unwrap(), 1);
+    assert_eq!(store.event_id_range().await.unwrap(), Some((ids[2], ids[2])));
+
+    // Ids keep increasing after a prune
+    let next = store.append_event(&BrokerEvent::AccountDeleted { id: 4 }, &ctx()).await.unwrap();
+    assert!(next > ids[2]);
+}
+
+async fn changes_log_events_in_the_same_transaction(store: &dyn Store) {
+    let admin = EventContext {
+        correlation_id: Some("req-1".to_string()),
+        actor: Some(Actor::Admin),
+    };
+    let acc = store.create_account(&account("outbox", "h"), &admin).await.unwrap();
+    let key = EventContext {
+        correlation_id: None,
+        actor: Some(Actor::ApiKey {
+            id: 7,
+            name: "trader".to_string(),
+            key_prefix: "bsk_abcd".to_string(),
+        }),
+    };
+    let created = store.create_batch(acc.id, &batch(1, "WIN", &["1", "2"]), &key).await.unwrap();
+    let pids: Vec<i64> = created.bets.iter().map(|b| b.pid).collect();
+
+    // Rolled back or refused changes log nothing
+    store.create_batch(999, &batch(1, "WIN", &["1"]), &ctx()).await.unwrap_err();
+    store
+        .update_bets_status(
+            acc.id,
+            created.id,
+            &[change(pids[0], PENDING, BetStatus::Successful), change(pids[1], &[BetStatus::Successful], BetStatus::Failed)],
+            &ctx(),
+        )
+        .await
+        .unwrap_err();
+    assert!(store.update_account(999, &account("ghost", "h"), &ctx()).await.unwrap().is_none());
+    assert!(!store.delete_account(999, &ctx()).await.unwrap());
+
+    store
+        .update_bet_status(acc.id, created.id, &change(pids[0], PENDING, BetStatus::Successful), &ctx())
+        .await
+        .unwrap();
+    store
+        .update_bets_status(acc.id, created.id, &[change(9999, PENDING, BetStatus::Failed)], &ctx())
+        .await
+        .unwrap();
+    assert!(store.complete_batch(acc.id, created.id, &ctx()).await.unwrap());
+    assert!(!store.complete_batch(acc.id, created.id, &ctx()).await.unwrap());
+
+    let pending = store.undispatched_events(100).await.unwrap();
+    let names: Vec<&str> = pending.iter().map(|e| e.event.event_name()).collect();
+    assert_eq!(names, ["account_created", "batch_created", "bet_status_updated", "batch_completed"]);
+    assert!(matches!(&pending[1].event, BrokerEvent::BatchCreated { batch } if batch.id == created.id));
+
+    // The envelope carries the request context the change was made under
+    assert_eq!(pending[0].schema_version, EVENT_SCHEMA_VERSION);
+    assert_eq!(pending[0].correlation_id.as_deref(), Some("req-1"));
+    assert!(matches!(pending[0].actor, Some(Actor::Admin)));
+    assert!(pending[1].correlation_id.is_none());
+    assert!(matches!(&pending[1].actor, Some(Actor::ApiKey { id: 7, name, .. }) if name == "trader"));
+    assert!(pending[2].actor.is_none());
+
+    // Each event is dispatched exactly once, oldest first
+    assert_eq!(store.dispatch_event(&pending[0]).await.unwrap(), Some(0));
+    assert_eq!(store.dispatch_event(&pending[0]).await.unwrap(), None);
+    let rest = store.undispatched_events(1).await.unwrap();
+    assert_eq!(rest[0].event_id, pending[1].event_id);
+
+    // Dispatched events are still replayable
+    let replay = store.events_after(0, 100).await.unwrap();
+    assert_eq!(replay.len(), 4);
+}
+
+async fn dispatched_events_stop_at_the_first_undispatched(store: &dyn Store) {
+    assert_eq!(store.dispatched_through().await.unwrap(), 0);
+    let mut ids = Vec::new();
+    for id in 1..=4 {
+        ids.push(store.append_event(&BrokerEvent::AccountDeleted { id }, &ctx()).await.unwrap());
+    }
+    let logged = store.events_after(0, 10).await.unwrap();
+    assert!(store.dispatched_events_after(0, 10).await.unwrap().is_empty());
+
+    // The third event is still undispatched, so the fourth is held back
+    for i in [0, 1, 3] {
+        store.dispatch_event(&logged[i]).await.unwrap();
+    }
+    let relayed = store.dispatched_events_after(0, 10).await.unwrap();
+    assert_eq!(relayed.iter().map(|e| e.event_id).collect::<Vec<_>>(), ids[..2]);
+    assert_eq!(store.dispatched_events_after(ids[0], 1).await.unwrap()[0].event_id, ids[1]);
+    assert_eq!(store.dispatched_through().await.unwrap(), ids[1]);
+
+    store.dispatch_event(&logged[2]).await.unwrap();
+    let relayed = store.dispatched_events_after(ids[1], 10).await.unwrap();
+    assert_eq!(relayed.iter().map(|e| e.event_id).collect::<Vec<_>>(), ids[2..]);
+    assert_eq!(store.dispatched_through().await.unwrap(), ids[3]);
+}
+
+async fn fx_rates_and_stake_totals(store: &dyn Store) {
+    let rate = |text: &str| text.parse::<ExchangeRate>().unwrap();
+    assert!(store.list_fx_rates().await.unwrap().is_empty());
+    store.set_fx_rate(Currency::Nzd, Currency::Aud, rate("0.9")).await.unwrap();
+    let updated = store.set_fx_rate(Currency::Nzd, Currency::Aud, rate("0.9215")).await.unwrap();
+    assert_eq!(updated.rate, rate("0.9215"));
+    store.set_fx_rate(Currency::Aud, Currency::Nzd, rate("1.085")).await.unwrap();
+    let rates = store.list_fx_rates().await.unwrap();
+    assert_eq!(rates.iter().map(|r| (r.from, r.to)).collect::<Vec<_>>(), [
+        (Currency::Aud, Currency::Nzd),
+        (Currency::Nzd, Currency::Aud)
+    ]);
+    assert!(store.delete_fx_rate(Currency::Aud, Currency::Nzd).await.unwrap());
+    assert!(!store.delete_fx_rate(Currency::Aud, Currency::Nzd).await.unwrap());
+
+    // Batches and bets take the account's currency
+    let aud = store.create_account(&account("aud", "h"), &ctx()).await.unwrap();
+    let mut nzd_request = account("nzd", "h");
+    nzd_request.currency = Currency::Nzd;
+    let nzd = store.create_account(&nzd_request, &ctx()).await.unwrap();
+    assert_eq!(nzd.currency, Currency::Nzd);
+    let aud_batch = store.create_batch(aud.id, &batch(1, "WIN", &["1", "2"]), &ctx()).await.unwrap();
+    let nzd_batch = store.create_batch(nzd.id, &batch(1, "WIN", &["3"]), &ctx()).await.unwrap();
+    assert_eq!(nzd_batch.currency, Currency::Nzd);
+    assert_eq!(nzd_batch.bets[0].currency, Currency::Nzd);
+    assert_eq!(aud_batch.total_stake, Money::from_minor(300));
+
+    let everything = TotalsQuery {
+        currency: Currency::Aud,
+        account_id: None,
+        status: None,
+        created_after: None,
+        created_before: None,
+    };
+    let totals = store.stake_totals(&everything, None).await.unwrap();
+    assert_eq!(totals, [
+        StakeTotals { currency: Currency::Aud, bets: 2, stake: Money::from_minor(300), cost: Money::from_minor(200) },
+        StakeTotals { currency: Currency::Nzd, bets: 1, stake: Money::from_minor(150), cost: Money::from_minor(100) },
+    ]);
+
+    let pid = aud_batch.bets[0].pid;
+    store
+        .update_bet_status(aud.id, aud_batch.id, &change(pid, PENDING, BetStatus::Successful), &ctx())
+        .await
+        .unwrap();
+    let successful = TotalsQuery { status: Some(BetStatus::Successful), ..everything };
+    let totals = store.stake_totals(&successful, None).await.unwrap();
+    assert_eq!(totals.len(), 1);
+    assert_eq!(totals[0].bets, 1);
+
+    let totals = store.stake_totals(&TotalsQuery { status: None, ..successful }, Some(&[nzd.id])).await.unwrap();
+    assert_eq!(totals.iter().map(|t| t.currency).collect::<Vec<_>>(), [Currency::Nzd]);
+    assert!(store.stake_totals(&everything, Some(&[])).await.unwrap().is_empty());
+}
+
+async fn race_results_settle_successful_bets(store: &dyn Store) {
+    let dividend = |bet_type: &str, selection: &str, amount: &str| Dividend {
+        bet_type: bet_type.parse().unwrap(),
+        selection: selection.to_string(),
+        dividend: amount.parse().unwrap(),
+    };
+    let result = |placings: &[u32], dividends| SetRaceResultRequest { placings: placings.to_vec(), dividends };
+
+    let acc = store.create_account(&account("settle", "h"), &ctx()).await.unwrap();
+    let win = store.create_batch(acc.id, &batch(5, "WIN", &["3", "1", "2"]), &ctx()).await.unwrap();
+    let exacta = store.create_batch(acc.id, &batch(5, "EXACTA", &["3/1"]), &ctx()).await.unwrap();
+    let other_race = store.create_batch(acc.id, &batch(6, "WIN", &["3"]), &ctx()).await.unwrap();
+    let pids: Vec<i64> = win.bets.iter().map(|b| b.pid).collect();
+    store
+        .update_bets_status(
+            acc.id,
+            win.id,
+            &[
+                change(pids[0], PENDING, BetStatus::Successful),
+                change(pids[1], PENDING, BetStatus::Successful),
+                change(pids[2], PENDING, BetStatus::Failed),
+            ],
+            &ctx(),
+        )
+        .await
+        .unwrap();
+    store
+        .update_bet_status(acc.id, other_race.id, &change(other_race.bets[0].pid, PENDING, BetStatus::Successful), &ctx())
+        .await
+        .unwrap();
+    assert!(store.get_race_result(5).await.unwrap().is_none());
+    assert_eq!(store.race_selections(5).await.unwrap(), [(BetType::Win, "1".to_string()), (BetType::Win, "3".to_string())]);
+
+    // Only successful bets settle; a batch with a pending bet is not settled
+    let (_, before) = store.event_id_range().await.unwrap().unwrap();
+    let first = result(&[3, 1, 7], vec![dividend("WIN", "3", "4.20"), dividend("EXACTA", "3/1", "15.00")]);
+    let settlement = store.set_race_result(5, &first, &ctx()).await.unwrap();
+    assert_eq!((settlement.settled, settlement.won, settlement.unsettled), (2, 1, 0));
+    assert_eq!(settlement.result.placings, [3, 1, 7]);
+    assert_eq!(store.get_race_result(5).await.unwrap().unwrap().dividends, first.dividends);
+
+    let bet = |batch_id, pid| async move { store.get_bet(acc.id, batch_id, pid).await.unwrap().unwrap() };
+    let won = bet(win.id, pids[0]).await;
+    assert_eq!((won.result, won.payout), (Some(BetResult::Won), Some(Money::from_minor(630))));
+    assert!(won.settled_at.is_some());
+    let lost = bet(win.id, pids[1]).await;
+    assert_eq!((lost.result, lost.payout), (Some(BetResult::Lost), Some(Money::ZERO)));
+    assert!(bet(win.id, pids[2]).await.result.is_none());
+    assert!(bet(exacta.id, exacta.bets[0].pid).await.result.is_none());
+    assert!(bet(other_race.id, other_race.bets[0].pid).await.result.is_none());
+
+    let events = store.events_after(before, 100).await.unwrap();
+    let names: Vec<&str> = events.iter().map(|e| e.event.event_name()).collect();
+    assert_eq!(names, ["bet_settled", "bet_settled", "batch_settled"]);
+    assert!(matches!(
+        &events[2].event,
+        BrokerEvent::BatchSettled { batch_id, race_id: 5, settled: 2, won: 1, stake, payout, currency: Currency::Aud, .. }
+            if *batch_id == win.id && *stake == Money::from_minor(300) && *payout == Money::from_minor(630)
+    ));
+
+    // Setting the same result again changes nothing; bets that have since
+    // become successful settle
+    let (_, before) = store.event_id_range().await.unwrap().unwrap();
+    let settlement = store.set_race_result(5, &first, &ctx()).await.unwrap();
+    assert_eq!((settlement.settled, settlement.won), (0, 0));
+    assert!(store.events_after(before, 100).await.unwrap().is_empty());
+    store
+        .update_bet_status(acc.id, exacta.id, &change(exacta.bets[0].pid, PENDING, BetStatus::Successful), &ctx())
+        .await
+        .unwrap();
+    let (_, before) = store.event_id_range().await.unwrap().unwrap();
+    let settlement = store.set_race_result(5, &first, &ctx()).await.unwrap();
+    assert_eq!((settlement.settled, settlement.won), (1, 1));
+    assert_eq!(bet(exacta.id, exacta.bets[0].pid).await.payout, Some(Money::from_minor(2250)));
+    let names: Vec<String> =
+        store.events_after(before, 100).await.unwrap().iter().map(|e| e.event.event_name().to_string()).collect();
+    assert_eq!(names, ["bet_settled", "batch_settled"]);
+
+    // A corrected result resettles the bets it changes
+    let corrected = result(&[1, 3, 7], vec![dividend("WIN", "1", "3.00"), dividend("EXACTA", "1/3", "15.00")]);
+    let settlement = store.set_race_result(5, &corrected, &ctx()).await.unwrap();
+    assert_eq!((settlement.settled, settlement.won), (3, 1));
+    assert_eq!(bet(win.id, pids[0]).await.result, Some(BetResult::Lost));
+    assert_eq!(bet(win.id, pids[1]).await.payout, Some(Money::from_minor(450)));
+    assert_eq!(bet(exacta.id, exacta.bets[0].pid).await.result, Some(BetResult::Lost));
+
+    // A winner is never settled lost: without a dividend it stays unsettled.
+    // A batch stored without a bet type cannot be settled either; both are
+    // reported rather than skipped silently
+    let mut untyped = batch(6, "WIN", &["1"]);
+    untyped.meta.bet_type = None;
+    let untyped = store.create_batch(acc.id, &untyped, &ctx()).await.unwrap();
+    store
+        .update_bet_status(acc.id, untyped.id, &change(untyped.bets[0].pid, PENDING, BetStatus::Successful), &ctx())
+        .await
+        .unwrap();
+    let settlement = store.set_race_result(6, &result(&[3, 1], vec![]), &ctx()).await.unwrap();
+    assert_eq!((settlement.settled, settlement.won, settlement.unsettled), (0, 0, 2));
+    assert!(bet(other_race.id, other_race.bets[0].pid).await.result.is_none());
+    assert!(bet(untyped.id, untyped.bets[0].pid).await.result.is_none());
+
+    // A bet overridden out of successful loses its settlement
+    store
+        .update_bet_status(acc.id, win.id, &change(pids[1], ANY, BetStatus::Failed), &ctx())
+        .await
+        .unwrap();
+    let failed = bet(win.id, pids[1]).await;
+    assert!(failed.result.is_none() && failed.payout.is_none() && failed.settled_at.is_none());
+}
+
+async fn snapshot_matches_the_event_log(store: &dyn Store) {
+    let empty = store.snapshot(None).await.unwrap();
+    assert_eq!(empty.event_id, 0);
+    assert!(empty.accounts.is_empty() && empty.batches.is_empty());
+
+    let alpha = store.create_account(&account("alpha", "a"), &ctx()).await.unwrap();
+    let beta = store.create_account(&account("beta", "b"), &ctx()).await.unwrap();
+    let open = store.create_batch(alpha.id, &batch(1, "WIN", &["1", "2"]), &ctx()).await.unwrap();
+    let done = store.create_batch(alpha.id, &batch(2, "WIN", &["3"]), &ctx()).await.unwrap();
+    store.create_batch(beta.id, &batch(3, "PLACE", &["4"]), &ctx()).await.unwrap();
+    store.complete_batch(alpha.id, done.id, &ctx()).await.unwrap();
+
+    let full = store.snapshot(None).await.unwrap();
+    assert_eq!(full.event_id, store.event_id_range().await.unwrap().unwrap().1);
+    assert_eq!(full.accounts.iter().map(|a| a.id).collect::<Vec<_>>(), [alpha.id, beta.id]);
+    assert_eq!(full.batches.len(), 2, "completed batches are left out");
+    assert_eq!(full.batches[0].id, open.id);
+    assert_eq!(full.batches[0].bets.len(), 2);
+
+    let scoped = store.snapshot(Some(&[beta.id])).await.unwrap();
+    assert_eq!(scoped.event_id, full.event_id);
+    assert_eq!(scoped.accounts.len(), 1);
+    assert_eq!(scoped.accounts[0].id, beta.id);
+    assert!(scoped.batches.iter().all(|b| b.account_id == beta.id));
+    assert!(store.snapshot(Some(&[])).await.unwrap().accounts.is_empty());
+
+    // Later changes move the snapshot forward
+    store.delete_account(beta.id, &ctx()).await.unwrap();
+    let after = store.snapshot(None).await.unwrap();
+    assert!(after.event_id > full.event_id);
+    assert_eq!(after.accounts.len(), 1);
+    assert_eq!(after.batches.len(), 1);
+}
+
+async fn webhook_deliveries_lifecycle(store: &dyn Store) {
+    let alpha = store.create_account(&account("alpha", "host-a"), &ctx()).await.unwrap();
+    let new_webhook = |event_types: Option<Vec<&str>>, account_id| NewWebhook {
+        url: "http://127.0.0.1:1/hook".to_string(),
+        secret: "whsec_test".to_string(),
+        event_types: event_types.map(|types| types.into_iter().map(String::from).collect()),
+        account_id,
+    };
+    let all = store.create_webhook(&new_webhook(None, None)).await.unwrap();
+    let filtered = store
+        .create_webhook(&new_webhook(Some(vec!["account_deleted"]), Some(alpha.id)))
+        .await
+        .unwrap();
+    assert_eq!(filtered.event_types, Some(vec!["account_deleted".to_string()]));
+    assert_eq!(store.list_webhooks().await.unwrap().len(), 2);
+
+    // Only the unfiltered webhook wants an update
+    let event = BrokerEvent::AccountUpdated { account: alpha.clone() };
+    let updated_id = store.append_event(&event, &ctx()).await.unwrap();
+    let updated = store.events_after(updated_id - 1, 1).await.unwrap();
+    assert_eq!(store.dispatch_event(&updated[0]).await.unwrap(), Some(1));
+    let event = BrokerEvent::AccountDeleted { id: alpha.id };
+    let deleted_id = store.append_event(&event, &ctx()).await.unwrap();
+    let deleted = store.events_after(deleted_id - 1, 1).await.unwrap();
+    assert_eq!(store.dispatch_event(&deleted[0]).await.unwrap(), Some(2));
+
+    // Claimed deliveries are not handed out again while their lease runs,
+    // even to concurrent claims
+    let now = Utc::now();
+    let lease = Duration::from_secs(30);
+    let (first, second) = tokio::join!(
+        store.claim_webhook_deliveries(now, lease, 2),
+        store.claim_webhook_deliveries(now, lease, 2)
+    );
+    let mut claimed: Vec<i64> = first.unwrap().iter().chain(&second.unwrap()).map(|d| d.id).collect();
+    claimed.sort_unstable();
+    claimed.dedup();
+    assert_eq!(claimed.len(), 3);
+    assert!(store.claim_webhook_deliveries(now, lease, 10).await.unwrap().is_empty());
+
+    // A claim whose attempt is never recorded lapses with its lease
+    let due = store
+        .claim_webhook_deliveries(now + chrono::Duration::seconds(31), lease, 10)
+        .await
+        .unwrap();
+    assert_eq!(due.iter().map(|d| d.id).collect::<Vec<_>>(), claimed);
+    assert_eq!(due[0].event_type, "account_updated");
+    assert_eq!(due[0].secret, "whsec_test");
+    assert_eq!(due[0].attempts, 0);
+
+    // A retry is not due until its scheduled time
+    let retry = DeliveryAttempt {
+        outcome: AttemptOutcome::Retry { at: now + chrono::Duration::seconds(60) },
+        status_code: Some(500),
+        error: Some("HTTP 500".to_string()),
+    };
+    store.record_webhook_attempt(due[0].id, &retry).await.unwrap();
+    let delivered = DeliveryAttempt { outcome: AttemptOutcome::Delivered, status_code: Some(200), error: None };
+    store.record_webhook_attempt(due[1].id, &delivered).await.unwrap();
+    let dead = DeliveryAttempt { outcome: AttemptOutcome::Dead, status_code: None, error: Some("refused".to_string()) };
+    store.record_webhook_attempt(due[2].id, &dead).await.unwrap();
+
+    assert!(store.claim_webhook_deliveries(now, lease, 10).await.unwrap().is_empty());
+    let later = store
+        .claim_webhook_deliveries(now + chrono::Duration::seconds(61), lease, 10)
+        .await
+        .unwrap();
+    assert_eq!(later.len(), 1);
+    assert_eq!(later[0].attempts, 1);
+
+    let history = store.list_webhook_deliveries(all.id, None, &first_page(10)).await.unwrap();
+    assert_eq!(history.items.len(), 2);
+    assert_eq!(history.items[0].event_id, deleted_id);
+    assert_eq!(history.items[1].status, DeliveryStatus::Pending);
+    assert_eq!(history.items[1].last_status_code, Some(500));
+    assert!(history.items[1].next_attempt_at.is_some());
+
+    let page = store.list_webhook_deliveries(all.id, None, &first_page(1)).await.unwrap();
+    let cursor = page.next_cursor.unwrap();
+    let rest = store
+        .list_webhook_deliveries(all.id, None, &next_page(1, &cursor))
+        .await
+        .unwrap();
+    assert_eq!(rest.items[0].id, history.items[1].id);
+    assert!(rest.next_cursor.is_none());
+
+    let dead_letters = store
+        .list_webhook_deliveries(filtered.id, Some(DeliveryStatus::Dead), &first_page(10))
+        .await
+        .unwrap();
+    assert_eq!(dead_letters.items.len(), 1);
+    assert_eq!(dead_letters.items[0].last_error.as_deref(), Some("refused"));
+    assert!(dead_letters.items[0].next_attempt_at.is_none());
+
+    // Deleting the webhook removes its deliveries
+    assert!(store.delete_webhook(all.id).await.unwrap());
+    assert!(!store.delete_webhook(all.id).await.unwrap());
+    assert!(store.get_webhook(all.id).await.unwrap().is_none());
+    assert!(store
+        .claim_webhook_deliveries(now + chrono::Duration::hours(1), lease, 10)
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,174 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use tokio::sync::Notify;
+
+use crate::config::WebhooksConfig;
+use crate::models::webhook::{AttemptOutcome, DeliveryAttempt, DueDelivery};
+use crate::store::Store;
+
+/// Prefix of generated signing secrets
+pub const SECRET_PREFIX: &str = "whsec_";
+
+/// Header with `sha256=<hex HMAC of "<timestamp>.<body>">`
+pub const SIGNATURE_HEADER: &str = "x-betstream-signature";
+
+/// Header with the unix time the delivery was signed at
+pub const TIMESTAMP_HEADER: &str = "x-betstream-timestamp";
+
+/// Header with the event type, e.g. `bet_status_updated`
+pub const EVENT_HEADER: &str = "x-betstream-event";
+
+/// Header with the delivery id; retries of a delivery reuse it
+pub const DELIVERY_HEADER: &str = "x-betstream-delivery";
+
+// Deliveries picked up per pass of the worker
+const BATCH_SIZE: i64 = 50;
+
+// Longest response body excerpt kept as `last_error`
+const MAX_ERROR_LEN: usize = 500;
+
+/// Generate a new random signing secret
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", SECRET_PREFIX, hex::encode(bytes))
+}
+
+/// Signature header value for `body` sent at `timestamp`. Receivers recompute
+/// it with their copy of the secret and should reject stale timestamps.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Wait before retrying after `attempts` failed attempts: the initial backoff,
+/// doubled per further attempt and capped at the maximum
+pub fn backoff(config: &WebhooksConfig, attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    config
+        .initial_backoff()
+        .saturating_mul(factor)
+        .min(config.max_backoff())
+}
+
+/// Send due deliveries in the background. The worker wakes on `wakeup`, which
+/// dispatching signals after queueing deliveries, and every poll interval for
+/// retries that have come due. Deliveries are claimed before they are sent,
+/// so each is sent by one instance even when several share the database.
+pub fn spawn_worker(store: Arc<dyn Store>, config: WebhooksConfig, wakeup: Arc<Notify>) {
+    tokio::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout())
+            .build()
+            .expect("build webhook HTTP client");
+
+        loop {
+            loop {
+                let due = match store.claim_webhook_deliveries(Utc::now(), config.claim_lease(), BATCH_SIZE).await {
+                    Ok(due) => due,
+                    Err(e) => {
+                        eprintln!("Failed to load due webhook deliveries: {:?}", e);
+                        break;
+                    }
+                };
+                if due.is_empty() {
+                    break;
+                }
+                let attempts = due
+                    .into_iter()
+                    .map(|delivery| attempt(&client, store.as_ref(), &config, delivery));
+                futures::future::join_all(attempts).await;
+            }
+
+            tokio::select! {
+                _ = wakeup.notified() => {}
+                _ = tokio::time::sleep(config.poll_interval()) => {}
+            }
+        }
+    });
+}
+
+// POST one delivery and record the outcome
+async fn attempt(client: &reqwest::Client, store: &dyn Store, config: &WebhooksConfig, delivery: DueDelivery) {
+    let timestamp = Utc::now().timestamp();
+    let response = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.event_type)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign(&delivery.secret, timestamp, &delivery.payload))
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    let (status_code, error) = match response {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+        Ok(response) => {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let excerpt: String = body.chars().take(MAX_ERROR_LEN).collect();
+            (Some(status.as_u16()), Some(format!("HTTP {}: {}", status, excerpt)))
+        }
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    let attempts = u32::try_from(delivery.attempts).unwrap_or(0) + 1;
+    let outcome = if error.is_none() {
+        AttemptOutcome::Delivered
+    } else if attempts >= config.max_attempts {
+        AttemptOutcome::Dead
+    } else {
+        let wait = chrono::Duration::from_std(backoff(config, attempts)).unwrap_or(chrono::Duration::MAX);
+        AttemptOutcome::Retry { at: Utc::now() + wait }
+    };
+    if matches!(outcome, AttemptOutcome::Dead) {
+        eprintln!(
+            "Webhook delivery {} to {} dead-lettered after {} attempts",
+            delivery.id, delivery.url, attempts
+        );
+    }
+
+    let attempt = DeliveryAttempt { outcome, status_code, error };
+    if let Err(e) = store.record_webhook_attempt(delivery.id, &attempt).await {
+        eprintln!("Failed to record webhook delivery {}: {:?}", delivery.id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let config = WebhooksConfig {
+            initial_backoff_ms: 500,
+            max_backoff_secs: 3,
+            ..WebhooksConfig::default()
+        };
+
+        assert_eq!(backoff(&config, 1), Duration::from_millis(500));
+        assert_eq!(backoff(&config, 2), Duration::from_secs(1));
+        assert_eq!(backoff(&config, 3), Duration::from_secs(2));
+        assert_eq!(backoff(&config, 4), Duration::from_secs(3));
+        assert_eq!(backoff(&config, 40), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let signature = sign("secret", 1700000000, "{}");
+
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_eq!(signature, sign("secret", 1700000000, "{}"));
+        assert_ne!(signature, sign("secret", 1700000001, "{}"));
+        assert_ne!(signature, sign("other", 1700000000, "{}"));
+    }
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn public_routes_need_no_key() {
+    let app = TestApp::new().await;
+
+    let root = app.request_as(None, axum::http::Method::GET, "/", None).await;
+    assert_eq!(root.status, StatusCode::OK);
+    assert_eq!(root.body, "Betting API 🎲");
+
+    let health = app.request_as(None, axum::http::Method::GET, "/health", None).await;
+    assert_eq!(health.status, StatusCode::OK);
+    assert_eq!(health.body, "OK");
+
+    let docs = app
+        .request_as(None, axum::http::Method::GET, "/api-docs/openapi.json", None)
+        .await;
+    assert_eq!(docs.status, StatusCode::OK);
+    assert!(docs.body["paths"]["/api/v1/accounts"].is_object());
+    assert!(docs.body["components"]["securitySchemes"]["bearer_auth"].is_object());
+}
+
+#[tokio::test]
+async fn account_crud() {
+    let app = TestApp::new().await;
+
+    let account = app.create_account("alpha").await;
+    let id = account["id"].as_i64().unwrap();
+    assert_eq!(account["hostname"], "alpha.example.com");
+
+    let fetched = app.get(&format!("/api/v1/accounts/{}", id)).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.body["name"], "alpha");
+
+    let updated = app
+        .put(
+            &format!("/api/v1/accounts/{}", id),
+            json!({ "name": "alpha2", "hostname": "alpha2.example.com", "currency": "AUD" }),
+        )
+        .await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(updated.body["name"], "alpha2");
+
+    let deleted = app.delete(&format!("/api/v1/accounts/{}", id)).await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+
+    let missing = app.get(&format!("/api/v1/accounts/{}", id)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.headers["content-type"], "application/problem+json");
+    assert_eq!(missing.body["status"], 404);
+
+    let missing = app.delete(&format!("/api/v1/accounts/{}", id)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn duplicate_names_conflict() {
+    let app = TestApp::new().await;
+    app.create_account("alpha").await;
+
+    let duplicate = app
+        .post("/api/v1/accounts", json!({ "name": "alpha", "hostname": "other", "currency": "AUD" }))
+        .await;
+    assert_eq!(duplicate.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn invalid_input_is_rejected() {
+    let app = TestApp::new().await;
+
+    let missing_field = app.post("/api/v1/accounts", json!({ "name": "alpha" })).await;
+    assert_eq!(missing_field.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let bad_path = app.get("/api/v1/accounts/abc").await;
+    assert_eq!(bad_path.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn list_accounts_paginates_and_filters() {
+    let app = TestApp::new().await;
+    for name in ["alpha", "beta", "gamma"] {
+        app.create_account(name).await;
+    }
+
+    let first = app.get("/api/v1/accounts?limit=2").await;
+    assert_eq!(first.status, StatusCode::OK);
+    assert_eq!(first.body["items"].as_array().unwrap().len(), 2);
+    assert_eq!(first.body["has_more"], true);
+
+    let cursor = first.body["next_cursor"].as_str().unwrap();
+    let second = app
+        .get(&format!("/api/v1/accounts?limit=2&cursor={}", cursor))
+        .await;
+    assert_eq!(second.body["items"].as_array().unwrap().len(), 1);
+    assert_eq!(second.body["has_more"], false);
+
+    let filtered = app.get("/api/v1/accounts?name=beta").await;
+    let items = filtered.body["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["name"], "beta");
+}
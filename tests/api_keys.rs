@@ -0,0 +1,135 @@
+mod common;
+
+use axum::http::{header, Method, StatusCode};
+use common::{test_config, TestApp};
+use serde_json::json;
+
+#[tokio::test]
+async fn protected_routes_require_a_key() {
+    let app = TestApp::new().await;
+
+    let missing = app.request_as(None, Method::GET, "/api/v1/accounts", None).await;
+    assert_eq!(missing.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(missing.headers[header::WWW_AUTHENTICATE], "Bearer");
+
+    let wrong = app
+        .request_as(Some("bsk_wrong"), Method::GET, "/api/v1/accounts", None)
+        .await;
+    assert_eq!(wrong.status, StatusCode::UNAUTHORIZED);
+
+    let sse = app.request_as(None, Method::GET, "/sse", None).await;
+    assert_eq!(sse.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn api_key_lifecycle() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+
+    let created = app
+        .post(
+            "/api/v1/admin/api-keys",
+            json!({ "name": "reader", "access": "read", "account_ids": [account_id] }),
+        )
+        .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    let key = created.body["key"].as_str().unwrap().to_string();
+    let key_id = created.body["id"].as_i64().unwrap();
+    assert!(key.starts_with(created.body["key_prefix"].as_str().unwrap()));
+
+    let listed = app.get("/api/v1/admin/api-keys").await;
+    assert_eq!(listed.status, StatusCode::OK);
+    let keys = listed.body.as_array().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert!(keys[0].get("key").is_none());
+    assert_eq!(keys[0]["account_ids"], json!([account_id]));
+
+    let uri = format!("/api/v1/accounts/{}", account_id);
+    let allowed = app.request_as(Some(&key), Method::GET, &uri, None).await;
+    assert_eq!(allowed.status, StatusCode::OK);
+
+    let revoked = app.delete(&format!("/api/v1/admin/api-keys/{}", key_id)).await;
+    assert_eq!(revoked.status, StatusCode::NO_CONTENT);
+
+    let rejected = app.request_as(Some(&key), Method::GET, &uri, None).await;
+    assert_eq!(rejected.status, StatusCode::UNAUTHORIZED);
+
+    let again = app.delete(&format!("/api/v1/admin/api-keys/{}", key_id)).await;
+    assert_eq!(again.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn invalid_key_requests_are_rejected() {
+    let app = TestApp::new().await;
+
+    let empty_name = app
+        .post("/api/v1/admin/api-keys", json!({ "name": " ", "access": "read" }))
+        .await;
+    assert_eq!(empty_name.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let unknown_account = app
+        .post(
+            "/api/v1/admin/api-keys",
+            json!({ "name": "x", "access": "read", "account_ids": [999] }),
+        )
+        .await;
+    assert_eq!(unknown_account.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn scoped_keys_are_confined() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let reader = app.create_key("read", Some(vec![alpha])).await;
+    let writer = app.create_key("write", Some(vec![alpha])).await;
+
+    let listed = app
+        .request_as(Some(&reader), Method::GET, "/api/v1/accounts", None)
+        .await;
+    let items = listed.body["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["id"], alpha);
+
+    let other = app
+        .request_as(Some(&reader), Method::GET, &format!("/api/v1/accounts/{}", beta), None)
+        .await;
+    assert_eq!(other.status, StatusCode::FORBIDDEN);
+
+    let body = json!({ "name": "alpha2", "hostname": "h", "currency": "AUD" });
+    let read_only = app
+        .request_as(Some(&reader), Method::PUT, &format!("/api/v1/accounts/{}", alpha), Some(body.clone()))
+        .await;
+    assert_eq!(read_only.status, StatusCode::FORBIDDEN);
+
+    let write = app
+        .request_as(Some(&writer), Method::PUT, &format!("/api/v1/accounts/{}", alpha), Some(body))
+        .await;
+    assert_eq!(write.status, StatusCode::OK);
+
+    // Scoped keys cannot create accounts or manage keys
+    let create = app
+        .request_as(
+            Some(&writer),
+            Method::POST,
+            "/api/v1/accounts",
+            Some(json!({ "name": "gamma", "hostname": "h", "currency": "AUD" })),
+        )
+        .await;
+    assert_eq!(create.status, StatusCode::FORBIDDEN);
+
+    let admin = app
+        .request_as(Some(&writer), Method::GET, "/api/v1/admin/api-keys", None)
+        .await;
+    assert_eq!(admin.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn disabled_auth_allows_anonymous_access() {
+    let mut config = test_config();
+    config.auth.enabled = false;
+    let app = TestApp::with_config(config).await;
+
+    let response = app.request_as(None, Method::GET, "/api/v1/accounts", None).await;
+    assert_eq!(response.status, StatusCode::OK);
+}
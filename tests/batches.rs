@@ -0,0 +1,304 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn batch_lifecycle() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+
+    let batch = app.create_batch(account_id, &[(1, "1"), (2, "2")]).await;
+    let batch_id = batch["id"].as_i64().unwrap();
+    assert_eq!(batch["completed"], false);
+    assert_eq!(batch["bets"].as_array().unwrap().len(), 2);
+    assert!(batch["bets"].as_array().unwrap().iter().all(|bet| bet["status"] == "pending"));
+
+    let listed = app.get(&format!("/api/v1/accounts/{}/batches", account_id)).await;
+    assert_eq!(listed.status, StatusCode::OK);
+    assert_eq!(listed.body["items"][0]["id"], batch_id);
+
+    let fetched = app
+        .get(&format!("/api/v1/accounts/{}/batches/{}", account_id, batch_id))
+        .await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.body["bets"], batch["bets"]);
+
+    let completed = app
+        .delete(&format!("/api/v1/accounts/{}/batches/{}", account_id, batch_id))
+        .await;
+    assert_eq!(completed.status, StatusCode::OK);
+
+    let open = app
+        .get(&format!("/api/v1/accounts/{}/batches?completed=false", account_id))
+        .await;
+    assert!(open.body["items"].as_array().unwrap().is_empty());
+
+    // Completing twice finds no open batch
+    let again = app
+        .delete(&format!("/api/v1/accounts/{}/batches/{}", account_id, batch_id))
+        .await;
+    assert_eq!(again.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn batches_for_unknown_accounts_are_rejected() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post(
+            "/api/v1/accounts/999/batches",
+            json!({ "meta": { "race_id": 1, "bet_type": "WIN" }, "bets": [] }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn amounts_are_exact_decimal_strings() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches", account_id);
+    let bet = |stake: serde_json::Value, cost: serde_json::Value| {
+        json!({ "meta": { "race_id": 1, "bet_type": "WIN" }, "bets": [{ "id": 1, "selection": "1", "stake": stake, "cost": cost }] })
+    };
+
+    // Numbers are read by their digits, so 0.1 stays exactly ten cents
+    let response = app.post(&uri, bet(json!(0.1), json!("0.2"))).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(response.body["bets"][0]["stake"], "0.10");
+    assert_eq!(response.body["bets"][0]["cost"], "0.20");
+
+    for (stake, cost) in [
+        (json!("-1.00"), json!("1")),
+        (json!(1.005), json!("1")),
+        (json!("1"), json!("NaN")),
+        (json!("1e3"), json!("1")),
+        (json!(null), json!("1")),
+        // Above the 1,000,000,000.00 cap that keeps totals from overflowing
+        (json!("50000000000000000"), json!("1")),
+        (json!("1"), json!(1000000000.01)),
+    ] {
+        let response = app.post(&uri, bet(stake.clone(), cost.clone())).await;
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY, "{} / {}", stake, cost);
+    }
+    let response = app.post(&uri, bet(json!("1000000000.00"), json!("1"))).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+}
+
+#[tokio::test]
+async fn batch_totals_are_capped_and_their_events_still_stream() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches", account_id);
+    let batch = |stake: &str| {
+        let bet = |id: i64| json!({ "id": id, "selection": id.to_string(), "stake": stake, "cost": "1" });
+        json!({ "meta": { "race_id": 1, "bet_type": "WIN" }, "bets": [bet(1), bet(2)] })
+    };
+    let mut sse = app.sse().await;
+
+    // Each stake is under the cap, but together they are over it
+    let response = app.post(&uri, batch("600000000")).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.body["errors"][0]["pointer"], "/bets");
+    assert_eq!(
+        response.body["errors"][0]["detail"],
+        "Total stake is above the most a batch may carry, 1000000000.00"
+    );
+
+    let response = app.post(&uri, batch("500000000")).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(response.body["total_stake"], "1000000000.00");
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "batch_created");
+    assert_eq!(event.data["batch"]["total_stake"], "1000000000.00");
+
+    // Later events are not held up behind it
+    app.create_account("beta").await;
+    assert_eq!(sse.next_event().await.event, "account_created");
+}
+
+#[tokio::test]
+async fn selections_must_fit_the_bet_type() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches", account_id);
+    let batch = |bet_type: &str, selections: &[&str]| {
+        let bets: Vec<_> = selections
+            .iter()
+            .enumerate()
+            .map(|(i, selection)| json!({ "id": i + 1, "selection": selection, "stake": "1.00", "cost": "1.00" }))
+            .collect();
+        json!({ "meta": { "race_id": 1, "bet_type": bet_type }, "bets": bets })
+    };
+
+    let ok = app.post(&uri, batch("TRIFECTA", &["1/2/3", "3/2/1"])).await;
+    assert_eq!(ok.status, StatusCode::OK, "{}", ok.body);
+
+    let invalid = app.post(&uri, batch("TRIFECTA", &["1/2/3", "1/2", "4/4/5", "x"])).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(invalid.body["code"], "validation_failed");
+    let errors = invalid.body["errors"].as_array().unwrap();
+    let pointers: Vec<_> = errors.iter().map(|e| e["pointer"].as_str().unwrap()).collect();
+    assert_eq!(pointers, ["/bets/1/selection", "/bets/2/selection", "/bets/3/selection"]);
+    assert_eq!(errors[0]["detail"], "Bet 2: TRIFECTA takes 3 runners, got 2");
+
+    let win = app.post(&uri, batch("WIN", &["1/2"])).await;
+    assert_eq!(win.body["detail"], "Bet 1: WIN takes 1 runner, got 2");
+
+    let unknown = app.post(&uri, batch("DOUBLE", &["1"])).await;
+    assert_eq!(unknown.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(unknown.body["detail"].as_str().unwrap().contains("Unknown bet type: DOUBLE"));
+
+    // Nothing from the rejected batches was stored
+    let page = app.get(&uri).await;
+    assert_eq!(page.body["items"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn meta_is_typed_and_filterable() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches", account_id);
+
+    let meta = json!({ "race_id": 7, "bet_type": "place", "venue": "Randwick", "name": "Morning run" });
+    let created = app.post(&uri, json!({ "meta": meta, "bets": [] })).await;
+    assert_eq!(created.status, StatusCode::OK, "{}", created.body);
+    assert_eq!(
+        created.body["meta"],
+        json!({ "race_id": 7, "bet_type": "PLACE", "venue": "Randwick", "name": "Morning run" })
+    );
+    app.create_batch(account_id, &[(1, "1")]).await;
+
+    for query in ["race_id=7", "bet_type=place", "venue=Randwick"] {
+        let page = app.get(&format!("{}?{}", uri, query)).await;
+        let items = page.body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1, "{}", query);
+        assert_eq!(items[0]["id"], created.body["id"]);
+    }
+    assert_eq!(app.get(&format!("{}?bet_type=DOUBLE", uri)).await.status, StatusCode::BAD_REQUEST);
+
+    let missing = app.post(&uri, json!({ "meta": { "name": "no race" }, "bets": [] })).await;
+    assert_eq!(missing.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let pointers: Vec<_> = missing.body["errors"].as_array().unwrap().iter().map(|e| e["pointer"].clone()).collect();
+    assert_eq!(pointers, [json!("/meta/race_id"), json!("/meta/bet_type")]);
+
+    // Bets without a race or bet type could never be settled
+    let bet = json!({ "id": 1, "selection": "1", "stake": "1.00", "cost": "1.00" });
+    let untyped = app.post(&uri, json!({ "meta": { "race_id": 7 }, "bets": [bet] })).await;
+    assert_eq!(untyped.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(untyped.body["errors"][0]["pointer"], "/meta/bet_type");
+    let unraced = app.post(&uri, json!({ "meta": { "bet_type": "WIN" }, "bets": [bet] })).await;
+    assert_eq!(unraced.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(unraced.body["errors"][0]["pointer"], "/meta/race_id");
+    assert_eq!(app.get(&uri).await.body["items"].as_array().unwrap().len(), 2);
+
+    let string_race = app.post(&uri, json!({ "meta": { "race_id": "R1", "bet_type": "WIN" }, "bets": [] })).await;
+    assert_eq!(string_race.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn batches_are_scoped_to_their_account() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let batch_id = app.create_batch(alpha, &[(1, "1")]).await["id"].as_i64().unwrap();
+
+    let response = app
+        .get(&format!("/api/v1/accounts/{}/batches/{}", beta, batch_id))
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn single_bet_get_and_update() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "1")]).await;
+    let batch_id = batch["id"].as_i64().unwrap();
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches/{}/bets/{}", account_id, batch_id, pid);
+
+    let bet = app.get(&uri).await;
+    assert_eq!(bet.status, StatusCode::OK);
+    assert_eq!(bet.body["selection"], "1");
+
+    let updated = app.patch(&uri, json!({ "status": "failed" })).await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(updated.body["status"], "failed");
+
+    let invalid = app.patch(&uri, json!({ "status": "won" })).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let missing = app
+        .get(&format!("/api/v1/accounts/{}/batches/{}/bets/999", account_id, batch_id))
+        .await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn bulk_bet_update() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "1"), (2, "2"), (3, "3")]).await;
+    let batch_id = batch["id"].as_i64().unwrap();
+    let pids: Vec<i64> = batch["bets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bet| bet["pid"].as_i64().unwrap())
+        .collect();
+    let uri = format!("/api/v1/accounts/{}/batches/{}/bets", account_id, batch_id);
+
+    let updated = app
+        .patch(
+            &uri,
+            json!([
+                { "pid": pids[0], "status": "successful", "reference": "TKT-1" },
+                { "pid": 999, "status": "successful" },
+                { "pid": pids[1], "status": "failed", "message": "price moved" }
+            ]),
+        )
+        .await;
+    assert_eq!(updated.status, StatusCode::OK);
+    let results = updated.body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["outcome"], "updated");
+    assert_eq!(results[0]["bet"]["status"], "successful");
+    assert_eq!(results[0]["bet"]["reference"], "TKT-1");
+    assert_eq!(results[1], json!({ "pid": 999, "outcome": "not_found" }));
+    assert_eq!(results[2]["bet"]["status"], "failed");
+    assert_eq!(results[2]["bet"]["message"], "price moved");
+
+    let untouched = app.get(&format!("{}/{}", uri, pids[2])).await;
+    assert_eq!(untouched.body["status"], "pending");
+}
+
+#[tokio::test]
+async fn bulk_bet_update_rejects_bad_requests() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "1")]).await;
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches/{}/bets", account_id, batch["id"]);
+
+    let duplicate = app
+        .patch(
+            &uri,
+            json!([{ "pid": pid, "status": "successful" }, { "pid": pid, "status": "failed" }]),
+        )
+        .await;
+    assert_eq!(duplicate.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let missing_status = app.patch(&uri, json!([{ "pid": pid }])).await;
+    assert_eq!(missing_status.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let unknown_batch = app
+        .patch(
+            &format!("/api/v1/accounts/{}/batches/999/bets", account_id),
+            json!([{ "pid": pid, "status": "successful" }]),
+        )
+        .await;
+    assert_eq!(unknown_batch.status, StatusCode::NOT_FOUND);
+}
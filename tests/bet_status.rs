@@ -0,0 +1,128 @@
+//! The bet status state machine as seen through the API.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+const STATUSES: [&str; 3] = ["pending", "successful", "failed"];
+
+fn allowed(from: &str, to: &str) -> bool {
+    from == "pending" && (to == "successful" || to == "failed")
+}
+
+// A fresh bet moved into `status` through a legal transition
+async fn bet_in(app: &TestApp, account_id: i64, status: &str) -> String {
+    let batch = app.create_batch(account_id, &[(1, "1")]).await;
+    let uri = format!(
+        "/api/v1/accounts/{}/batches/{}/bets/{}",
+        account_id, batch["id"], batch["bets"][0]["pid"]
+    );
+    if status != "pending" {
+        let response = app.patch(&uri, json!({ "status": status })).await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+    uri
+}
+
+#[tokio::test]
+async fn every_transition_follows_the_table() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+
+    for from in STATUSES {
+        for to in STATUSES {
+            let uri = bet_in(&app, account_id, from).await;
+            let response = app.patch(&uri, json!({ "status": to })).await;
+
+            if allowed(from, to) {
+                assert_eq!(response.status, StatusCode::OK, "{} -> {}", from, to);
+                assert_eq!(response.body["status"], to);
+            } else {
+                assert_eq!(response.status, StatusCode::CONFLICT, "{} -> {}", from, to);
+                assert_eq!(response.body["code"], "conflict");
+                let unchanged = app.get(&uri).await;
+                assert_eq!(unchanged.body["status"], from);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn admin_override_allows_any_transition() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+
+    for from in STATUSES {
+        for to in STATUSES {
+            let uri = bet_in(&app, account_id, from).await;
+            let response = app.patch(&uri, json!({ "status": to, "force": true })).await;
+            assert_eq!(response.status, StatusCode::OK, "{} -> {}", from, to);
+            assert_eq!(response.body["status"], to);
+        }
+    }
+}
+
+#[tokio::test]
+async fn override_requires_the_admin_key() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let writer = app.create_key("write", None).await;
+    let uri = bet_in(&app, account_id, "successful").await;
+
+    let response = app
+        .request_as(
+            Some(&writer),
+            Method::PATCH,
+            &uri,
+            Some(json!({ "status": "failed", "force": true })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn completed_batches_are_frozen() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "1")]).await;
+    let batch_uri = format!("/api/v1/accounts/{}/batches/{}", account_id, batch["id"]);
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+    app.delete(&batch_uri).await;
+
+    let single = app
+        .patch(&format!("{}/bets/{}", batch_uri, pid), json!({ "status": "successful", "force": true }))
+        .await;
+    assert_eq!(single.status, StatusCode::CONFLICT);
+
+    let bulk = app
+        .patch(
+            &format!("{}/bets", batch_uri),
+            json!([{ "pid": pid, "status": "successful" }]),
+        )
+        .await;
+    assert_eq!(bulk.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn bulk_updates_refuse_settled_bets() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "1"), (2, "2")]).await;
+    let batch_uri = format!("/api/v1/accounts/{}/batches/{}", account_id, batch["id"]);
+    let (a, b) = (batch["bets"][0]["pid"].clone(), batch["bets"][1]["pid"].clone());
+    app.patch(&format!("{}/bets/{}", batch_uri, a), json!({ "status": "failed" }))
+        .await;
+
+    let bulk = app
+        .patch(
+            &format!("{}/bets", batch_uri),
+            json!([{ "pid": b, "status": "successful" }, { "pid": a, "status": "successful" }]),
+        )
+        .await;
+    assert_eq!(bulk.status, StatusCode::CONFLICT);
+
+    let untouched = app.get(&format!("{}/bets/{}", batch_uri, b)).await;
+    assert_eq!(untouched.body["status"], "pending");
+}
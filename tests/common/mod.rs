@@ -0,0 +1,453 @@
+//! In-process test harness: the full router on a private in-memory SQLite
+//! database, driven with `tower::ServiceExt::oneshot` without binding a port.
+//! Webhook tests also get a local HTTP stand-in, [`WebhookReceiver`], and
+//! WebSocket tests serve the router on a local port for a [`WsClient`].
+
+#![allow(dead_code)]
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    body::{Body, BodyDataStream},
+    extract::State,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    routing::post,
+    Router,
+};
+use betstream::{build_app, config::Config};
+use futures::{SinkExt, StreamExt};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tower::ServiceExt;
+
+pub const ADMIN_KEY: &str = "test-admin-key-0123456789abcdef0123456789";
+
+/// How long [`SseClient::next_event`] waits before failing the test
+const SSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct TestApp {
+    router: Router,
+}
+
+/// A buffered response; `body` is parsed as JSON when possible, otherwise
+/// kept as a JSON string
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        Self::with_config(test_config()).await
+    }
+
+    pub async fn with_config(config: Config) -> Self {
+        let router = build_app(config).await.expect("build app");
+        TestApp { router }
+    }
+
+    /// Send a request as the admin
+    pub async fn request(&self, method: Method, uri: &str, body: Option<Value>) -> TestResponse {
+        self.request_as(Some(ADMIN_KEY), method, uri, body).await
+    }
+
+    /// Send a request with the given bearer key, or none at all
+    pub async fn request_as(
+        &self,
+        key: Option<&str>,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+    ) -> TestResponse {
+        self.request_with_headers(key, method, uri, body, &[]).await
+    }
+
+    /// Send a request as `key` with extra headers
+    pub async fn request_with_headers(
+        &self,
+        key: Option<&str>,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+        headers: &[(&str, &str)],
+    ) -> TestResponse {
+        let mut builder = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        if let Some(key) = key {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", key));
+        }
+        let request = match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .expect("valid request");
+
+        self.send(request).await
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> TestResponse {
+        let response = self.router.clone().oneshot(request).await.expect("infallible");
+        let (parts, body) = response.into_parts();
+        let bytes = body.collect().await.expect("read body").to_bytes();
+        let body = serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+
+        TestResponse { status: parts.status, headers: parts.headers, body }
+    }
+
+    pub async fn get(&self, uri: &str) -> TestResponse {
+        self.request(Method::GET, uri, None).await
+    }
+
+    pub async fn post(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::POST, uri, Some(body)).await
+    }
+
+    pub async fn put(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::PUT, uri, Some(body)).await
+    }
+
+    pub async fn patch(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::PATCH, uri, Some(body)).await
+    }
+
+    pub async fn delete(&self, uri: &str) -> TestResponse {
+        self.request(Method::DELETE, uri, None).await
+    }
+
+    pub async fn create_account(&self, name: &str) -> Value {
+        let response = self
+            .post("/api/v1/accounts", json!({ "name": name, "hostname": format!("{}.example.com", name), "currency": "AUD" }))
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+        response.body
+    }
+
+    /// Create a batch with one pending bet per `(id, selection)`
+    pub async fn create_batch(&self, account_id: i64, bets: &[(i64, &str)]) -> Value {
+        let bets: Vec<Value> = bets
+            .iter()
+            .map(|(id, selection)| json!({ "id": id, "selection": selection, "stake": "10.00", "cost": "10.00" }))
+            .collect();
+        let response = self
+            .post(
+                &format!("/api/v1/accounts/{}/batches", account_id),
+                json!({ "meta": { "race_id": 1, "bet_type": "win" }, "bets": bets }),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+        response.body
+    }
+
+    /// Mint an API key through the admin endpoint and return its secret
+    pub async fn create_key(&self, access: &str, account_ids: Option<Vec<i64>>) -> String {
+        let response = self
+            .post(
+                "/api/v1/admin/api-keys",
+                json!({ "name": "test", "access": access, "account_ids": account_ids }),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::CREATED, "{}", response.body);
+        response.body["key"].as_str().expect("key in response").to_string()
+    }
+
+    /// Serve the router on a local port, for clients that need a real
+    /// connection; returns the address, e.g. `127.0.0.1:40123`
+    pub async fn serve(&self) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind test server");
+        let addr = listener.local_addr().unwrap().to_string();
+        let router = self.router.clone();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        addr
+    }
+
+    /// Open `/ws` on a served copy of the app as `key`
+    pub async fn ws_as(&self, key: &str) -> WsClient {
+        let url = format!("ws://{}/ws?api_key={}", self.serve().await, key);
+        let (stream, _) = tokio_tungstenite::connect_async(url).await.expect("WebSocket handshake");
+        WsClient { stream }
+    }
+
+    /// Open `/sse` as the admin
+    pub async fn sse(&self) -> SseClient {
+        self.sse_as(ADMIN_KEY).await
+    }
+
+    /// Open `/sse` with the given key; the subscription is live once this returns
+    pub async fn sse_as(&self, key: &str) -> SseClient {
+        self.sse_with_headers(key, &[]).await
+    }
+
+    /// Reconnect to `/sse` as the admin, as an `EventSource` would after
+    /// receiving `last_event_id`
+    pub async fn sse_resume(&self, last_event_id: &str) -> SseClient {
+        self.sse_with_headers(ADMIN_KEY, &[("last-event-id", last_event_id)]).await
+    }
+
+    pub async fn sse_with_headers(&self, key: &str, headers: &[(&str, &str)]) -> SseClient {
+        self.sse_at(key, "/sse", headers).await
+    }
+
+    /// Open any event stream, e.g. `/sse?types=...` or an account's
+    /// `/api/v1/accounts/:id/events`
+    pub async fn sse_at(&self, key: &str, uri: &str, headers: &[(&str, &str)]) -> SseClient {
+        let mut request = Request::builder()
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", key));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.body(Body::empty()).expect("valid request");
+        let response = self.router.clone().oneshot(request).await.expect("infallible");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        SseClient { stream: response.into_body().into_data_stream(), buffer: String::new() }
+    }
+}
+
+/// Defaults plus an admin key and a private in-memory database
+pub fn test_config() -> Config {
+    let mut config = Config::default();
+    config.database.url = "sqlite::memory:".to_string();
+    // Every pooled connection would otherwise open its own empty database
+    config.database.max_connections = 1;
+    config.database.sqlite.journal_mode = "memory".to_string();
+    config.auth.admin_key = Some(ADMIN_KEY.to_string());
+    // Quick retries so webhook tests do not wait on production backoff
+    config.webhooks.initial_backoff_ms = 50;
+    config.webhooks.poll_interval_ms = 25;
+    config
+}
+
+/// One `event:`/`id:`/`data:` frame from the SSE stream
+#[derive(Debug)]
+pub struct SseEvent {
+    pub event: String,
+    pub id: Option<String>,
+    pub data: Value,
+}
+
+pub struct SseClient {
+    stream: BodyDataStream,
+    buffer: String,
+}
+
+impl SseClient {
+    /// Next event, skipping keep-alive comments; panics after [`SSE_TIMEOUT`]
+    pub async fn next_event(&mut self) -> SseEvent {
+        tokio::time::timeout(SSE_TIMEOUT, self.read_event())
+            .await
+            .expect("timed out waiting for SSE event")
+            .expect("SSE stream ended")
+    }
+
+    /// Assert nothing but keep-alives arrive within `wait`
+    pub async fn assert_silent(&mut self, wait: Duration) {
+        if let Ok(event) = tokio::time::timeout(wait, self.read_event()).await {
+            panic!("unexpected SSE event: {:?}", event);
+        }
+    }
+
+    /// Assert the server ends the stream without sending another event
+    pub async fn assert_closed(&mut self) {
+        let next = tokio::time::timeout(SSE_TIMEOUT, self.read_event())
+            .await
+            .expect("timed out waiting for SSE stream to end");
+        assert!(next.is_none(), "unexpected SSE event: {:?}", next);
+    }
+
+    // `None` once the stream has ended
+    async fn read_event(&mut self) -> Option<SseEvent> {
+        loop {
+            while let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                if let Some(event) = parse_frame(&frame) {
+                    return Some(event);
+                }
+            }
+
+            let chunk = self.stream.next().await?.expect("read SSE chunk");
+            self.buffer.push_str(&String::from_utf8_lossy(&chunk));
+        }
+    }
+}
+
+// Comment-only frames (keep-alives) have no event name and are skipped
+fn parse_frame(frame: &str) -> Option<SseEvent> {
+    let mut event = None;
+    let mut id = None;
+    let mut data = Vec::new();
+    for line in frame.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("id:") {
+            id = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.trim_start().to_string());
+        }
+    }
+
+    let event = event?;
+    let data = serde_json::from_str(&data.join("\n")).expect("SSE data is JSON");
+    Some(SseEvent { event, id, data })
+}
+
+/// A `/ws` connection. Responses and events arrive on the same socket;
+/// [`WsClient::send`] skips events until the command's response arrives.
+pub struct WsClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl WsClient {
+    /// Send a command with `request_id` and wait for its response
+    pub async fn send(&mut self, request_id: &str, mut command: Value) -> Value {
+        command["request_id"] = json!(request_id);
+        self.send_raw(&command.to_string()).await;
+        loop {
+            let message = self.next().await;
+            if message["type"] == "response" {
+                assert_eq!(message["request_id"], request_id);
+                return message;
+            }
+        }
+    }
+
+    pub async fn send_raw(&mut self, text: &str) {
+        self.stream
+            .send(Message::Text(text.to_string()))
+            .await
+            .expect("send WebSocket message");
+    }
+
+    /// Next JSON message, skipping control frames
+    pub async fn next(&mut self) -> Value {
+        let deadline = tokio::time::Instant::now() + SSE_TIMEOUT;
+        loop {
+            let message = tokio::time::timeout_at(deadline, self.stream.next())
+                .await
+                .expect("timed out waiting for a WebSocket message")
+                .expect("WebSocket closed")
+                .expect("WebSocket error");
+            if let Message::Text(text) = message {
+                return serde_json::from_str(&text).expect("WebSocket message is JSON");
+            }
+        }
+    }
+
+    /// Assert nothing but control frames arrives within `wait`
+    pub async fn assert_silent(&mut self, wait: Duration) {
+        let deadline = tokio::time::Instant::now() + wait;
+        while let Ok(message) = tokio::time::timeout_at(deadline, self.stream.next()).await {
+            if let Some(Ok(Message::Text(text))) = message {
+                panic!("unexpected WebSocket message: {}", text);
+            }
+        }
+    }
+}
+
+/// One request received by a [`WebhookReceiver`]
+#[derive(Debug, Clone)]
+pub struct ReceivedHook {
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl ReceivedHook {
+    pub fn header(&self, name: &str) -> &str {
+        self.headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_else(|| panic!("missing {} header", name))
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).expect("webhook body is JSON")
+    }
+}
+
+#[derive(Default)]
+struct ReceiverState {
+    received: Mutex<Vec<ReceivedHook>>,
+    /// Statuses for the next requests, then `fallback`
+    script: Mutex<VecDeque<StatusCode>>,
+    fallback: Mutex<Option<StatusCode>>,
+}
+
+/// A local HTTP server standing in for a webhook endpoint. It records every
+/// POST and answers with scripted statuses, `200` once the script runs out.
+pub struct WebhookReceiver {
+    pub url: String,
+    state: Arc<ReceiverState>,
+}
+
+impl WebhookReceiver {
+    pub async fn start() -> Self {
+        let state = Arc::new(ReceiverState::default());
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind webhook receiver");
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        WebhookReceiver { url, state }
+    }
+
+    /// Answer the next requests with these statuses, in order
+    pub fn respond_with(&self, statuses: &[StatusCode]) {
+        self.state.script.lock().unwrap().extend(statuses);
+    }
+
+    /// Answer every request without a scripted status with `status`
+    pub fn always(&self, status: StatusCode) {
+        *self.state.fallback.lock().unwrap() = Some(status);
+    }
+
+    /// Wait until at least `count` requests arrived and return them all
+    pub async fn wait_for(&self, count: usize) -> Vec<ReceivedHook> {
+        let deadline = tokio::time::Instant::now() + SSE_TIMEOUT;
+        loop {
+            let received = self.received();
+            if received.len() >= count {
+                return received;
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "timed out waiting for {} webhook requests, got {}",
+                count,
+                received.len()
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    pub fn received(&self) -> Vec<ReceivedHook> {
+        self.state.received.lock().unwrap().clone()
+    }
+}
+
+async fn receive(State(state): State<Arc<ReceiverState>>, headers: HeaderMap, body: String) -> StatusCode {
+    state.received.lock().unwrap().push(ReceivedHook { headers, body });
+    let scripted = state.script.lock().unwrap().pop_front();
+    let fallback = *state.fallback.lock().unwrap();
+    scripted.or(fallback).unwrap_or(StatusCode::OK)
+}
@@ -0,0 +1,97 @@
+//! With `events.cross_instance`, instances sharing a database relay each
+//! other's events to their own subscribers, once and in id order.
+
+mod common;
+
+use std::{path::PathBuf, time::Duration};
+
+use betstream::config::Config;
+use common::TestApp;
+
+// A database file both instances open; removed when dropped
+struct SharedDatabase {
+    path: PathBuf,
+}
+
+impl SharedDatabase {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("betstream-{}-{}.db", name, std::process::id()));
+        let database = SharedDatabase { path };
+        database.remove();
+        database
+    }
+
+    fn config(&self) -> Config {
+        let mut config = common::test_config();
+        config.database.url = format!("sqlite://{}?mode=rwc", self.path.display());
+        config.database.max_connections = 5;
+        config.database.sqlite.journal_mode = "wal".to_string();
+        config.events.cross_instance = true;
+        config.events.relay_interval_ms = 20;
+        config
+    }
+
+    fn remove(&self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.path.display(), suffix));
+        }
+    }
+}
+
+impl Drop for SharedDatabase {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+#[tokio::test]
+async fn events_reach_subscribers_of_every_instance() {
+    let database = SharedDatabase::new("relay");
+    let a = TestApp::with_config(database.config()).await;
+    let b = TestApp::with_config(database.config()).await;
+    let mut sse_a = a.sse().await;
+    let mut sse_b = b.sse().await;
+
+    let account = a.create_account("alpha").await;
+    let on_a = sse_a.next_event().await;
+    let on_b = sse_b.next_event().await;
+    assert_eq!(on_b.event, "account_created");
+    assert_eq!(on_b.id, on_a.id);
+    assert_eq!(on_b.data, on_a.data);
+    assert_eq!(on_b.data["account"], account);
+
+    let account_id = account["id"].as_i64().unwrap();
+    b.create_batch(account_id, &[(1, "1")]).await;
+    let on_b = sse_b.next_event().await;
+    let on_a = sse_a.next_event().await;
+    assert_eq!(on_a.event, "batch_created");
+    assert_eq!(on_a.id, on_b.id);
+
+    // Each instance sends an event once, whichever dispatched it
+    sse_a.assert_silent(Duration::from_millis(200)).await;
+    sse_b.assert_silent(Duration::from_millis(200)).await;
+}
+
+#[tokio::test]
+async fn interleaved_writes_arrive_in_id_order() {
+    let database = SharedDatabase::new("order");
+    let a = TestApp::with_config(database.config()).await;
+    let b = TestApp::with_config(database.config()).await;
+    let mut sse_a = a.sse().await;
+    let mut sse_b = b.sse().await;
+
+    for i in 0..5 {
+        let (name_a, name_b) = (format!("a{}", i), format!("b{}", i));
+        tokio::join!(a.create_account(&name_a), b.create_account(&name_b));
+    }
+
+    for sse in [&mut sse_a, &mut sse_b] {
+        let mut ids = Vec::new();
+        for _ in 0..10 {
+            let event = sse.next_event().await;
+            ids.push(event.id.unwrap().parse::<i64>().unwrap());
+        }
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", ids);
+        sse.assert_silent(Duration::from_millis(200)).await;
+    }
+}
@@ -0,0 +1,507 @@
+//! Every `BrokerEvent` reaches `/sse` subscribers, filtered by key scope, and
+//! reconnecting clients replay what they missed via `Last-Event-ID`.
+
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn account_events() {
+    let app = TestApp::new().await;
+    let mut sse = app.sse().await;
+
+    let account = app.create_account("alpha").await;
+    let id = account["id"].as_i64().unwrap();
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "account_created");
+    assert_eq!(event.data["type"], "account_created");
+    assert_eq!(event.data["account"], account);
+
+    app.put(
+        &format!("/api/v1/accounts/{}", id),
+        json!({ "name": "alpha2", "hostname": "alpha2.example.com", "currency": "AUD" }),
+    )
+    .await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "account_updated");
+    assert_eq!(event.data["account"]["name"], "alpha2");
+
+    app.delete(&format!("/api/v1/accounts/{}", id)).await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "account_deleted");
+    assert_eq!(event.data["id"], id);
+}
+
+#[tokio::test]
+async fn batch_and_bet_events() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let mut sse = app.sse().await;
+
+    let batch = app.create_batch(account_id, &[(1, "1"), (2, "2")]).await;
+    let batch_id = batch["id"].as_i64().unwrap();
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+    let other_pid = batch["bets"][1]["pid"].as_i64().unwrap();
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "batch_created");
+    assert_eq!(event.data["batch"], batch);
+
+    let bet_uri = format!("/api/v1/accounts/{}/batches/{}/bets/{}", account_id, batch_id, pid);
+    app.patch(&bet_uri, json!({ "status": "failed" })).await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "bet_status_updated");
+    assert_eq!(event.data["account_id"], account_id);
+    assert_eq!(event.data["bet"]["status"], "failed");
+
+    app.patch(
+        &format!("/api/v1/accounts/{}/batches/{}/bets", account_id, batch_id),
+        json!([{ "pid": other_pid, "status": "successful" }]),
+    )
+    .await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "batch_bets_updated");
+    assert_eq!(event.data["batch_id"], batch_id);
+    assert_eq!(event.data["bets"].as_array().unwrap().len(), 1);
+    assert_eq!(event.data["bets"][0]["pid"], other_pid);
+    assert_eq!(event.data["bets"][0]["status"], "successful");
+
+    app.delete(&format!("/api/v1/accounts/{}/batches/{}", account_id, batch_id))
+        .await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "batch_completed");
+    assert_eq!(event.data["type"], "batch_completed");
+    assert_eq!(event.data["id"], batch_id);
+    assert_eq!(event.data["account_id"], account_id);
+}
+
+#[tokio::test]
+async fn events_carry_an_envelope() {
+    let app = TestApp::new().await;
+    let mut sse = app.sse().await;
+
+    let response = app
+        .request_with_headers(
+            Some(common::ADMIN_KEY),
+            Method::POST,
+            "/api/v1/accounts",
+            Some(json!({ "name": "alpha", "hostname": "alpha.example.com", "currency": "AUD" })),
+            &[("x-request-id", "req-abc")],
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers["x-request-id"], "req-abc");
+    let event = sse.next_event().await;
+    assert_eq!(event.data["type"], "account_created");
+    assert_eq!(event.data["event_id"].to_string(), event.id.unwrap());
+    assert_eq!(event.data["schema_version"], 1);
+    assert!(event.data["emitted_at"].is_string());
+    assert_eq!(event.data["correlation_id"], "req-abc");
+    assert_eq!(event.data["actor"], json!({ "type": "admin" }));
+
+    // Without a client id one is generated and recorded
+    let account_id = response.body["id"].as_i64().unwrap();
+    let key = app.create_key("write", Some(vec![account_id])).await;
+    let key_id = app.get("/api/v1/admin/api-keys").await.body[0]["id"].clone();
+    let response = app
+        .request_as(
+            Some(&key),
+            Method::PUT,
+            &format!("/api/v1/accounts/{}", account_id),
+            Some(json!({ "name": "alpha2", "hostname": "alpha.example.com", "currency": "AUD" })),
+        )
+        .await;
+    let request_id = response.headers["x-request-id"].to_str().unwrap();
+    assert_eq!(request_id.len(), 32);
+    let event = sse.next_event().await;
+    assert_eq!(event.data["type"], "account_updated");
+    assert_eq!(event.data["correlation_id"], request_id);
+    assert_eq!(event.data["actor"]["type"], "api_key");
+    assert_eq!(event.data["actor"]["id"], key_id);
+    assert!(key.starts_with(event.data["actor"]["key_prefix"].as_str().unwrap()));
+}
+
+#[tokio::test]
+async fn failed_requests_emit_nothing() {
+    let app = TestApp::new().await;
+    let mut sse = app.sse().await;
+
+    let response = app.delete("/api/v1/accounts/999").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let response = app.delete("/api/v1/accounts/999/batches/1").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    sse.assert_silent(Duration::from_millis(200)).await;
+}
+
+#[tokio::test]
+async fn subscribers_only_see_their_accounts() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let key = app.create_key("read", Some(vec![alpha])).await;
+    let mut sse = app.sse_as(&key).await;
+
+    app.create_batch(beta, &[(1, "1")]).await;
+    let batch = app.create_batch(alpha, &[(1, "1")]).await;
+
+    // The beta batch was sent first but is filtered out
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "batch_created");
+    assert_eq!(event.data["batch"]["id"], batch["id"]);
+}
+
+#[tokio::test]
+async fn events_carry_increasing_ids() {
+    let app = TestApp::new().await;
+    let mut sse = app.sse().await;
+
+    app.create_account("alpha").await;
+    app.create_account("beta").await;
+    let first: i64 = sse.next_event().await.id.expect("id").parse().unwrap();
+    let second: i64 = sse.next_event().await.id.expect("id").parse().unwrap();
+    assert!(second > first);
+}
+
+#[tokio::test]
+async fn reconnect_replays_missed_events() {
+    let app = TestApp::new().await;
+    let mut sse = app.sse().await;
+    app.create_account("alpha").await;
+    let last_id = sse.next_event().await.id.expect("id");
+    drop(sse);
+
+    // Sent while disconnected
+    app.create_account("beta").await;
+    app.create_account("gamma").await;
+
+    let mut sse = app.sse_resume(&last_id).await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "account_created");
+    assert_eq!(event.data["account"]["name"], "beta");
+    let event = sse.next_event().await;
+    assert_eq!(event.data["account"]["name"], "gamma");
+
+    // Then the live stream, without repeating the replay
+    app.create_account("delta").await;
+    let event = sse.next_event().await;
+    assert_eq!(event.data["account"]["name"], "delta");
+    sse.assert_silent(Duration::from_millis(200)).await;
+
+    // Caught-up clients get no replay
+    let latest = event.id.expect("id");
+    let mut sse = app.sse_resume(&latest).await;
+    sse.assert_silent(Duration::from_millis(200)).await;
+}
+
+#[tokio::test]
+async fn replay_respects_key_scope() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let key = app.create_key("read", Some(vec![alpha])).await;
+    let mut sse = app.sse().await;
+    app.create_batch(alpha, &[(1, "1")]).await;
+    let last_id = sse.next_event().await.id.expect("id");
+
+    app.create_batch(beta, &[(1, "1")]).await;
+    let batch = app.create_batch(alpha, &[(2, "2")]).await;
+
+    let mut sse = app
+        .sse_with_headers(&key, &[("last-event-id", last_id.as_str())])
+        .await;
+    let event = sse.next_event().await;
+    assert_eq!(event.data["batch"]["id"], batch["id"]);
+}
+
+#[tokio::test]
+async fn unknown_event_ids_require_resync() {
+    let app = TestApp::new().await;
+    let mut sse = app.sse().await;
+    app.create_account("alpha").await;
+    let latest = sse.next_event().await.id.expect("id");
+
+    for last_id in ["not-a-number", "-1", "999999"] {
+        let mut sse = app.sse_resume(last_id).await;
+        let event = sse.next_event().await;
+        assert_eq!(event.event, "resync_required");
+        assert_eq!(event.id.as_deref(), Some(latest.as_str()));
+        assert_eq!(event.data["last_event_id"], last_id);
+        assert_eq!(event.data["latest_event_id"].to_string(), latest);
+    }
+}
+
+#[tokio::test]
+async fn clients_too_far_behind_require_resync() {
+    let mut config = common::test_config();
+    config.events.replay_limit = 2;
+    let app = TestApp::with_config(config).await;
+    let mut sse = app.sse().await;
+    app.create_account("alpha").await;
+    let last_id = sse.next_event().await.id.expect("id");
+
+    for name in ["beta", "gamma", "delta"] {
+        app.create_account(name).await;
+    }
+
+    let mut sse = app.sse_resume(&last_id).await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "resync_required");
+
+    // Live events still follow the resync
+    app.create_account("epsilon").await;
+    let event = sse.next_event().await;
+    assert_eq!(event.data["account"]["name"], "epsilon");
+}
+
+#[tokio::test]
+async fn snapshot_mode_starts_with_current_state() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let open = app.create_batch(alpha, &[(1, "1")]).await;
+    let done = app.create_batch(beta, &[(2, "2")]).await;
+    app.delete(&format!("/api/v1/accounts/{}/batches/{}", beta, done["id"]))
+        .await;
+
+    let mut sse = app.sse_at(common::ADMIN_KEY, "/sse?snapshot=true", &[]).await;
+    let snapshot = sse.next_event().await;
+    assert_eq!(snapshot.event, "snapshot");
+    assert_eq!(snapshot.data["type"], "snapshot");
+    assert_eq!(snapshot.data["event_id"].to_string(), snapshot.id.clone().unwrap());
+    assert_eq!(snapshot.data["accounts"].as_array().unwrap().len(), 2);
+    assert_eq!(snapshot.data["batches"], json!([open]));
+
+    // Live events pick up right after the snapshot
+    let batch = app.create_batch(beta, &[(3, "3")]).await;
+    let event = sse.next_event().await;
+    assert_eq!(event.data["batch"], batch);
+    let snapshot_id: i64 = snapshot.id.unwrap().parse().unwrap();
+    assert_eq!(event.data["event_id"], snapshot_id + 1);
+
+    // Scoped keys only see their accounts
+    let key = app.create_key("read", Some(vec![beta])).await;
+    let mut sse = app.sse_at(&key, "/sse?snapshot=true", &[]).await;
+    let snapshot = sse.next_event().await;
+    assert_eq!(snapshot.data["accounts"].as_array().unwrap().len(), 1);
+    assert_eq!(snapshot.data["accounts"][0]["id"], beta);
+    assert_eq!(snapshot.data["batches"], json!([batch]));
+
+    let mut sse = app
+        .sse_at(common::ADMIN_KEY, &format!("/api/v1/accounts/{}/events?snapshot=true", alpha), &[])
+        .await;
+    let snapshot = sse.next_event().await;
+    assert_eq!(snapshot.data["accounts"][0]["id"], alpha);
+    assert_eq!(snapshot.data["batches"], json!([open]));
+}
+
+#[tokio::test]
+async fn snapshot_mode_replays_when_it_can() {
+    let app = TestApp::new().await;
+    let mut sse = app.sse().await;
+    app.create_account("alpha").await;
+    let last_id = sse.next_event().await.id.expect("id");
+    app.create_account("beta").await;
+
+    // A resumable reconnect gets the missed events, not a snapshot
+    let resume = [("last-event-id", last_id.as_str())];
+    let mut sse = app.sse_at(common::ADMIN_KEY, "/sse?snapshot=true", &resume).await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "account_created");
+    assert_eq!(event.data["account"]["name"], "beta");
+
+    // One that would need a resync gets a fresh snapshot instead
+    let mut sse = app
+        .sse_at(common::ADMIN_KEY, "/sse?snapshot=true", &[("last-event-id", "999999")])
+        .await;
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "snapshot");
+    assert_eq!(event.data["accounts"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn sse_filters_by_account_batch_and_type() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let mut by_account = app
+        .sse_at(common::ADMIN_KEY, &format!("/sse?account_id={}", alpha), &[])
+        .await;
+    let mut by_type = app
+        .sse_at(common::ADMIN_KEY, "/sse?types=bet_status_updated,batch_completed", &[])
+        .await;
+
+    app.create_batch(beta, &[(1, "1")]).await;
+    let batch = app.create_batch(alpha, &[(1, "1"), (2, "2")]).await;
+    let batch_id = batch["id"].as_i64().unwrap();
+    let mut by_batch = app
+        .sse_at(common::ADMIN_KEY, &format!("/sse?batch_id={}", batch_id), &[])
+        .await;
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+    app.patch(
+        &format!("/api/v1/accounts/{}/batches/{}/bets/{}", alpha, batch_id, pid),
+        json!({ "status": "successful" }),
+    )
+    .await;
+    app.create_account("gamma").await;
+
+    let event = by_account.next_event().await;
+    assert_eq!(event.event, "batch_created");
+    assert_eq!(event.data["batch"]["id"], batch_id);
+    assert_eq!(by_account.next_event().await.event, "bet_status_updated");
+    by_account.assert_silent(Duration::from_millis(100)).await;
+
+    let event = by_type.next_event().await;
+    assert_eq!(event.event, "bet_status_updated");
+    by_type.assert_silent(Duration::from_millis(100)).await;
+
+    // Account-level events have no batch and are dropped by a batch filter
+    let event = by_batch.next_event().await;
+    assert_eq!(event.event, "bet_status_updated");
+    assert_eq!(event.data["bet"]["batch_id"], batch_id);
+    by_batch.assert_silent(Duration::from_millis(100)).await;
+}
+
+#[tokio::test]
+async fn account_event_route() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let key = app.create_key("read", Some(vec![alpha])).await;
+    let mut sse = app
+        .sse_at(&key, &format!("/api/v1/accounts/{}/events?types=batch_created", alpha), &[])
+        .await;
+
+    app.create_batch(beta, &[(1, "1")]).await;
+    let batch = app.create_batch(alpha, &[(1, "1")]).await;
+    app.delete(&format!("/api/v1/accounts/{}/batches/{}", alpha, batch["id"]))
+        .await;
+
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "batch_created");
+    assert_eq!(event.data["batch"]["id"], batch["id"]);
+    sse.assert_silent(Duration::from_millis(100)).await;
+
+    let response = app
+        .request_as(Some(&key), Method::GET, &format!("/api/v1/accounts/{}/events", beta), None)
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    let response = app
+        .request_as(Some(&key), Method::GET, &format!("/sse?account_id={}", beta), None)
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    let response = app.get("/api/v1/accounts/999/events").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let response = app.get("/sse?types=bet_status_updated,bogus").await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.body["detail"].as_str().unwrap().contains("bogus"));
+}
+
+fn small_channel(max_lags: u64) -> betstream::config::Config {
+    let mut config = common::test_config();
+    config.events.channel_capacity = 1;
+    config.events.max_lags = max_lags;
+    config
+}
+
+#[tokio::test]
+async fn lag_is_reported_to_the_subscriber() {
+    let app = TestApp::with_config(small_channel(0)).await;
+    let mut sse = app.sse().await;
+
+    // Nothing is read while three events go out through a one-slot channel
+    for name in ["alpha", "beta", "gamma"] {
+        app.create_account(name).await;
+    }
+
+    let event = sse.next_event().await;
+    assert_eq!(event.event, "lagged");
+    assert_eq!(event.id, None);
+    assert_eq!(
+        event.data,
+        json!({ "type": "lagged", "skipped": 2, "lag_count": 1, "disconnecting": false })
+    );
+    let event = sse.next_event().await;
+    assert_eq!(event.data["account"]["name"], "gamma");
+
+    let report = app.get("/api/v1/admin/sse-connections").await.body;
+    assert_eq!(report["connections"].as_array().unwrap().len(), 1);
+    assert_eq!(report["connections"][0]["subscriber"], "admin");
+    assert_eq!(report["connections"][0]["lag_count"], 1);
+    assert_eq!(report["connections"][0]["skipped_events"], 2);
+    assert_eq!(report["total_skipped_events"], 2);
+
+    drop(sse);
+    let report = app.get("/api/v1/admin/sse-connections").await.body;
+    assert_eq!(report["connections"], json!([]));
+    assert_eq!(report["total_skipped_events"], 2);
+}
+
+#[tokio::test]
+async fn chronically_slow_subscribers_are_disconnected() {
+    let app = TestApp::with_config(small_channel(2)).await;
+    let mut sse = app.sse().await;
+
+    for round in 0..2 {
+        for n in 0..3 {
+            app.create_account(&format!("account-{}-{}", round, n)).await;
+        }
+        let event = sse.next_event().await;
+        assert_eq!(event.event, "lagged");
+        assert_eq!(event.data["lag_count"], round + 1);
+        if round == 0 {
+            sse.next_event().await;
+        } else {
+            assert_eq!(event.data["disconnecting"], true);
+        }
+    }
+    sse.assert_closed().await;
+
+    let report = app.get("/api/v1/admin/sse-connections").await.body;
+    assert_eq!(report["disconnected_for_lag"], 1);
+}
+
+#[tokio::test]
+async fn connection_report_is_admin_only() {
+    let app = TestApp::new().await;
+    let key = app.create_key("read", None).await;
+
+    let response = app
+        .request_as(Some(&key), Method::GET, "/api/v1/admin/sse-connections", None)
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn events_are_written_to_the_jsonl_sink() {
+    let path = std::env::temp_dir().join(format!("betstream-events-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut config = common::test_config();
+    config.sinks.jsonl.enabled = true;
+    config.sinks.jsonl.path = path.to_string_lossy().into_owned();
+    let app = TestApp::with_config(config).await;
+    let mut sse = app.sse().await;
+
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    app.create_batch(account_id, &[(1, "1")]).await;
+    let created = sse.next_event().await;
+    let batch = sse.next_event().await;
+
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1]["dispatched_at"].is_string());
+    let mut first = lines[0].clone();
+    first.as_object_mut().unwrap().remove("dispatched_at");
+    assert_eq!(first, created.data);
+    assert_eq!(lines[1]["event_id"].to_string(), batch.id.unwrap());
+    assert_eq!(lines[1]["type"], "batch_created");
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use common::{TestApp, TestResponse, ADMIN_KEY};
+use serde_json::{json, Value};
+
+async fn with_key(app: &TestApp, method: Method, uri: &str, key: &str, body: Value) -> TestResponse {
+    app.request_with_headers(Some(ADMIN_KEY), method, uri, Some(body), &[("Idempotency-Key", key)])
+        .await
+}
+
+fn batch_body(selection: &str) -> Value {
+    json!({ "meta": { "race_id": 1, "bet_type": "WIN" }, "bets": [{ "id": 1, "selection": selection, "stake": 5.0, "cost": 5.0 }] })
+}
+
+#[tokio::test]
+async fn retried_batch_creation_is_replayed() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches", account_id);
+    let mut sse = app.sse().await;
+
+    let first = with_key(&app, Method::POST, &uri, "retry-1", batch_body("1")).await;
+    assert_eq!(first.status, StatusCode::OK);
+    assert!(first.headers.get("idempotent-replayed").is_none());
+
+    let retry = with_key(&app, Method::POST, &uri, "retry-1", batch_body("1")).await;
+    assert_eq!(retry.status, StatusCode::OK);
+    assert_eq!(retry.headers["idempotent-replayed"], "true");
+    assert_eq!(retry.headers["content-type"], "application/json");
+    assert_eq!(retry.body, first.body);
+
+    let listed = app.get(&uri).await;
+    assert_eq!(listed.body["items"].as_array().unwrap().len(), 1);
+
+    // Only the first request emits an event
+    assert_eq!(sse.next_event().await.event, "batch_created");
+    sse.assert_silent(Duration::from_millis(200)).await;
+}
+
+#[tokio::test]
+async fn reused_key_with_different_body_conflicts() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches", account_id);
+
+    with_key(&app, Method::POST, &uri, "k", batch_body("1")).await;
+    let conflict = with_key(&app, Method::POST, &uri, "k", batch_body("2")).await;
+    assert_eq!(conflict.status, StatusCode::CONFLICT);
+    assert_eq!(conflict.body["code"], "conflict");
+
+    // Same key on another account is independent
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let other = with_key(
+        &app,
+        Method::POST,
+        &format!("/api/v1/accounts/{}/batches", beta),
+        "k",
+        batch_body("2"),
+    )
+    .await;
+    assert_eq!(other.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn failed_requests_release_the_key() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "1")]).await;
+    let batch_id = batch["id"].as_i64().unwrap();
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+
+    let missing = format!("/api/v1/accounts/{}/batches/{}/bets/999", account_id, batch_id);
+    let failed = with_key(&app, Method::PATCH, &missing, "k", json!({ "status": "failed" })).await;
+    assert_eq!(failed.status, StatusCode::NOT_FOUND);
+
+    let uri = format!("/api/v1/accounts/{}/batches/{}/bets/{}", account_id, batch_id, pid);
+    let ok = with_key(&app, Method::PATCH, &uri, "k", json!({ "status": "failed" })).await;
+    assert_eq!(ok.status, StatusCode::OK);
+    assert!(ok.headers.get("idempotent-replayed").is_none());
+}
+
+#[tokio::test]
+async fn requests_dropped_midway_can_be_retried() {
+    let mut config = common::test_config();
+    config.idempotency.lease_secs = 1;
+    let app = TestApp::with_config(config).await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches", account_id);
+
+    // Drop the request after one poll, then two, and so on until it finishes
+    // unpolled, as a client that disconnects or times out would
+    for polls in 1.. {
+        assert!(polls <= 200, "request never finished");
+        let key = format!("dropped-{}", polls);
+        let mut finished = false;
+        {
+            let mut request = Box::pin(with_key(&app, Method::POST, &uri, &key, batch_body("1")));
+            for _ in 0..polls {
+                if futures::poll!(request.as_mut()).is_ready() {
+                    finished = true;
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+        }
+
+        // The retry gets the response of the request if it ran, or runs it
+        // once the dropped claim's lease is up; either way one batch exists
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        let retry = loop {
+            let retry = with_key(&app, Method::POST, &uri, &key, batch_body("1")).await;
+            if retry.status != StatusCode::CONFLICT || tokio::time::Instant::now() > deadline {
+                break retry;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        };
+        assert_eq!(retry.status, StatusCode::OK, "after {} polls: {}", polls, retry.body);
+        let listed = app.get(&format!("{}?limit=100", uri)).await;
+        assert_eq!(listed.body["items"].as_array().unwrap().len(), polls, "after {} polls", polls);
+
+        if finished {
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+async fn bulk_bet_updates_are_replayed() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "1")]).await;
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches/{}/bets", account_id, batch["id"]);
+
+    let body = json!([{ "pid": pid, "status": "successful" }]);
+
+    let first = with_key(&app, Method::PATCH, &uri, "bulk", body.clone()).await;
+    let retry = with_key(&app, Method::PATCH, &uri, "bulk", body).await;
+    assert_eq!(retry.headers["idempotent-replayed"], "true");
+    assert_eq!(retry.body, first.body);
+}
+
+#[tokio::test]
+async fn invalid_or_unauthorized_keys_are_rejected() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let uri = format!("/api/v1/accounts/{}/batches", alpha);
+
+    let too_long = "x".repeat(256);
+    let invalid = with_key(&app, Method::POST, &uri, &too_long, batch_body("1")).await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+
+    // A read key cannot replay another client's stored response
+    with_key(&app, Method::POST, &uri, "k", batch_body("1")).await;
+    let reader = app.create_key("read", Some(vec![alpha])).await;
+    let replay = app
+        .request_with_headers(Some(&reader), Method::POST, &uri, Some(batch_body("1")), &[("Idempotency-Key", "k")])
+        .await;
+    assert_eq!(replay.status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,148 @@
+//! Race results and the settlement of bets on them.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn results_settle_successful_bets() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "3"), (2, "1"), (3, "7")]).await;
+    let bets_uri = format!("/api/v1/accounts/{}/batches/{}/bets", account_id, batch["id"]);
+    let response = app
+        .patch(
+            &bets_uri,
+            json!([
+                { "pid": batch["bets"][0]["pid"], "status": "successful" },
+                { "pid": batch["bets"][1]["pid"], "status": "successful" },
+                { "pid": batch["bets"][2]["pid"], "status": "failed" }
+            ]),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+    let mut events = app.sse_at(common::ADMIN_KEY, "/sse?types=bet_settled,batch_settled", &[]).await;
+    assert_eq!(app.get("/api/v1/races/1/result").await.status, StatusCode::NOT_FOUND);
+
+    // Runner 3 won and has a successful WIN bet, so it needs a dividend
+    let unpaid = app.put("/api/v1/races/1/result", json!({ "placings": [3, 1, 7], "dividends": [] })).await;
+    assert_eq!(unpaid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(unpaid.body["errors"][0]["pointer"], "/dividends");
+    assert_eq!(unpaid.body["errors"][0]["detail"], "WIN 3 won on placings 3/1/7 and has bets, but no dividend");
+
+    let result = json!({
+        "placings": [3, 1, 7],
+        "dividends": [{ "bet_type": "WIN", "selection": "3", "dividend": "4.20" }]
+    });
+    let key = app.create_key("write", None).await;
+    let forbidden = app.request_as(Some(&key), Method::PUT, "/api/v1/races/1/result", Some(result.clone())).await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+
+    let response = app.put("/api/v1/races/1/result", result).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(response.body["settled"], 2);
+    assert_eq!(response.body["won"], 1);
+    assert_eq!(response.body["result"]["placings"], json!([3, 1, 7]));
+
+    let bets = app.get(&format!("/api/v1/accounts/{}/batches/{}", account_id, batch["id"])).await.body["bets"].clone();
+    assert_eq!(bets[0]["result"], "won");
+    assert_eq!(bets[0]["payout"], "42.00");
+    assert_eq!(bets[1]["result"], "lost");
+    assert_eq!(bets[1]["payout"], "0.00");
+    assert!(bets[2]["result"].is_null());
+
+    for pid in [&bets[0]["pid"], &bets[1]["pid"]] {
+        let event = events.next_event().await;
+        assert_eq!(event.event, "bet_settled");
+        assert_eq!(event.data["race_id"], 1);
+        assert_eq!(&event.data["bet"]["pid"], pid);
+    }
+    let event = events.next_event().await;
+    assert_eq!(event.event, "batch_settled");
+    assert_eq!(event.data["batch_id"], batch["id"]);
+    assert_eq!(event.data["settled"], 2);
+    assert_eq!(event.data["stake"], "20.00");
+    assert_eq!(event.data["payout"], "42.00");
+    assert_eq!(event.data["currency"], "AUD");
+
+    let stored = app.get("/api/v1/races/1/result").await;
+    assert_eq!(stored.status, StatusCode::OK);
+    assert_eq!(stored.body["dividends"][0]["dividend"], "4.20");
+
+    // Results are global: a key scoped to another account reads them too
+    let other = app.create_account("beta").await["id"].as_i64().unwrap();
+    let scoped = app.create_key("read", Some(vec![other])).await;
+    let response = app.request_as(Some(&scoped), Method::GET, "/api/v1/races/1/result", None).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, stored.body);
+    let anonymous = app.request_as(None, Method::GET, "/api/v1/races/1/result", None).await;
+    assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn results_cannot_pay_a_batch_more_than_the_cap() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let bet = |id: i64, selection: &str| json!({ "id": id, "selection": selection, "stake": "1000000", "cost": "1" });
+    let batch = app
+        .post(
+            &format!("/api/v1/accounts/{}/batches", account_id),
+            json!({ "meta": { "race_id": 1, "bet_type": "WIN" }, "bets": [bet(1, "3"), bet(2, "1")] }),
+        )
+        .await
+        .body;
+    let statuses: Vec<_> = batch["bets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| json!({ "pid": b["pid"], "status": "successful" }))
+        .collect();
+    let bets_uri = format!("/api/v1/accounts/{}/batches/{}/bets", account_id, batch["id"]);
+    assert_eq!(app.patch(&bets_uri, json!(statuses)).await.status, StatusCode::OK);
+    let mut events = app.sse_at(common::ADMIN_KEY, "/sse?types=bet_settled,batch_settled", &[]).await;
+    let result = |dividend: &str| {
+        json!({ "placings": [3, 1, 7], "dividends": [{ "bet_type": "WIN", "selection": "3", "dividend": dividend }] })
+    };
+
+    // 1,000,000.00 at 1000.01 pays 1,000,010,000.00, over the cap
+    let response = app.put("/api/v1/races/1/result", result("1000.01")).await;
+    assert_eq!(response.status, StatusCode::CONFLICT, "{}", response.body);
+    assert_eq!(app.get("/api/v1/races/1/result").await.status, StatusCode::NOT_FOUND);
+    let stored = app.get(&format!("/api/v1/accounts/{}/batches/{}", account_id, batch["id"])).await.body;
+    assert!(stored["bets"][0]["result"].is_null());
+
+    let response = app.put("/api/v1/races/1/result", result("1000.00")).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(events.next_event().await.data["bet"]["payout"], "1000000000.00");
+    assert_eq!(events.next_event().await.event, "bet_settled");
+    let event = events.next_event().await;
+    assert_eq!(event.event, "batch_settled");
+    assert_eq!(event.data["payout"], "1000000000.00");
+}
+
+#[tokio::test]
+async fn dividends_must_win_on_the_placings() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .put(
+            "/api/v1/races/1/result",
+            json!({
+                "placings": [3, 1, 7],
+                "dividends": [
+                    { "bet_type": "WIN", "selection": "1", "dividend": "4.20" },
+                    { "bet_type": "EXACTA", "selection": "3", "dividend": "15.00" }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.body["code"], "validation_failed");
+    assert_eq!(response.body["errors"][0]["pointer"], "/dividends/0/selection");
+    assert_eq!(response.body["errors"][0]["detail"], "WIN 1 did not win on placings 3/1/7");
+    assert_eq!(response.body["errors"][1]["detail"], "EXACTA takes 2 runners, got 1");
+    assert_eq!(app.get("/api/v1/races/1/result").await.status, StatusCode::NOT_FOUND);
+}
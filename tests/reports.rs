@@ -0,0 +1,112 @@
+//! Account currencies, the FX rate table and totals reported in one base
+//! currency.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+async fn create_account_in(app: &TestApp, name: &str, currency: &str) -> i64 {
+    let response = app
+        .post("/api/v1/accounts", json!({ "name": name, "hostname": "h", "currency": currency }))
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(response.body["currency"], currency);
+    response.body["id"].as_i64().unwrap()
+}
+
+#[tokio::test]
+async fn accounts_require_a_supported_currency() {
+    let app = TestApp::new().await;
+
+    let missing = app.post("/api/v1/accounts", json!({ "name": "alpha", "hostname": "h" })).await;
+    assert_eq!(missing.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let unknown = app
+        .post("/api/v1/accounts", json!({ "name": "alpha", "hostname": "h", "currency": "XYZ" }))
+        .await;
+    assert_eq!(unknown.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Batches and bets carry the account's currency, with totals in it
+    let account_id = create_account_in(&app, "alpha", "NZD").await;
+    let batch = app.create_batch(account_id, &[(1, "1"), (2, "2")]).await;
+    assert_eq!(batch["currency"], "NZD");
+    assert_eq!(batch["bets"][0]["currency"], "NZD");
+    assert_eq!(batch["total_stake"], "20.00");
+    assert_eq!(batch["total_cost"], "20.00");
+}
+
+#[tokio::test]
+async fn fx_rates_are_admin_managed() {
+    let app = TestApp::new().await;
+
+    let response = app.put("/api/v1/admin/fx-rates/NZD/AUD", json!({ "rate": "0.9215" })).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(response.body["from"], "NZD");
+    assert_eq!(response.body["to"], "AUD");
+    assert_eq!(response.body["rate"], "0.921500");
+
+    let listed = app.get("/api/v1/admin/fx-rates").await;
+    assert_eq!(listed.body.as_array().unwrap().len(), 1);
+
+    let same = app.put("/api/v1/admin/fx-rates/AUD/AUD", json!({ "rate": "1" })).await;
+    assert_eq!(same.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let zero = app.put("/api/v1/admin/fx-rates/AUD/NZD", json!({ "rate": "0" })).await;
+    assert_eq!(zero.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let unknown = app.put("/api/v1/admin/fx-rates/XYZ/AUD", json!({ "rate": "1" })).await;
+    assert_eq!(unknown.status, StatusCode::BAD_REQUEST);
+
+    let key = app.create_key("write", None).await;
+    let forbidden = app
+        .request_as(Some(&key), Method::PUT, "/api/v1/admin/fx-rates/AUD/NZD", Some(json!({ "rate": "1.08" })))
+        .await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+
+    assert_eq!(app.delete("/api/v1/admin/fx-rates/NZD/AUD").await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.delete("/api/v1/admin/fx-rates/NZD/AUD").await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn totals_are_converted_into_the_base_currency() {
+    let app = TestApp::new().await;
+    let aud = create_account_in(&app, "aud", "AUD").await;
+    let nzd = create_account_in(&app, "nzd", "NZD").await;
+    app.create_batch(aud, &[(1, "1")]).await;
+    app.create_batch(nzd, &[(1, "1"), (2, "2")]).await;
+
+    // NZD bets cannot be reported in AUD without a rate
+    let response = app.get("/api/v1/reports/totals?currency=AUD").await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    app.put("/api/v1/admin/fx-rates/NZD/AUD", json!({ "rate": "0.9215" })).await;
+    let report = app.get("/api/v1/reports/totals?currency=AUD").await;
+    assert_eq!(report.status, StatusCode::OK, "{}", report.body);
+    assert_eq!(report.body["currency"], "AUD");
+    assert_eq!(report.body["bets"], 3);
+    // 10.00 AUD plus 20.00 NZD at 0.9215
+    assert_eq!(report.body["stake"], "28.43");
+    assert_eq!(report.body["by_currency"][1]["currency"], "NZD");
+    assert_eq!(report.body["by_currency"][1]["stake"], "20.00");
+    assert_eq!(report.body["by_currency"][1]["converted_stake"], "18.43");
+
+    let only_aud = app.get(&format!("/api/v1/reports/totals?currency=AUD&account_id={}", aud)).await;
+    assert_eq!(only_aud.body["stake"], "10.00");
+    let nothing_yet = app.get("/api/v1/reports/totals?currency=AUD&status=successful").await;
+    assert_eq!(nothing_yet.body["bets"], 0);
+    assert_eq!(nothing_yet.body["stake"], "0.00");
+
+    // Scoped keys only see their own accounts
+    let key = app.create_key("read", Some(vec![aud])).await;
+    let scoped = app
+        .request_as(Some(&key), Method::GET, "/api/v1/reports/totals?currency=NZD", None)
+        .await;
+    assert_eq!(scoped.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let scoped = app
+        .request_as(Some(&key), Method::GET, "/api/v1/reports/totals?currency=AUD", None)
+        .await;
+    assert_eq!(scoped.body["bets"], 1);
+    let other = app
+        .request_as(Some(&key), Method::GET, &format!("/api/v1/reports/totals?currency=AUD&account_id={}", nzd), None)
+        .await;
+    assert_eq!(other.status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,169 @@
+//! Webhook subscriptions and the delivery worker, against a local receiver.
+
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use betstream::webhooks::sign;
+use common::{TestApp, WebhookReceiver};
+use serde_json::{json, Value};
+
+async fn create_webhook(app: &TestApp, body: Value) -> Value {
+    let response = app.post("/api/v1/admin/webhooks", body).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.body);
+    response.body
+}
+
+// Poll the delivery history until its newest delivery reaches `status`
+async fn wait_for_delivery(app: &TestApp, webhook_id: i64, status: &str) -> Value {
+    for _ in 0..200 {
+        let page = app
+            .get(&format!("/api/v1/admin/webhooks/{}/deliveries", webhook_id))
+            .await
+            .body;
+        if page["items"][0]["status"] == status {
+            return page["items"][0].clone();
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("delivery for webhook {} never became {}", webhook_id, status);
+}
+
+#[tokio::test]
+async fn webhook_crud_and_validation() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+
+    let created = create_webhook(
+        &app,
+        json!({
+            "url": "https://hooks.example.com/betstream",
+            "event_types": ["bet_status_updated", "batch_created", "batch_created"],
+            "account_id": account_id
+        }),
+    )
+    .await;
+    assert!(created["secret"].as_str().unwrap().starts_with("whsec_"));
+    assert_eq!(created["event_types"], json!(["batch_created", "bet_status_updated"]));
+    assert_eq!(created["account_id"], account_id);
+
+    let list = app.get("/api/v1/admin/webhooks").await;
+    assert_eq!(list.status, StatusCode::OK);
+    assert_eq!(list.body.as_array().unwrap().len(), 1);
+    assert!(list.body[0].get("secret").is_none());
+
+    for body in [
+        json!({ "url": "not a url" }),
+        json!({ "url": "ftp://hooks.example.com" }),
+        json!({ "url": "https://hooks.example.com", "event_types": [] }),
+        json!({ "url": "https://hooks.example.com", "event_types": ["bogus"] }),
+        json!({ "url": "https://hooks.example.com", "account_id": 999 }),
+    ] {
+        let response = app.post("/api/v1/admin/webhooks", body.clone()).await;
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    }
+
+    let key = app.create_key("write", None).await;
+    let response = app
+        .request_as(Some(&key), Method::GET, "/api/v1/admin/webhooks", None)
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let uri = format!("/api/v1/admin/webhooks/{}", created["id"]);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NOT_FOUND);
+    let response = app.get(&format!("{}/deliveries", uri)).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn deliveries_are_signed_and_filtered() {
+    let app = TestApp::new().await;
+    let receiver = WebhookReceiver::start().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let webhook = create_webhook(
+        &app,
+        json!({ "url": receiver.url, "event_types": ["batch_created"], "account_id": alpha }),
+    )
+    .await;
+
+    app.create_batch(beta, &[(1, "1")]).await;
+    app.create_account("gamma").await;
+    let batch = app.create_batch(alpha, &[(1, "1")]).await;
+
+    let received = receiver.wait_for(1).await;
+    let hook = &received[0];
+    assert_eq!(hook.header("content-type"), "application/json");
+    assert_eq!(hook.header("x-betstream-event"), "batch_created");
+    let body = hook.json();
+    assert_eq!(body["type"], "batch_created");
+    assert_eq!(body["batch"], batch);
+    assert_eq!(body["schema_version"], 1);
+    assert_eq!(body["actor"], json!({ "type": "admin" }));
+
+    let timestamp: i64 = hook.header("x-betstream-timestamp").parse().unwrap();
+    let expected = sign(webhook["secret"].as_str().unwrap(), timestamp, &hook.body);
+    assert_eq!(hook.header("x-betstream-signature"), expected);
+
+    let delivery = wait_for_delivery(&app, webhook["id"].as_i64().unwrap(), "delivered").await;
+    assert_eq!(delivery["attempts"], 1);
+    assert_eq!(delivery["last_status_code"], 200);
+    assert_eq!(delivery["next_attempt_at"], Value::Null);
+    assert_eq!(delivery["id"].to_string(), hook.header("x-betstream-delivery"));
+
+    // The other events were filtered out
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(receiver.received().len(), 1);
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried() {
+    let app = TestApp::new().await;
+    let receiver = WebhookReceiver::start().await;
+    receiver.respond_with(&[StatusCode::INTERNAL_SERVER_ERROR, StatusCode::SERVICE_UNAVAILABLE]);
+    let webhook = create_webhook(&app, json!({ "url": receiver.url })).await;
+
+    app.create_account("alpha").await;
+
+    let received = receiver.wait_for(3).await;
+    let delivery_id = received[0].header("x-betstream-delivery");
+    assert!(received.iter().all(|hook| hook.header("x-betstream-delivery") == delivery_id));
+    assert!(received.iter().all(|hook| hook.body == received[0].body));
+
+    let delivery = wait_for_delivery(&app, webhook["id"].as_i64().unwrap(), "delivered").await;
+    assert_eq!(delivery["attempts"], 3);
+    assert_eq!(delivery["last_status_code"], 200);
+}
+
+#[tokio::test]
+async fn deliveries_are_dead_lettered_after_max_attempts() {
+    let mut config = common::test_config();
+    config.webhooks.max_attempts = 2;
+    let app = TestApp::with_config(config).await;
+    let receiver = WebhookReceiver::start().await;
+    receiver.always(StatusCode::INTERNAL_SERVER_ERROR);
+    let webhook = create_webhook(&app, json!({ "url": receiver.url })).await;
+    let webhook_id = webhook["id"].as_i64().unwrap();
+
+    app.create_account("alpha").await;
+
+    let delivery = wait_for_delivery(&app, webhook_id, "dead").await;
+    assert_eq!(delivery["attempts"], 2);
+    assert_eq!(delivery["last_status_code"], 500);
+    assert!(delivery["last_error"].as_str().unwrap().starts_with("HTTP 500"));
+    assert_eq!(delivery["event_type"], "account_created");
+
+    // No further attempts once dead
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(receiver.received().len(), 2);
+
+    let uri = format!("/api/v1/admin/webhooks/{}/deliveries", webhook_id);
+    let dead = app.get(&format!("{}?status=dead", uri)).await.body;
+    assert_eq!(dead["items"].as_array().unwrap().len(), 1);
+    let delivered = app.get(&format!("{}?status=delivered", uri)).await.body;
+    assert_eq!(delivered["items"], json!([]));
+    let response = app.get(&format!("{}?status=bogus", uri)).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,154 @@
+//! `/ws` streams the same events as `/sse` and runs executor commands on the
+//! same socket, each answered with a response carrying its `request_id`.
+
+mod common;
+
+use std::time::Duration;
+
+use common::{TestApp, ADMIN_KEY};
+use serde_json::json;
+
+#[tokio::test]
+async fn commands_are_answered_and_emit_events() {
+    let app = TestApp::new().await;
+    let account_id = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(account_id, &[(1, "1")]).await;
+    let batch_id = batch["id"].as_i64().unwrap();
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+    let mut ws = app.ws_as(ADMIN_KEY).await;
+
+    let response = ws.send("sub-1", json!({ "command": "subscribe", "account_id": account_id })).await;
+    assert_eq!(response["ok"], true);
+    assert_eq!(response["result"]["subscription_id"], 1);
+
+    let response = ws
+        .send(
+            "bet-1",
+            json!({
+                "command": "update_bet_status",
+                "account_id": account_id,
+                "batch_id": batch_id,
+                "pid": pid,
+                "status": "successful",
+                "reference": "TKT-1",
+            }),
+        )
+        .await;
+    assert_eq!(response["ok"], true);
+    assert_eq!(response["result"]["status"], "successful");
+    assert_eq!(response["result"]["reference"], "TKT-1");
+
+    // The event follows its response, correlated by the request id
+    let event = ws.next().await;
+    assert_eq!(event["type"], "bet_status_updated");
+    assert_eq!(event["bet"]["pid"], pid);
+    assert_eq!(event["correlation_id"], "bet-1");
+    assert_eq!(event["actor"], json!({ "type": "admin" }));
+
+    let response = ws
+        .send("done-1", json!({ "command": "complete_batch", "account_id": account_id, "batch_id": batch_id }))
+        .await;
+    assert_eq!(response["ok"], true);
+    let event = ws.next().await;
+    assert_eq!(event["type"], "batch_completed");
+    assert_eq!(event["correlation_id"], "done-1");
+
+    let response = ws.send("hb-1", json!({ "command": "heartbeat" })).await;
+    assert_eq!(response["ok"], true);
+    assert!(response["result"]["server_time"].is_string());
+
+    // Changes made elsewhere arrive too
+    app.create_batch(account_id, &[(2, "2")]).await;
+    assert_eq!(ws.next().await["type"], "batch_created");
+}
+
+#[tokio::test]
+async fn subscriptions_select_events() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let mut ws = app.ws_as(ADMIN_KEY).await;
+
+    // Nothing is sent before the first subscription
+    app.create_batch(alpha, &[(1, "1")]).await;
+    ws.assert_silent(Duration::from_millis(200)).await;
+
+    let response = ws.send("s1", json!({ "command": "subscribe", "types": "batch_created" })).await;
+    let batches = response["result"]["subscription_id"].as_i64().unwrap();
+    let response = ws.send("s2", json!({ "command": "subscribe", "account_id": alpha })).await;
+    let alpha_events = response["result"]["subscription_id"].as_i64().unwrap();
+
+    // Matching both subscriptions still sends the event once
+    app.create_batch(alpha, &[(2, "2")]).await;
+    assert_eq!(ws.next().await["type"], "batch_created");
+    app.create_account("beta").await;
+    ws.assert_silent(Duration::from_millis(200)).await;
+
+    let response = ws.send("u1", json!({ "command": "unsubscribe", "subscription_id": alpha_events })).await;
+    assert_eq!(response["ok"], true);
+    let response = ws.send("u2", json!({ "command": "unsubscribe", "subscription_id": batches })).await;
+    assert_eq!(response["ok"], true);
+    app.create_batch(alpha, &[(3, "3")]).await;
+    ws.assert_silent(Duration::from_millis(200)).await;
+
+    let response = ws.send("u3", json!({ "command": "unsubscribe", "subscription_id": batches })).await;
+    assert_eq!(response["ok"], false);
+    assert_eq!(response["error"]["status"], 404);
+}
+
+#[tokio::test]
+async fn failed_commands_get_problem_details() {
+    let app = TestApp::new().await;
+    let alpha = app.create_account("alpha").await["id"].as_i64().unwrap();
+    let beta = app.create_account("beta").await["id"].as_i64().unwrap();
+    let batch = app.create_batch(alpha, &[(1, "1")]).await;
+    let pid = batch["bets"][0]["pid"].as_i64().unwrap();
+    let update = |status: &str| {
+        json!({
+            "command": "update_bet_status",
+            "account_id": alpha,
+            "batch_id": batch["id"],
+            "pid": pid,
+            "status": status,
+        })
+    };
+
+    let mut ws = app.ws_as(ADMIN_KEY).await;
+    ws.send_raw("not json").await;
+    let response = ws.next().await;
+    assert_eq!(response["type"], "response");
+    assert_eq!(response["request_id"], json!(null));
+    assert_eq!(response["error"]["status"], 400);
+
+    let response = ws.send("r1", json!({ "command": "launch" })).await;
+    assert_eq!(response["ok"], false);
+    assert_eq!(response["error"]["status"], 400);
+
+    let response = ws.send("r2", json!({ "command": "subscribe", "types": "nope" })).await;
+    assert_eq!(response["error"]["status"], 400);
+
+    // The REST transition rules apply
+    assert_eq!(ws.send("r3", update("successful")).await["ok"], true);
+    let response = ws.send("r4", update("pending")).await;
+    assert_eq!(response["error"]["status"], 409);
+    assert_eq!(response["error"]["code"], "conflict");
+
+    // And so do key scopes
+    let key = app.create_key("read", Some(vec![alpha])).await;
+    let mut ws = app.ws_as(&key).await;
+    let response = ws.send("r5", update("failed")).await;
+    assert_eq!(response["error"]["status"], 403);
+    let response = ws.send("r6", json!({ "command": "subscribe", "account_id": beta })).await;
+    assert_eq!(response["error"]["status"], 403);
+}
+
+#[tokio::test]
+async fn handshake_requires_a_key() {
+    let app = TestApp::new().await;
+    let url = format!("ws://{}/ws", app.serve().await);
+
+    let err = tokio_tungstenite::connect_async(url).await.unwrap_err();
+    match err {
+        tokio_tungstenite::tungstenite::Error::Http(response) => assert_eq!(response.status(), 401),
+        other => panic!("unexpected error: {}", other),
+    }
+}